@@ -58,6 +58,9 @@ pub mod poly;
 /// This module describes traits for conditionally selecting a variable from a
 /// list of variables.
 pub mod select;
+/// This module implements the Poseidon permutation over any field that has a
+/// `FieldVar`, including the quadratic extension used by zkFOAKS.
+pub mod poseidon;
 
 #[allow(missing_docs)]
 pub mod prelude {
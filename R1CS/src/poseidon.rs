@@ -0,0 +1,215 @@
+use crate::fields::{FieldOpsBounds, FieldVar};
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_relations::r1cs::SynthesisError;
+use ark_std::{vec, vec::Vec};
+
+/// Parameters of a Poseidon permutation over `F`.
+///
+/// [`PoseidonParameters::new`] derives the round constants from the Grain
+/// LFSR of the Poseidon reference implementation, sampling every coefficient
+/// of an extension field element over `F::BasePrimeField` by rejection,
+/// lowest coefficient first. The MDS matrix is the Cauchy matrix
+/// `1 / (i + j + width)`. With `F` the quadratic extension of `2^61 - 1`,
+/// `width = 6`, 8 full and 30 partial rounds and `alpha = 17` this is the
+/// permutation of `infrastructure::poseidon` in zkFOAKS.
+#[derive(Clone, Debug)]
+pub struct PoseidonParameters<F: Field> {
+    /// Number of full rounds, half of them before the partial rounds.
+    pub full_rounds: usize,
+    /// Number of partial rounds, applying the S-box to the first element only.
+    pub partial_rounds: usize,
+    /// Exponent of the S-box `x^alpha`.
+    pub alpha: u64,
+    /// Constants added to the state at the start of every round.
+    pub round_constants: Vec<Vec<F>>,
+    /// Matrix mixing the state at the end of every round.
+    pub mds: Vec<Vec<F>>,
+}
+
+/// Grain LFSR in self-shrinking mode.
+struct GrainLfsr {
+    state: [bool; 80],
+    head: usize,
+}
+
+impl GrainLfsr {
+    fn new(field_bits: usize, width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        let mut state = [false; 80];
+        let mut pos = 0;
+        let mut push = |value: usize, len: usize| {
+            for i in (0..len).rev() {
+                state[pos] = (value >> i) & 1 == 1;
+                pos += 1;
+            }
+        };
+        // prime field, x^alpha S-box
+        push(1, 2);
+        push(0, 4);
+        push(field_bits, 12);
+        push(width, 12);
+        push(full_rounds, 10);
+        push(partial_rounds, 10);
+        push((1 << 30) - 1, 30);
+
+        let mut lfsr = Self { state, head: 0 };
+        for _ in 0..160 {
+            lfsr.clock();
+        }
+        lfsr
+    }
+
+    fn clock(&mut self) -> bool {
+        let bit = |offset: usize| self.state[(self.head + offset) % 80];
+        let new_bit = bit(62) ^ bit(51) ^ bit(38) ^ bit(23) ^ bit(13) ^ bit(0);
+        self.state[self.head] = new_bit;
+        self.head = (self.head + 1) % 80;
+        new_bit
+    }
+
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let keep = self.clock();
+            let bit = self.clock();
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    fn next_prime_field_element<P: PrimeField>(&mut self) -> P {
+        loop {
+            let bits: Vec<bool> = (0..P::MODULUS_BIT_SIZE).map(|_| self.next_bit()).collect();
+            if let Some(element) = P::from_bigint(P::BigInt::from_bits_be(&bits)) {
+                return element;
+            }
+        }
+    }
+
+    fn next_field_element<F: Field>(&mut self) -> F {
+        let coefficients: Vec<F::BasePrimeField> = (0..F::extension_degree())
+            .map(|_| self.next_prime_field_element())
+            .collect();
+        F::from_base_prime_field_elems(&coefficients).unwrap()
+    }
+}
+
+impl<F: Field> PoseidonParameters<F> {
+    /// Generates the round constants and MDS matrix of a permutation of
+    /// `width` elements.
+    pub fn new(width: usize, full_rounds: usize, partial_rounds: usize, alpha: u64) -> Self {
+        let field_bits = <F::BasePrimeField as PrimeField>::MODULUS_BIT_SIZE as usize;
+        let mut lfsr = GrainLfsr::new(field_bits, width, full_rounds, partial_rounds);
+        let round_constants = (0..full_rounds + partial_rounds)
+            .map(|_| (0..width).map(|_| lfsr.next_field_element()).collect())
+            .collect();
+
+        let mds = (0..width)
+            .map(|i| {
+                (0..width)
+                    .map(|j| F::from((i + j + width) as u64).inverse().unwrap())
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            full_rounds,
+            partial_rounds,
+            alpha,
+            round_constants,
+            mds,
+        }
+    }
+
+    /// Number of field elements in the state.
+    pub fn width(&self) -> usize {
+        self.mds.len()
+    }
+
+    fn is_full_round(&self, round: usize) -> bool {
+        let half_full = self.full_rounds / 2;
+        round < half_full || round >= half_full + self.partial_rounds
+    }
+
+    /// Applies the permutation to `state` natively.
+    pub fn permute_native(&self, state: &mut [F]) {
+        assert_eq!(state.len(), self.width());
+        for (round, constants) in self.round_constants.iter().enumerate() {
+            for (x, c) in state.iter_mut().zip(constants) {
+                *x += c;
+            }
+
+            let sbox_count = if self.is_full_round(round) { state.len() } else { 1 };
+            for x in &mut state[..sbox_count] {
+                *x = x.pow([self.alpha]);
+            }
+
+            let mixed: Vec<F> = self
+                .mds
+                .iter()
+                .map(|row| row.iter().zip(state.iter()).map(|(m, x)| *m * x).sum())
+                .collect();
+            state.copy_from_slice(&mixed);
+        }
+    }
+}
+
+/// Applies the permutation to `state` in the constraint system. Additions of
+/// round constants and the MDS layer are linear, so only the S-boxes add
+/// constraints.
+#[tracing::instrument(target = "r1cs", skip(params, state))]
+pub fn poseidon_permute<F, CF, V>(
+    params: &PoseidonParameters<F>,
+    state: &mut [V],
+) -> Result<(), SynthesisError>
+where
+    F: Field,
+    CF: PrimeField,
+    V: FieldVar<F, CF>,
+    for<'a> &'a V: FieldOpsBounds<'a, F, V>,
+{
+    assert_eq!(state.len(), params.width());
+    for (round, constants) in params.round_constants.iter().enumerate() {
+        for (x, c) in state.iter_mut().zip(constants) {
+            *x += *c;
+        }
+
+        let sbox_count = if params.is_full_round(round) { state.len() } else { 1 };
+        for x in &mut state[..sbox_count] {
+            *x = x.pow_by_constant([params.alpha])?;
+        }
+
+        let mut mixed = vec![V::zero(); state.len()];
+        for (out, row) in mixed.iter_mut().zip(&params.mds) {
+            for (m, x) in row.iter().zip(state.iter()) {
+                *out += x * *m;
+            }
+        }
+        state.clone_from_slice(&mixed);
+    }
+    Ok(())
+}
+
+/// Two to one compression: `left` and `right` fill the end of the state after
+/// a zero capacity, the output is the first `left.len()` elements after it.
+/// This matches `PoseidonHasher` of zkFOAKS on digests of two elements.
+#[tracing::instrument(target = "r1cs", skip(params, left, right))]
+pub fn poseidon_compress<F, CF, V>(
+    params: &PoseidonParameters<F>,
+    left: &[V],
+    right: &[V],
+) -> Result<Vec<V>, SynthesisError>
+where
+    F: Field,
+    CF: PrimeField,
+    V: FieldVar<F, CF>,
+    for<'a> &'a V: FieldOpsBounds<'a, F, V>,
+{
+    assert!(left.len() + right.len() < params.width());
+    let capacity = params.width() - left.len() - right.len();
+    let mut state = vec![V::zero(); capacity];
+    state.extend_from_slice(left);
+    state.extend_from_slice(right);
+
+    poseidon_permute(params, &mut state)?;
+    Ok(state[capacity..capacity + left.len()].to_vec())
+}
@@ -0,0 +1,96 @@
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    eq::EqGadget,
+    fields::{fp::FpVar, fp2::Fp2Var},
+    poseidon::{poseidon_compress, poseidon_permute, PoseidonParameters},
+    R1CSVar,
+};
+use ark_relations::r1cs::ConstraintSystem;
+use prime_field::ark::{Fp61, Fp61x2, Fp61x2Config};
+
+fn element(real: u64, img: u64) -> Fp61x2 {
+    Fp61x2::new(Fp61::from(real), Fp61::from(img))
+}
+
+fn foaks_parameters() -> PoseidonParameters<Fp61x2> {
+    PoseidonParameters::new(6, 8, 30, 17)
+}
+
+// Test vectors from `infrastructure::poseidon` of zkFOAKS
+fn counting_state() -> Vec<Fp61x2> {
+    (0..6).map(|i| element(i, i + 6)).collect()
+}
+
+fn counting_output() -> Vec<Fp61x2> {
+    vec![
+        element(1148174422229025470, 388482865900556289),
+        element(404086809262610216, 1855407273105801168),
+        element(1089296707972025237, 1883085730324888203),
+        element(510504912242687085, 570277774103056198),
+        element(794118730322698530, 231188705230079989),
+        element(2220924848000554619, 2130591855111670392),
+    ]
+}
+
+#[test]
+fn native_vectors() {
+    let params = foaks_parameters();
+    assert_eq!(
+        params.round_constants[0][0],
+        element(2025068226995972209, 460696318347934662)
+    );
+
+    let mut state = vec![Fp61x2::from(0u64); 6];
+    params.permute_native(&mut state);
+    assert_eq!(state[0], element(359328414208176523, 644084420742128250));
+    assert_eq!(state[5], element(1973665981007036655, 1548501366178755857));
+
+    let mut state = counting_state();
+    params.permute_native(&mut state);
+    assert_eq!(state, counting_output());
+}
+
+#[test]
+fn permutation_gadget_matches_native() {
+    let params = foaks_parameters();
+    let cs = ConstraintSystem::<Fp61>::new_ref();
+
+    let mut state = counting_state()
+        .iter()
+        .map(|x| Fp2Var::<Fp61x2Config>::new_witness(cs.clone(), || Ok(*x)).unwrap())
+        .collect::<Vec<_>>();
+    poseidon_permute(&params, &mut state).unwrap();
+
+    assert_eq!(state.value().unwrap(), counting_output());
+    assert!(cs.is_satisfied().unwrap());
+    // x^17 is 4 squarings of 2 constraints and a multiplication of 3
+    assert_eq!(cs.num_constraints(), 11 * (8 * 6 + 30));
+}
+
+#[test]
+fn compression_gadget_matches_hasher() {
+    let params = foaks_parameters();
+    let cs = ConstraintSystem::<Fp61>::new_ref();
+
+    let leaf = |i: u64| [element(i, 1), element(2, i)];
+    let alloc = |xs: [Fp61x2; 2]| {
+        xs.iter()
+            .map(|x| Fp2Var::<Fp61x2Config>::new_witness(cs.clone(), || Ok(*x)).unwrap())
+            .collect::<Vec<_>>()
+    };
+    let digest = poseidon_compress(&params, &alloc(leaf(3)), &alloc(leaf(4))).unwrap();
+
+    assert_eq!(
+        digest.value().unwrap(),
+        vec![
+            element(2232180723706229168, 956883930679334679),
+            element(642474643030698172, 816363376046804525),
+        ]
+    );
+    assert!(cs.is_satisfied().unwrap());
+
+    // a wrong witness does not satisfy the same circuit
+    let expected = FpVar::new_input(cs.clone(), || Ok(Fp61::from(0u64))).unwrap();
+    digest[0].c0.enforce_equal(&expected).unwrap();
+    assert!(!cs.is_satisfied().unwrap());
+}
@@ -0,0 +1,145 @@
+//! Export of constraint systems to the text format read by the `spartan`
+//! crate of zkFOAKS (see `foaks/spartan/src/r1cs_file.rs`), which proves them
+//! over the field of modulus `2^61 - 1`.
+//!
+//! Coefficients and values are written as signed integers, so a constraint
+//! system only exports when all of them are small: an element `x` is written
+//! as `x` or as `-(p - x)`, whichever is below the FOAKS modulus in absolute
+//! value. The exported system is satisfied over the FOAKS field whenever its
+//! constraints hold over the integers, as they do for boolean and limb checks
+//! on small values.
+
+use core::fmt;
+use std::fmt::Write;
+
+use ark_ff::PrimeField;
+
+use crate::r1cs::{ConstraintMatrices, ConstraintSystemRef, Matrix};
+
+/// Modulus of the base field of zkFOAKS
+pub const FOAKS_MODULUS: u64 = (1 << 61) - 1;
+
+/// Errors of the export to zkFOAKS.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ExportError {
+    /// The constraint system has no matrices or no assignment.
+    MissingConstraintSystem,
+    /// A coefficient of a matrix is not a small integer.
+    CoefficientOutOfRange {
+        /// `'A'`, `'B'` or `'C'`
+        matrix: char,
+        /// Constraint of the coefficient
+        row: usize,
+        /// Variable of the coefficient
+        column: usize,
+    },
+    /// A value of the assignment is not a small integer, `index` counts the
+    /// instance variables first.
+    ValueOutOfRange {
+        /// Variable of the value
+        index: usize,
+    },
+}
+
+impl ark_std::error::Error for ExportError {}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            ExportError::MissingConstraintSystem => {
+                write!(f, "the constraint system has no matrices or assignment")
+            }
+            ExportError::CoefficientOutOfRange {
+                matrix,
+                row,
+                column,
+            } => write!(
+                f,
+                "coefficient ({}, {}) of {} is not a small integer",
+                row, column, matrix
+            ),
+            ExportError::ValueOutOfRange { index } => {
+                write!(f, "value of variable {} is not a small integer", index)
+            }
+        }
+    }
+}
+
+fn small<F: PrimeField>(x: F) -> Option<u64> {
+    let repr = x.into_repr();
+    let limbs = repr.as_ref();
+    (limbs[1..].iter().all(|&limb| limb == 0) && limbs[0] < FOAKS_MODULUS).then(|| limbs[0])
+}
+
+/// `x` as a signed integer of absolute value below [`FOAKS_MODULUS`], if any.
+pub fn to_foaks_integer<F: PrimeField>(x: F) -> Option<i64> {
+    match small(x) {
+        Some(x) => Some(x as i64),
+        None => small(-x).map(|x| -(x as i64)),
+    }
+}
+
+/// Writes `matrices` in the constraint system format of zkFOAKS.
+pub fn write_foaks_r1cs<F: PrimeField>(
+    matrices: &ConstraintMatrices<F>,
+) -> Result<String, ExportError> {
+    let mut out = format!(
+        "{} {} {}\n",
+        matrices.num_constraints, matrices.num_instance_variables, matrices.num_witness_variables
+    );
+    let all: [(char, &Matrix<F>); 3] = [('A', &matrices.a), ('B', &matrices.b), ('C', &matrices.c)];
+    for (matrix, rows) in all {
+        for (row, terms) in rows.iter().enumerate() {
+            write!(out, "{}", terms.len()).unwrap();
+            for &(coefficient, column) in terms {
+                let coefficient =
+                    to_foaks_integer(coefficient).ok_or(ExportError::CoefficientOutOfRange {
+                        matrix,
+                        row,
+                        column,
+                    })?;
+                write!(out, " {} {}", column, coefficient).unwrap();
+            }
+            out.push('\n');
+        }
+    }
+    Ok(out)
+}
+
+/// Writes the instance assignment, starting with the constant one, and the
+/// witness assignment in the assignment format of zkFOAKS.
+pub fn write_foaks_assignment<F: PrimeField>(
+    instance: &[F],
+    witness: &[F],
+) -> Result<String, ExportError> {
+    let mut out = String::new();
+    let mut index = 0;
+    for values in [instance, witness] {
+        write!(out, "{}", values.len()).unwrap();
+        for &value in values {
+            let value = to_foaks_integer(value).ok_or(ExportError::ValueOutOfRange { index })?;
+            write!(out, " {}", value).unwrap();
+            index += 1;
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Writes the matrices and the assignment of a finalized constraint system,
+/// synthesized in proving mode, for zkFOAKS.
+pub fn export_to_foaks<F: PrimeField>(
+    cs: &ConstraintSystemRef<F>,
+) -> Result<(String, String), ExportError> {
+    let matrices = cs
+        .to_matrices()
+        .ok_or(ExportError::MissingConstraintSystem)?;
+    let cs = cs.borrow().ok_or(ExportError::MissingConstraintSystem)?;
+    if cs.witness_assignment.len() != matrices.num_witness_variables {
+        return Err(ExportError::MissingConstraintSystem);
+    }
+    Ok((
+        write_foaks_r1cs(&matrices)?,
+        write_foaks_assignment(&cs.instance_assignment, &cs.witness_assignment)?,
+    ))
+}
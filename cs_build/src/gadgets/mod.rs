@@ -0,0 +1,5 @@
+//! Gadgets shared by the constraint systems of the memory checks, over
+//! variables whose values are known when synthesizing.
+
+pub mod permutation;
+pub mod range;
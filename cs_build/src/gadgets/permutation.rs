@@ -0,0 +1,127 @@
+//! Multiset equality of two tables with a grand product.
+//!
+//! A row `(t_0, ..., t_{k-1})` is compressed to `Σ_j alpha^(k-1-j) t_j` and
+//! the products of `gamma - compressed` over both tables are compared. Two
+//! tables whose rows are not permutations of each other have the same product
+//! with probability at most `(n + k) / |F|` over random challenges, so
+//! `alpha` and `gamma` must be sampled once both tables are fixed, e.g. by
+//! the verifier or by hashing public tables with [`Challenges::derive`].
+
+use ark_ff::PrimeField;
+use ark_std::rand::Rng;
+use blake2::{Blake2s, Digest};
+
+use crate::lc;
+use crate::r1cs::*;
+
+/// Challenges of the grand product
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Challenges<F: PrimeField> {
+    /// Compresses the rows
+    pub alpha: F,
+    /// Shifts the compressed rows
+    pub gamma: F,
+}
+
+/// [`Challenges`] allocated as instance variables
+#[derive(Debug, Clone, Copy)]
+pub struct AllocatedChallenges<F: PrimeField> {
+    alpha: Variable,
+    gamma: Variable,
+    values: Challenges<F>,
+}
+
+impl<F: PrimeField> Challenges<F> {
+    /// Uniformly random challenges.
+    pub fn sample<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self {
+            alpha: F::rand(rng),
+            gamma: F::rand(rng),
+        }
+    }
+
+    /// Challenges hashed from the cells of `tables`, each table being hashed
+    /// with its number of cells. The tables must be public, or committed to,
+    /// so that they are fixed before the challenges are known.
+    pub fn derive(tables: &[&[u64]]) -> Self {
+        let mut hasher = Blake2s::new();
+        hasher.update(b"cs_build permutation");
+        for table in tables {
+            hasher.update((table.len() as u64).to_le_bytes());
+            table.iter().for_each(|x| hasher.update(x.to_le_bytes()));
+        }
+        let seed = hasher.finalize();
+        let challenge = |label: u8| {
+            let mut hasher = Blake2s::new();
+            hasher.update(seed);
+            hasher.update([label]);
+            F::from_le_bytes_mod_order(&hasher.finalize())
+        };
+        Self {
+            alpha: challenge(0),
+            gamma: challenge(1),
+        }
+    }
+
+    /// Allocates the challenges as instance variables, so that the verifier
+    /// provides them.
+    pub fn allocate(self, cs: &ConstraintSystemRef<F>) -> Result<AllocatedChallenges<F>> {
+        Ok(AllocatedChallenges {
+            alpha: cs.new_input_variable(|| Ok(self.alpha))?,
+            gamma: cs.new_input_variable(|| Ok(self.gamma))?,
+            values: self,
+        })
+    }
+}
+
+/// `Π_i (gamma - Σ_j alpha^(k-1-j) rows[i][j])` and its value, the cells of
+/// `rows` are variables with their values.
+pub fn grand_product<F: PrimeField>(
+    cs: &ConstraintSystemRef<F>,
+    challenges: &AllocatedChallenges<F>,
+    rows: &[Vec<(Variable, F)>],
+) -> Result<(LinearCombination<F>, F)> {
+    let Challenges { alpha, gamma } = challenges.values;
+    let mut product = (lc!() + Variable::One, F::one());
+    for row in rows {
+        let (first, rest) = row.split_first().ok_or(SynthesisError::Unsatisfiable)?;
+        // Horner's rule, one constraint per cell
+        let mut compressed = (lc!() + first.0, first.1);
+        for &(variable, value) in rest {
+            let next = compressed.1 * alpha + value;
+            let next_var = cs.new_witness_variable(|| Ok(next))?;
+            cs.enforce_constraint(
+                compressed.0,
+                lc!() + challenges.alpha,
+                lc!() + next_var - variable,
+            )?;
+            compressed = (lc!() + next_var, next);
+        }
+        let next = product.1 * (gamma - compressed.1);
+        let next_var = cs.new_witness_variable(|| Ok(next))?;
+        cs.enforce_constraint(
+            product.0,
+            lc!() + challenges.gamma - &compressed.0,
+            lc!() + next_var,
+        )?;
+        product = (lc!() + next_var, next);
+    }
+    Ok(product)
+}
+
+/// Enforces the rows of `left` to be a permutation of the rows of `right`,
+/// all rows having the same number of cells.
+pub fn enforce_permutation<F: PrimeField>(
+    cs: &ConstraintSystemRef<F>,
+    challenges: &AllocatedChallenges<F>,
+    left: &[Vec<(Variable, F)>],
+    right: &[Vec<(Variable, F)>],
+) -> Result<()> {
+    let width = left.first().map(Vec::len);
+    if left.len() != right.len() || left.iter().chain(right).any(|row| Some(row.len()) != width) {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    let (left, _) = grand_product(cs, challenges, left)?;
+    let (right, _) = grand_product(cs, challenges, right)?;
+    cs.enforce_constraint(left - &right, lc!() + Variable::One, lc!())
+}
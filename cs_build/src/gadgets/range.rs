@@ -0,0 +1,77 @@
+//! Range checks and decompositions into limbs, with one boolean per bit: a
+//! value of `n` bits costs `n + 1` constraints.
+//!
+//! There is no lookup mode. A lookup argument needs a challenge sampled once
+//! the chunks and their multiplicities are fixed, which a Groth16 circuit can
+//! only get from its public inputs.
+
+use ark_ff::PrimeField;
+
+use crate::lc;
+use crate::r1cs::*;
+
+fn to_u64<F: PrimeField>(value: F) -> u64 {
+    value.into_repr().as_ref()[0]
+}
+
+/// Low `bits` bits of `value`, with `bits` at most 64
+fn low_bits<F: PrimeField>(value: F, bits: usize) -> u64 {
+    let value = to_u64(value);
+    if bits >= 64 {
+        value
+    } else {
+        value & ((1 << bits) - 1)
+    }
+}
+
+/// Enforces `x`, of value `value`, to be below `2^bits`, with `bits` at most
+/// 64 and below the bits of the modulus.
+pub fn enforce_range<F: PrimeField>(
+    cs: &ConstraintSystemRef<F>,
+    x: LinearCombination<F>,
+    value: F,
+    bits: usize,
+) -> Result<()> {
+    // the sum of the bits would wrap around the modulus
+    if bits >= F::size_in_bits() {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    let value = low_bits(value, bits);
+    let mut sum = lc!();
+    for shift in 0..bits {
+        let bit = F::from((value >> shift) & 1);
+        let bit_var = cs.new_witness_variable(|| Ok(bit))?;
+        cs.enforce_constraint(lc!() + bit_var, lc!() + bit_var - Variable::One, lc!())?;
+        sum += (F::from(2u64).pow([shift as u64]), bit_var);
+    }
+    cs.enforce_constraint(sum, lc!() + Variable::One, x)
+}
+
+/// Splits `x`, of value `value`, in `num_limbs` limbs of `limb_bits` bits
+/// from the least significant one and enforces their ranges, with
+/// `limb_bits * num_limbs` at most 64 and below the bits of the modulus.
+/// Returns the limbs with their values.
+pub fn decompose<F: PrimeField>(
+    cs: &ConstraintSystemRef<F>,
+    x: LinearCombination<F>,
+    value: F,
+    limb_bits: usize,
+    num_limbs: usize,
+) -> Result<Vec<(Variable, F)>> {
+    if limb_bits * num_limbs >= F::size_in_bits() {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    let value = low_bits(value, limb_bits * num_limbs);
+    let mut sum = lc!();
+    let mut limbs = Vec::with_capacity(num_limbs);
+    for k in 0..num_limbs {
+        let shift = k * limb_bits;
+        let limb = F::from(low_bits(F::from(value >> shift), limb_bits));
+        let limb_var = cs.new_witness_variable(|| Ok(limb))?;
+        enforce_range(cs, lc!() + limb_var, limb, limb_bits)?;
+        sum += (F::from(2u64).pow([shift as u64]), limb_var);
+        limbs.push((limb_var, limb));
+    }
+    cs.enforce_constraint(sum, lc!() + Variable::One, x)?;
+    Ok(limbs)
+}
@@ -0,0 +1,172 @@
+//! Groth16 proofs of constraint systems synthesized by `cs_build`, with
+//! `ark-groth16`.
+//!
+//! `ark-groth16` synthesizes circuits of `ark-relations`, so a circuit is
+//! first synthesized here into a [`Synthesized`] constraint system, which is
+//! then replayed in `ark-relations` with the same variables and constraints:
+//! the instance variables of the circuit are the public inputs of the proof.
+
+use core::fmt;
+
+use ark_ec::PairingEngine;
+use ark_ff::Field;
+use ark_groth16::{PreparedVerifyingKey, Proof, ProvingKey, VerifyingKey};
+use ark_relations::r1cs as relations;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::rand::{CryptoRng, RngCore};
+
+use crate::r1cs::{
+    ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, SynthesisError, SynthesisMode,
+};
+
+/// Errors of the Groth16 pipeline.
+#[derive(Debug)]
+pub enum Groth16Error {
+    /// The circuit failed to synthesize in `cs_build`.
+    Synthesis(SynthesisError),
+    /// `ark-groth16` failed to set up, prove or verify.
+    Groth16(relations::SynthesisError),
+    /// A key or a proof failed to (de)serialize.
+    Serialization(SerializationError),
+}
+
+impl ark_std::error::Error for Groth16Error {}
+
+impl fmt::Display for Groth16Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Groth16Error::Synthesis(e) => write!(f, "synthesis failed: {}", e),
+            Groth16Error::Groth16(e) => write!(f, "groth16 failed: {}", e),
+            Groth16Error::Serialization(e) => write!(f, "serialization failed: {}", e),
+        }
+    }
+}
+
+impl From<SynthesisError> for Groth16Error {
+    fn from(e: SynthesisError) -> Self {
+        Groth16Error::Synthesis(e)
+    }
+}
+
+impl From<relations::SynthesisError> for Groth16Error {
+    fn from(e: relations::SynthesisError) -> Self {
+        Groth16Error::Groth16(e)
+    }
+}
+
+impl From<SerializationError> for Groth16Error {
+    fn from(e: SerializationError) -> Self {
+        Groth16Error::Serialization(e)
+    }
+}
+
+/// A circuit synthesized by `cs_build`, with its assignment.
+#[derive(Debug, Clone)]
+pub struct Synthesized<F: Field> {
+    /// Matrices of the constraint system
+    pub matrices: ConstraintMatrices<F>,
+    /// Assignment of the instance variables, starting with the constant one
+    pub instance: Vec<F>,
+    /// Assignment of the witness variables
+    pub witness: Vec<F>,
+}
+
+impl<F: Field> Synthesized<F> {
+    /// Synthesizes `circuit` in proving mode.
+    pub fn new<C: ConstraintSynthesizer<F>>(circuit: C) -> Result<Self, SynthesisError> {
+        let cs = ConstraintSystem::new_ref();
+        cs.set_mode(SynthesisMode::Prove {
+            construct_matrices: true,
+        });
+        circuit.generate_constraints(cs.clone())?;
+        cs.finalize();
+        let matrices = cs.to_matrices().ok_or(SynthesisError::MissingCS)?;
+        let cs = cs.borrow().ok_or(SynthesisError::MissingCS)?;
+        Ok(Self {
+            matrices,
+            instance: cs.instance_assignment.clone(),
+            witness: cs.witness_assignment.clone(),
+        })
+    }
+
+    /// The public inputs of a proof of the circuit.
+    pub fn public_inputs(&self) -> &[F] {
+        &self.instance[1..]
+    }
+}
+
+impl<F: Field> relations::ConstraintSynthesizer<F> for Synthesized<F> {
+    fn generate_constraints(self, cs: relations::ConstraintSystemRef<F>) -> relations::Result<()> {
+        let assigned = |x: Option<&F>| {
+            x.copied()
+                .ok_or(relations::SynthesisError::AssignmentMissing)
+        };
+        let mut variables = vec![relations::Variable::One];
+        for i in 1..self.matrices.num_instance_variables {
+            variables.push(cs.new_input_variable(|| assigned(self.instance.get(i)))?);
+        }
+        for i in 0..self.matrices.num_witness_variables {
+            variables.push(cs.new_witness_variable(|| assigned(self.witness.get(i)))?);
+        }
+        let lc = |row: &[(F, usize)]| {
+            relations::LinearCombination(
+                row.iter()
+                    .map(|&(coefficient, column)| (coefficient, variables[column]))
+                    .collect(),
+            )
+        };
+        for i in 0..self.matrices.num_constraints {
+            cs.enforce_constraint(
+                lc(&self.matrices.a[i]),
+                lc(&self.matrices.b[i]),
+                lc(&self.matrices.c[i]),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Generates the proving key, which contains the verifying key, of circuits
+/// with the constraints of `circuit`.
+pub fn setup<E, R>(circuit: Synthesized<E::Fr>, rng: &mut R) -> Result<ProvingKey<E>, Groth16Error>
+where
+    E: PairingEngine,
+    R: RngCore + CryptoRng,
+{
+    Ok(ark_groth16::generate_random_parameters(circuit, rng)?)
+}
+
+/// Proves that the assignment of `circuit` satisfies it.
+pub fn prove<E, R>(
+    pk: &ProvingKey<E>,
+    circuit: Synthesized<E::Fr>,
+    rng: &mut R,
+) -> Result<Proof<E>, Groth16Error>
+where
+    E: PairingEngine,
+    R: RngCore + CryptoRng,
+{
+    Ok(ark_groth16::create_random_proof(circuit, pk, rng)?)
+}
+
+/// Verifies `proof` for `public_inputs`, see [`Synthesized::public_inputs`].
+pub fn verify<E: PairingEngine>(
+    vk: &VerifyingKey<E>,
+    public_inputs: &[E::Fr],
+    proof: &Proof<E>,
+) -> Result<bool, Groth16Error> {
+    let pvk: PreparedVerifyingKey<E> = ark_groth16::prepare_verifying_key(vk);
+    Ok(ark_groth16::verify_proof(&pvk, proof, public_inputs)?)
+}
+
+/// Serializes a key or a proof, compressed.
+pub fn to_bytes<T: CanonicalSerialize>(x: &T) -> Result<Vec<u8>, Groth16Error> {
+    let mut bytes = Vec::with_capacity(x.serialized_size());
+    x.serialize(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Deserializes a key or a proof serialized by [`to_bytes`].
+pub fn from_bytes<T: CanonicalDeserialize>(bytes: &[u8]) -> Result<T, Groth16Error> {
+    Ok(T::deserialize(bytes)?)
+}
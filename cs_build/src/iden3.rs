@@ -0,0 +1,335 @@
+//! Constraint systems and assignments in the binary `.r1cs` and `.wtns`
+//! formats of iden3, read and written by circom and snarkjs.
+//!
+//! Wires are the columns of [`ConstraintMatrices`]: the constant one, the
+//! other instance variables as public inputs, then the witness variables.
+//! Field elements are written in little endian on as many bytes as
+//! `F::BigInt`, in their canonical form.
+
+use core::fmt;
+
+use ark_ff::{BigInteger, FpParameters, FromBytes, PrimeField};
+
+use crate::r1cs::{ConstraintMatrices, ConstraintSystemRef, Matrix};
+
+const R1CS_MAGIC: &[u8; 4] = b"r1cs";
+const R1CS_VERSION: u32 = 1;
+const R1CS_HEADER: u32 = 1;
+const R1CS_CONSTRAINTS: u32 = 2;
+const R1CS_WIRE_TO_LABEL: u32 = 3;
+
+const WTNS_MAGIC: &[u8; 4] = b"wtns";
+const WTNS_VERSION: u32 = 2;
+const WTNS_HEADER: u32 = 1;
+const WTNS_VALUES: u32 = 2;
+
+/// Errors of the iden3 formats.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Iden3Error {
+    /// The constraint system has no matrices or no assignment.
+    MissingConstraintSystem,
+    /// The file ends in the middle of a section.
+    UnexpectedEnd,
+    /// The file does not start with the magic of its format.
+    InvalidMagic,
+    /// A version of the format other than the one written here.
+    UnsupportedVersion(u32),
+    /// A section the format requires is missing.
+    MissingSection(u32),
+    /// The content of a section does not match its size.
+    InvalidSection(u32),
+    /// The file is over another prime field.
+    FieldMismatch,
+    /// A field element is not below the modulus.
+    InvalidFieldElement,
+    /// A constraint refers to a wire the header does not declare.
+    WireOutOfRange {
+        /// Constraint of the wire
+        constraint: usize,
+        /// Wire out of range
+        wire: u32,
+    },
+}
+
+impl ark_std::error::Error for Iden3Error {}
+
+impl fmt::Display for Iden3Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Iden3Error::MissingConstraintSystem => {
+                write!(f, "the constraint system has no matrices or assignment")
+            }
+            Iden3Error::UnexpectedEnd => write!(f, "unexpected end of file"),
+            Iden3Error::InvalidMagic => write!(f, "invalid magic number"),
+            Iden3Error::UnsupportedVersion(version) => {
+                write!(f, "unsupported version {}", version)
+            }
+            Iden3Error::MissingSection(section) => write!(f, "missing section {}", section),
+            Iden3Error::InvalidSection(section) => write!(f, "invalid section {}", section),
+            Iden3Error::FieldMismatch => write!(f, "the file is over another field"),
+            Iden3Error::InvalidFieldElement => write!(f, "field element above the modulus"),
+            Iden3Error::WireOutOfRange { constraint, wire } => {
+                write!(
+                    f,
+                    "wire {} of constraint {} is out of range",
+                    wire, constraint
+                )
+            }
+        }
+    }
+}
+
+fn modulus_bytes<F: PrimeField>() -> Vec<u8> {
+    <F::Params as FpParameters>::MODULUS.to_bytes_le()
+}
+
+fn push_u32(out: &mut Vec<u8>, x: usize) {
+    out.extend_from_slice(&(x as u32).to_le_bytes());
+}
+
+fn push_section(out: &mut Vec<u8>, section: u32, content: &[u8]) {
+    out.extend_from_slice(&section.to_le_bytes());
+    out.extend_from_slice(&(content.len() as u64).to_le_bytes());
+    out.extend_from_slice(content);
+}
+
+fn push_element<F: PrimeField>(out: &mut Vec<u8>, x: F) {
+    out.extend_from_slice(&x.into_repr().to_bytes_le());
+}
+
+/// Writes `matrices` in the `.r1cs` format.
+pub fn write_r1cs<F: PrimeField>(matrices: &ConstraintMatrices<F>) -> Vec<u8> {
+    let modulus = modulus_bytes::<F>();
+    let num_wires = matrices.num_instance_variables + matrices.num_witness_variables;
+
+    let mut header = Vec::new();
+    push_u32(&mut header, modulus.len());
+    header.extend_from_slice(&modulus);
+    push_u32(&mut header, num_wires);
+    // no public outputs, the instance variables are public inputs
+    push_u32(&mut header, 0);
+    push_u32(&mut header, matrices.num_instance_variables - 1);
+    push_u32(&mut header, matrices.num_witness_variables);
+    header.extend_from_slice(&(num_wires as u64).to_le_bytes());
+    push_u32(&mut header, matrices.num_constraints);
+
+    let mut constraints = Vec::new();
+    for i in 0..matrices.num_constraints {
+        for matrix in [&matrices.a, &matrices.b, &matrices.c] {
+            push_u32(&mut constraints, matrix[i].len());
+            for &(coefficient, wire) in &matrix[i] {
+                push_u32(&mut constraints, wire);
+                push_element(&mut constraints, coefficient);
+            }
+        }
+    }
+
+    let labels: Vec<u8> = (0..num_wires as u64).flat_map(u64::to_le_bytes).collect();
+
+    let mut out = R1CS_MAGIC.to_vec();
+    out.extend_from_slice(&R1CS_VERSION.to_le_bytes());
+    push_u32(&mut out, 3);
+    push_section(&mut out, R1CS_HEADER, &header);
+    push_section(&mut out, R1CS_CONSTRAINTS, &constraints);
+    push_section(&mut out, R1CS_WIRE_TO_LABEL, &labels);
+    out
+}
+
+/// Writes the assignment of every wire, `instance` starting with the
+/// constant one, in the `.wtns` format.
+pub fn write_wtns<F: PrimeField>(instance: &[F], witness: &[F]) -> Vec<u8> {
+    let modulus = modulus_bytes::<F>();
+    let mut header = Vec::new();
+    push_u32(&mut header, modulus.len());
+    header.extend_from_slice(&modulus);
+    push_u32(&mut header, instance.len() + witness.len());
+
+    let mut values = Vec::new();
+    for &x in instance.iter().chain(witness) {
+        push_element(&mut values, x);
+    }
+
+    let mut out = WTNS_MAGIC.to_vec();
+    out.extend_from_slice(&WTNS_VERSION.to_le_bytes());
+    push_u32(&mut out, 2);
+    push_section(&mut out, WTNS_HEADER, &header);
+    push_section(&mut out, WTNS_VALUES, &values);
+    out
+}
+
+/// Writes the matrices and the assignment of a finalized constraint system,
+/// synthesized in proving mode, in the `.r1cs` and `.wtns` formats.
+pub fn export_to_iden3<F: PrimeField>(
+    cs: &ConstraintSystemRef<F>,
+) -> Result<(Vec<u8>, Vec<u8>), Iden3Error> {
+    let matrices = cs
+        .to_matrices()
+        .ok_or(Iden3Error::MissingConstraintSystem)?;
+    let cs = cs.borrow().ok_or(Iden3Error::MissingConstraintSystem)?;
+    if cs.witness_assignment.len() != matrices.num_witness_variables {
+        return Err(Iden3Error::MissingConstraintSystem);
+    }
+    Ok((
+        write_r1cs(&matrices),
+        write_wtns(&cs.instance_assignment, &cs.witness_assignment),
+    ))
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Iden3Error> {
+        if self.0.len() < n {
+            return Err(Iden3Error::UnexpectedEnd);
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, Iden3Error> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Result<u64, Iden3Error> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn element<F: PrimeField>(&mut self) -> Result<F, Iden3Error> {
+        let bytes = self.take(modulus_bytes::<F>().len())?;
+        let repr = F::BigInt::read(bytes).map_err(|_| Iden3Error::InvalidFieldElement)?;
+        F::from_repr(repr).ok_or(Iden3Error::InvalidFieldElement)
+    }
+
+    /// Checks the field size and the prime of a header.
+    fn field<F: PrimeField>(&mut self) -> Result<(), Iden3Error> {
+        let modulus = modulus_bytes::<F>();
+        if self.u32()? as usize != modulus.len() || self.take(modulus.len())? != modulus {
+            return Err(Iden3Error::FieldMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// The sections of a file of `magic` and `version`, by type. Sections of
+/// unknown types are kept, the first one of each type is used.
+fn sections<'a>(
+    bytes: &'a [u8],
+    magic: &[u8; 4],
+    version: u32,
+) -> Result<Vec<(u32, &'a [u8])>, Iden3Error> {
+    let mut reader = Reader(bytes);
+    if reader.take(4)? != magic {
+        return Err(Iden3Error::InvalidMagic);
+    }
+    let file_version = reader.u32()?;
+    if file_version != version {
+        return Err(Iden3Error::UnsupportedVersion(file_version));
+    }
+    // the number of sections is untrusted, a truncated file ends the loop
+    let num_sections = reader.u32()?;
+    let mut sections = Vec::new();
+    for _ in 0..num_sections {
+        let section = reader.u32()?;
+        let size = reader.u64()?;
+        let size = usize::try_from(size).map_err(|_| Iden3Error::UnexpectedEnd)?;
+        sections.push((section, reader.take(size)?));
+    }
+    Ok(sections)
+}
+
+fn section<'a>(sections: &[(u32, &'a [u8])], section: u32) -> Result<Reader<'a>, Iden3Error> {
+    sections
+        .iter()
+        .find(|(s, _)| *s == section)
+        .map(|(_, content)| Reader(content))
+        .ok_or(Iden3Error::MissingSection(section))
+}
+
+fn finish(reader: Reader<'_>, section: u32) -> Result<(), Iden3Error> {
+    match reader.0.is_empty() {
+        true => Ok(()),
+        false => Err(Iden3Error::InvalidSection(section)),
+    }
+}
+
+fn read_matrix<F: PrimeField>(
+    reader: &mut Reader<'_>,
+    constraint: usize,
+    num_wires: usize,
+) -> Result<Vec<(F, usize)>, Iden3Error> {
+    let num_terms = reader.u32()?;
+    (0..num_terms)
+        .map(|_| {
+            let wire = reader.u32()?;
+            if wire as usize >= num_wires {
+                return Err(Iden3Error::WireOutOfRange { constraint, wire });
+            }
+            Ok((reader.element()?, wire as usize))
+        })
+        .collect()
+}
+
+/// Reads a constraint system in the `.r1cs` format, over `F`. The public
+/// outputs and inputs of the file are the instance variables.
+pub fn read_r1cs<F: PrimeField>(bytes: &[u8]) -> Result<ConstraintMatrices<F>, Iden3Error> {
+    let sections = sections(bytes, R1CS_MAGIC, R1CS_VERSION)?;
+
+    let mut header = section(&sections, R1CS_HEADER)?;
+    header.field::<F>()?;
+    let num_wires = header.u32()? as usize;
+    let num_public_outputs = header.u32()? as usize;
+    let num_public_inputs = header.u32()? as usize;
+    let _num_private_inputs = header.u32()?;
+    let _num_labels = header.u64()?;
+    let num_constraints = header.u32()? as usize;
+    finish(header, R1CS_HEADER)?;
+    let num_instance_variables = 1 + num_public_outputs + num_public_inputs;
+    if num_instance_variables > num_wires {
+        return Err(Iden3Error::InvalidSection(R1CS_HEADER));
+    }
+
+    let mut reader = section(&sections, R1CS_CONSTRAINTS)?;
+    let (mut a, mut b, mut c): (Matrix<F>, Matrix<F>, Matrix<F>) = Default::default();
+    for i in 0..num_constraints {
+        a.push(read_matrix(&mut reader, i, num_wires)?);
+        b.push(read_matrix(&mut reader, i, num_wires)?);
+        c.push(read_matrix(&mut reader, i, num_wires)?);
+    }
+    finish(reader, R1CS_CONSTRAINTS)?;
+
+    let num_non_zero = |m: &Matrix<F>| m.iter().map(Vec::len).sum();
+    Ok(ConstraintMatrices {
+        num_instance_variables,
+        num_witness_variables: num_wires - num_instance_variables,
+        num_constraints,
+        a_num_non_zero: num_non_zero(&a),
+        b_num_non_zero: num_non_zero(&b),
+        c_num_non_zero: num_non_zero(&c),
+        a,
+        b,
+        c,
+    })
+}
+
+/// Reads the assignment of every wire in the `.wtns` format, over `F`,
+/// starting with the constant one.
+pub fn read_wtns<F: PrimeField>(bytes: &[u8]) -> Result<Vec<F>, Iden3Error> {
+    let sections = sections(bytes, WTNS_MAGIC, WTNS_VERSION)?;
+
+    let mut header = section(&sections, WTNS_HEADER)?;
+    header.field::<F>()?;
+    let num_values = header.u32()?;
+    finish(header, WTNS_HEADER)?;
+
+    let mut reader = section(&sections, WTNS_VALUES)?;
+    let values = (0..num_values)
+        .map(|_| reader.element())
+        .collect::<Result<_, _>>()?;
+    finish(reader, WTNS_VALUES)?;
+    Ok(values)
+}
@@ -15,8 +15,13 @@
 #[macro_use]
 extern crate ark_std;
 
+pub mod foaks;
+pub mod gadgets;
+pub mod groth16;
+pub mod iden3;
 pub mod mem_gen;
 pub mod r1cs;
+pub mod read_file;
 
 #[cfg(test)]
 mod tests {
@@ -25,29 +30,35 @@ mod tests {
     use crate::r1cs::*;
     use ark_ff::BigInteger256;
     use ark_test_curves::bls12_381::Fr;
+    use prime_field::ark03::Fp61;
     use std::{fs::File, io::Write};
+    /// Synthesizes `circuit` and checks whether it is satisfied.
+    fn synthesize<F: ark_ff::PrimeField>(
+        circuit: impl ConstraintSynthesizer<F>,
+    ) -> (ConstraintMatrices<F>, bool) {
+        let cs = ConstraintSystem::<F>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.finalize();
+        (cs.to_matrices().unwrap(), cs.is_satisfied().unwrap())
+    }
     #[test]
     fn mstore_check_val() {
+        use crate::mem_gen::mem_table::read_mem_table;
         let file_tx =
             File::open("./src/data/tx_mem_table.json").expect("file should open read only");
-        let json_tx: serde_json::Value =
-            serde_json::from_reader(file_tx).expect("file should be proper JSON");
-        let data = json_tx.get("data").expect("file should have data key");
-        let mWr = data.as_array().unwrap()[0]["m_wr"].as_u64().unwrap();
-        let mWr8 = data.as_array().unwrap()[0]["m_wr8"].as_u64().unwrap();
-        let val_src_0 = data.as_array().unwrap()[0]["val_src_0"].as_u64().unwrap();
-        let val_src_1 = data.as_array().unwrap()[0]["val_src_1"].as_u64().unwrap();
-        let val_src_2 = data.as_array().unwrap()[0]["val_src_2"].as_u64().unwrap();
-        let val_src_3 = data.as_array().unwrap()[0]["val_src_3"].as_u64().unwrap();
-        let val_dst_0 = data.as_array().unwrap()[0]["val_dst_0"].as_u64().unwrap();
-        let val_dst_1 = data.as_array().unwrap()[0]["val_dst_1"].as_u64().unwrap();
-        let val_dst_2 = data.as_array().unwrap()[0]["val_dst_2"].as_u64().unwrap();
-        let val_dst_3 = data.as_array().unwrap()[0]["val_dst_3"].as_u64().unwrap();
-
-        let matrices = val_check_matrix_gen(
-            mWr, mWr8, val_src_0, val_src_1, val_src_2, val_src_3, val_dst_0, val_dst_1, val_dst_2,
-            val_dst_3,
-        );
+        let row = read_mem_table(file_tx).expect("file should be a memory table")[0];
+
+        let (matrices, satisfied) = synthesize::<Fr>(ValCheckCircuit::from(&row));
+        assert!(satisfied);
+        // 1, out, mWr, mWr8, mid_1, then val_src and val_dst of each limb
+        // A [0,0,1,0,0,0,0]
+        // [1,0,0,0,1,0,0]
+        // B [0,0,0,1,0,0,0]
+        // [0,0,0,0,0,1,-1]
+        // C [0,0,0,0,1,0,0]
+        // [0,1,0,0,0,0,0]
+        // then 67 constraints of range checks per limb
+        assert_eq!(matrices.num_constraints, 5 + 8 * 67);
         assert_eq!(matrices.a[0], vec![(Fr::from(1u64), 2)]);
         assert_eq!(
             matrices.a[1],
@@ -55,21 +66,640 @@ mod tests {
         );
         assert_eq!(matrices.b[0], vec![(Fr::from(1u64), 3)]);
         assert_eq!(matrices.b[1], vec![(Fr::from(1u64), 5), (Fr::from(-1), 6)]);
+        assert_eq!(
+            matrices.b[4],
+            vec![(Fr::from(1u64), 11), (Fr::from(-1), 12)]
+        );
         assert_eq!(matrices.c[0], vec![(Fr::from(1u64), 4)]);
         assert_eq!(matrices.c[1], vec![(Fr::from(1u64), 1)]);
+
+        // a word write which changes the value, over another field
+        let mut circuit = ValCheckCircuit::from(&row);
+        assert!(synthesize::<ark_bls12_377::Fr>(circuit).1);
+        circuit.val_dst[3] += 1;
+        assert!(!synthesize::<ark_bls12_377::Fr>(circuit).1);
+
+        // limbs out of range, changed by the same amount
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        ValCheckCircuit::from(&row)
+            .generate_constraints(cs.clone())
+            .unwrap();
+        let shift = Fr::from(2u64).pow([64]);
+        {
+            let mut cs = cs.borrow_mut().unwrap();
+            // m_wr, m_wr8, mid_1, then val_src and val_dst of each limb
+            cs.witness_assignment[3] += shift;
+            cs.witness_assignment[4] += shift;
+        }
+        assert!(!cs.is_satisfied().unwrap());
+        // limbs of 64 bits do not fit in the field of zkFOAKS
+        let cs = ConstraintSystem::<Fp61>::new_ref();
+        assert_eq!(
+            ValCheckCircuit::from(&row).generate_constraints(cs),
+            Err(SynthesisError::Unsatisfiable)
+        );
     }
     #[test]
     fn mwr_bool_check() {
+        use crate::mem_gen::mem_table::read_mem_table;
+        let file_tx =
+            File::open("./src/data/tx_mem_table.json").expect("file should open read only");
+        let row = read_mem_table(file_tx).expect("file should be a memory table")[0];
+        let (matrices, satisfied) = synthesize::<Fr>(WriteCheckCircuit::from(&row));
+        assert!(satisfied);
+        // 1, out, mWr, mWr8
+        // A [1,0,-1,0]
+        // B [0,0,0,1]
+        // C [0,1,0,0]
+        assert_eq!(matrices.a[0], vec![(Fr::from(1u64), 0), (Fr::from(-1), 2)]);
+        assert_eq!(matrices.b[0], vec![(Fr::from(1u64), 3)]);
+        assert_eq!(matrices.c[0], vec![(Fr::from(1u64), 1)]);
+
+        // a single byte read
+        let circuit = WriteCheckCircuit { m_wr: 0, m_wr8: 1 };
+        assert!(!synthesize::<ark_bls12_377::Fr>(circuit).1);
+
+        // over the field of zkFOAKS
+        assert!(synthesize::<Fp61>(WriteCheckCircuit::from(&row)).1);
+        assert!(!synthesize::<Fp61>(circuit).1);
+    }
+    #[test]
+    fn mul_check() {
+        let (matrices, satisfied) = synthesize::<Fr>(MulCircuit { a: 3, b: 11, c: 33 });
+        assert!(satisfied);
+        // 1, a, b, c
+        assert_eq!(matrices.num_instance_variables, 2);
+        assert_eq!(matrices.a[0], vec![(Fr::from(1u64), 1)]);
+        assert_eq!(matrices.b[0], vec![(Fr::from(1u64), 2)]);
+        assert_eq!(matrices.c[0], vec![(Fr::from(1u64), 3)]);
+        assert!(!synthesize::<Fp61>(MulCircuit { a: 2, b: 7, c: 3 }).1);
+    }
+    #[test]
+    fn bool_check() {
+        let (matrices, satisfied) = synthesize::<Fr>(BoolCheckCircuit { x: 1 });
+        assert!(satisfied);
+        // one, out, x
+        // A [0,0,1]
+        // B [-1,0,1]
+        // C [0,1,0]
+        assert_eq!(matrices.a[0], vec![(Fr::from(1u64), 2)]);
+        assert_eq!(matrices.b[0], vec![(Fr::from(-1), 0), (Fr::from(1u64), 2)]);
+        assert_eq!(matrices.c[0], vec![(Fr::from(1u64), 1)]);
+        assert!(synthesize::<Fp61>(BoolCheckCircuit { x: 0 }).1);
+        assert!(!synthesize::<Fp61>(BoolCheckCircuit { x: 2 }).1);
+    }
+    #[test]
+    fn addr_inc_check() {
+        let addr = [u64::MAX, 1, 5, 0];
+        let circuit = AddrIncCheckCircuit {
+            last_access: 0,
+            addr_next: [u64::MAX, 1, 6, 0],
+            addr,
+        };
+        let (matrices, satisfied) = synthesize::<Fr>(circuit);
+        assert!(satisfied);
+        // 1, out, lastAccess, addr'[0..1], addr'[2..3], addr[0..1], addr[2..3], mid_1
+        assert_eq!(matrices.a[0], vec![(Fr::from(1u64), 0), (Fr::from(-1), 2)]);
+        assert_eq!(matrices.b[0], vec![(Fr::from(1u64), 3), (Fr::from(-1), 5)]);
+        assert_eq!(matrices.c[0], vec![(Fr::from(1u64), 7)]);
+        assert_eq!(matrices.a[1], vec![(Fr::from(1u64), 7)]);
+        assert_eq!(matrices.b[1], vec![(Fr::from(1u64), 4), (Fr::from(-1), 6)]);
+        assert_eq!(matrices.c[1], vec![(Fr::from(1u64), 1)]);
+
+        // both halves change, only after the last access
+        let circuit = AddrIncCheckCircuit {
+            addr_next: [0, 2, 6, 0],
+            ..circuit
+        };
+        assert!(!synthesize::<Fr>(circuit).1);
+        assert!(
+            synthesize::<ark_bls12_377::Fr>(AddrIncCheckCircuit {
+                last_access: 1,
+                ..circuit
+            })
+            .1
+        );
+    }
+    #[test]
+    fn mop_mwr_check() {
+        use crate::mem_gen::mem_table::read_mem_table;
         let file_tx =
             File::open("./src/data/tx_mem_table.json").expect("file should open read only");
-        let json_tx: serde_json::Value =
-            serde_json::from_reader(file_tx).expect("file should be proper JSON");
-        let data = json_tx.get("data").expect("file should have data key");
-        let mWr = data.as_array().unwrap()[0]["m_wr"].as_u64().unwrap();
-        let mWr8 = data.as_array().unwrap()[0]["m_wr8"].as_u64().unwrap();
-        let matrices = mWr_mWr8_check_matrix_gen(mWr, mWr8);
+        let row = read_mem_table(file_tx).expect("file should be a memory table")[0];
+        let (matrices, satisfied) = synthesize::<Fr>(OpCheckCircuit::from(&row));
+        assert!(satisfied);
+        // 1, out, mOp, mWr
+        // A [1,0,-1,0]
+        // B [0,0,0,1]
+        // C [0,1,0,0]
         assert_eq!(matrices.a[0], vec![(Fr::from(1u64), 0), (Fr::from(-1), 2)]);
         assert_eq!(matrices.b[0], vec![(Fr::from(1u64), 3)]);
         assert_eq!(matrices.c[0], vec![(Fr::from(1u64), 1)]);
+
+        // a write which is not a memory operation
+        let circuit = OpCheckCircuit { m_op: 0, m_wr: 1 };
+        assert!(!synthesize::<Fp61>(circuit).1);
+        assert!(synthesize::<Fp61>(OpCheckCircuit::from(&row)).1);
+    }
+    #[test]
+    fn update_value_check() {
+        let val = [1, 2, 3, 4, 5, 6, 7, 8];
+        let mut val_next = val;
+        val_next[5] += 1;
+        let circuit = UpdateValueCheckCircuit {
+            m_op_next: 1,
+            m_wr_next: 0,
+            last_access: 0,
+            val_next,
+            val,
+        };
+        let (matrices, satisfied) = synthesize::<Fr>(circuit);
+        assert!(satisfied);
+        // 1, out, mid_1, mid_2, mid_3, mOp, mWr, lastAccess, val_p1, val_1, val_p2, val_2
+        // A [0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0]
+        //   [1, 0, -1, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        //   [0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]
+        //   [0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]
+        // B [0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0]
+        //   [1, 0, 0, 0, 0, 0, 0, -1, 0, 0, 0, 0]
+        //   [0, 0, 0, 0, 0, 0, 0, 0, 1, -1, 0, 0]
+        //   [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, -1]
+        // C [0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        //   [0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]
+        //   [0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]
+        //   [0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        assert_eq!(matrices.a[0], vec![(Fr::from(1u64), 5)]);
+        assert_eq!(matrices.b[0], vec![(Fr::from(1u64), 6)]);
+        assert_eq!(matrices.c[0], vec![(Fr::from(1u64), 2)]);
+
+        assert_eq!(matrices.a[2], vec![(Fr::from(1u64), 3)]);
+        assert_eq!(matrices.a[3], vec![(Fr::from(1u64), 4)]);
+
+        assert_eq!(matrices.c[1], vec![(Fr::from(1u64), 3)]);
+        assert_eq!(matrices.c[2], vec![(Fr::from(1u64), 4)]);
+        assert_eq!(matrices.c[3], vec![(Fr::from(1u64), 1)]);
+
+        // both halves change, only on a write or after the last access
+        let mut circuit = circuit;
+        circuit.val_next[0] += 1;
+        assert!(!synthesize::<Fr>(circuit).1);
+        assert!(
+            synthesize::<ark_bls12_377::Fr>(UpdateValueCheckCircuit {
+                m_wr_next: 1,
+                ..circuit
+            })
+            .1
+        );
+        assert!(
+            synthesize::<ark_bls12_377::Fr>(UpdateValueCheckCircuit {
+                last_access: 1,
+                ..circuit
+            })
+            .1
+        );
+    }
+    #[test]
+    fn update_value_mul_check() {
+        let circuit = UpdateValueMulCheckCircuit {
+            m_op_next: 1,
+            m_wr_next: 0,
+            last_access: 1,
+            val_next: [0, 0, 0, 0, 5, 6, 7, 8],
+        };
+        let (matrices, satisfied) = synthesize::<Fr>(circuit);
+        assert!(satisfied);
+        // one, out, mid_1, mid_2, mid_3, mOp, mWr, lastAccess, val_p1, val_p2
+        // A [0, 0, 0, 0, 0, 1, 0, 0, 0, 0]
+        //   [1, 0, -1, 0, 0, 0, 0, 0, 0, 0]
+        //   [0, 0, 0, 1, 0, 0, 0, 0, 0, 0]
+        //   [0, 0, 0, 0, 1, 0, 0, 0, 0, 0]
+        // B [0, 0, 0, 0, 0, 0, 1, 0, 0, 0]
+        //   [0, 0, 0, 0, 0, 0, 0, 1, 0, 0]
+        //   [0, 0, 0, 0, 0, 0, 0, 0, 1, 0]
+        //   [0, 0, 0, 0, 0, 0, 0, 0, 0, 1]
+        // C [0, 0, 1, 0, 0, 0, 0, 0, 0, 0]
+        //   [0, 0, 0, 1, 0, 0, 0, 0, 0, 0]
+        //   [0, 0, 0, 0, 1, 0, 0, 0, 0, 0]
+        //   [0, 1, 0, 0, 0, 0, 0, 0, 0, 0]
+        assert_eq!(matrices.a[0], vec![(Fr::from(1u64), 5)]);
+        assert_eq!(matrices.a[2], vec![(Fr::from(1u64), 3)]);
+        assert_eq!(matrices.a[3], vec![(Fr::from(1u64), 4)]);
+
+        assert_eq!(matrices.b[0], vec![(Fr::from(1u64), 6)]);
+        assert_eq!(matrices.b[1], vec![(Fr::from(1u64), 7)]);
+        assert_eq!(matrices.b[2], vec![(Fr::from(1u64), 8)]);
+        assert_eq!(matrices.b[3], vec![(Fr::from(1u64), 9)]);
+
+        assert_eq!(matrices.c[0], vec![(Fr::from(1u64), 2)]);
+        assert_eq!(matrices.c[1], vec![(Fr::from(1u64), 3)]);
+        assert_eq!(matrices.c[2], vec![(Fr::from(1u64), 4)]);
+        assert_eq!(matrices.c[3], vec![(Fr::from(1u64), 1)]);
+
+        // both halves are set, only on a write or before the last access
+        let mut circuit = circuit;
+        circuit.val_next[3] = 1 << 31;
+        assert!(!synthesize::<Fr>(circuit).1);
+        assert!(
+            synthesize::<ark_bls12_377::Fr>(UpdateValueMulCheckCircuit {
+                m_wr_next: 1,
+                ..circuit
+            })
+            .1
+        );
+        assert!(
+            synthesize::<ark_bls12_377::Fr>(UpdateValueMulCheckCircuit {
+                last_access: 0,
+                ..circuit
+            })
+            .1
+        );
+    }
+    #[test]
+    fn foaks_export() {
+        use crate::foaks::*;
+        // x^3 + x + 5 = out, as in `R1CS/constraint_systems/sample_test.rs`
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let three = Fr::from(3u64);
+        let out = cs.new_input_variable(|| Ok(Fr::from(35u64))).unwrap();
+        let x = cs.new_witness_variable(|| Ok(three)).unwrap();
+        let sym_1 = cs.new_witness_variable(|| Ok(three * three)).unwrap();
+        let y = cs
+            .new_witness_variable(|| Ok(three * three * three))
+            .unwrap();
+        let sym_2 = cs.new_witness_variable(|| Ok(Fr::from(30u64))).unwrap();
+        cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + sym_1)
+            .unwrap();
+        cs.enforce_constraint(lc!() + sym_1, lc!() + x, lc!() + y)
+            .unwrap();
+        cs.enforce_constraint(lc!() + y + x, lc!() + Variable::One, lc!() + sym_2)
+            .unwrap();
+        cs.enforce_constraint(
+            lc!() + sym_2 + (Fr::from(5u64), Variable::One),
+            lc!() + Variable::One,
+            lc!() + out,
+        )
+        .unwrap();
+        cs.finalize();
+
+        let (r1cs, assignment) = export_to_foaks(&cs).unwrap();
+        assert_eq!(
+            r1cs,
+            include_str!("../../foaks/spartan/tests/data/cubic_r1cs.txt")
+        );
+        assert_eq!(
+            assignment,
+            include_str!("../../foaks/spartan/tests/data/cubic_assignment.txt")
+        );
+
+        let (matrices, _) = synthesize::<Fr>(WriteCheckCircuit { m_wr: 1, m_wr8: 0 });
+        assert_eq!(
+            write_foaks_r1cs(&matrices).unwrap(),
+            "1 2 2\n2 0 1 2 -1\n1 3 1\n1 1 1\n"
+        );
+        let val_src = Fr::from(BigInteger256::new([0, 0, 0, 128]));
+        assert_eq!(
+            write_foaks_assignment(&[Fr::from(1u64)], &[Fr::from(1u64), val_src]),
+            Err(ExportError::ValueOutOfRange { index: 2 })
+        );
+        assert_eq!(to_foaks_integer(-Fr::from(7u64)), Some(-7));
+    }
+    #[test]
+    fn typed_ingestion() {
+        use crate::mem_gen::mem_table::*;
+        use crate::read_file::*;
+        let open = |path| File::open(path).expect("file should open read only");
+        let sorted = read_mem_table(open("./src/data/sorted_mem_table.json")).unwrap();
+        assert_eq!(validate_mem_table(&sorted), Ok(()));
+        let unsorted = read_mem_table(open("./src/data/tx_mem_table.json")).unwrap();
+        let errors = validate_mem_table(&unsorted).unwrap_err();
+        assert_eq!(
+            errors[0],
+            RowError {
+                row: 1,
+                kind: RowErrorKind::NotSorted
+            }
+        );
+
+        // eight registers, `ISNOLAST` and `mWr`
+        let registers = read_mem_table(open("./src/data/mem_test.json")).unwrap();
+        assert_eq!(validate_mem_table(&registers), Ok(()));
+        assert_eq!(registers.len(), 32);
+        assert_eq!(registers[31].val_dst(), [128 << 32, 0, 0, 0]);
+
+        // rows are reported with their index
+        let mut json = std::fs::read_to_string("./src/data/mem_test.json").unwrap();
+        json = json.replacen("\"val3\": 0", "\"val3\": 4294967296", 1);
+        let (first, rest) = json.split_at(json.find("\"step\": 1,").unwrap());
+        json = first.to_string() + &rest.replacen("\"mWr\": 0", "\"mWr\": -1", 2);
+        match read_mem_table(json.as_bytes()) {
+            Err(ReadError::Rows(errors)) => {
+                let rows: Vec<_> = errors.iter().map(|e| e.row).collect();
+                assert_eq!(rows, [0, 1, 2]);
+                assert_eq!(
+                    errors[0].kind,
+                    RowErrorKind::OutOfRange {
+                        field: "val3",
+                        bits: 32
+                    }
+                );
+                assert!(matches!(errors[1].kind, RowErrorKind::Malformed(_)));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        // a missing flag is not read as zero
+        let json = std::fs::read_to_string("./src/data/tx_mem_table.json").unwrap();
+        let json = json.replacen("\"m_wr8\"", "\"m_wr9\"", 2);
+        match read_mem_table(json.as_bytes()) {
+            Err(ReadError::Rows(errors)) => {
+                let rows: Vec<_> = errors.iter().map(|e| e.row).collect();
+                assert_eq!(rows, [0, 1]);
+                assert!(errors[0].to_string().contains("m_wr8"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        let mut flags = sorted.clone();
+        flags[2].m_wr8 = 2;
+        flags[3].address = 1 << 40;
+        assert_eq!(
+            validate_mem_table(&flags).unwrap_err()[..2],
+            [
+                RowError {
+                    row: 2,
+                    kind: RowErrorKind::NotBoolean("m_wr8")
+                },
+                RowError {
+                    row: 3,
+                    kind: RowErrorKind::OutOfRange {
+                        field: "address",
+                        bits: ADDRESS_BITS
+                    }
+                }
+            ]
+        );
+
+        // the table of the trace is the sorted table, without byte writes
+        let trace = read_trace(open("./src/data/trace.json")).unwrap();
+        assert_eq!(validate_trace(&trace), Ok(()));
+        let mut expected = sorted;
+        expected.iter_mut().for_each(|row| row.m_wr8 = 0);
+        assert_eq!(mem_table_from_trace(&trace), expected);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        MemCheckCircuit::from_trace(trace.clone())
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        let mut swapped = trace;
+        swapped.swap(3, 4);
+        assert_eq!(
+            validate_trace(&swapped),
+            Err(vec![RowError {
+                row: 4,
+                kind: RowErrorKind::NotSorted
+            }])
+        );
+    }
+    #[test]
+    fn groth16_mem_check() {
+        use crate::groth16::*;
+        use crate::mem_gen::mem_table::*;
+        use crate::read_file::read_trace;
+        use ark_bls12_381::{Bls12_381, Fr};
+        use ark_groth16::{Proof, VerifyingKey};
+        use ark_std::test_rng;
+        let open = |path| File::open(path).expect("file should open read only");
+        let trace = read_trace(open("./src/data/trace.json")).unwrap();
+        let mut rng = test_rng();
+        let circuit = Synthesized::new(MemCheckCircuit::<Fr>::from_trace(trace.clone())).unwrap();
+        // the verifier derives the public inputs from the trace
+        let public_inputs = MemCheckCircuit::<Fr>::instance(&trace, &mem_table_from_trace(&trace));
+        assert_eq!(circuit.public_inputs(), public_inputs);
+
+        let pk = setup::<Bls12_381, _>(circuit.clone(), &mut rng).unwrap();
+        let proof = prove(&pk, circuit, &mut rng).unwrap();
+        let vk: VerifyingKey<Bls12_381> = from_bytes(&to_bytes(&pk.vk).unwrap()).unwrap();
+        let proof: Proof<Bls12_381> = from_bytes(&to_bytes(&proof).unwrap()).unwrap();
+        assert!(verify(&vk, &public_inputs, &proof).unwrap());
+
+        // other challenges, or another trace
+        let mut tampered = public_inputs.clone();
+        tampered[0] += Fr::from(1u64);
+        assert!(!verify(&vk, &tampered, &proof).unwrap());
+        let mut other = trace;
+        other[6].value[3] += 1;
+        let tampered = MemCheckCircuit::<Fr>::instance(&other, &mem_table_from_trace(&other));
+        assert!(!verify(&vk, &tampered, &proof).unwrap());
+        assert!(from_bytes::<Proof<Bls12_381>>(&[0; 8]).is_err());
+    }
+    #[test]
+    fn iden3_round_trip() {
+        use crate::iden3::*;
+        use crate::mem_gen::mem_table::*;
+        let table =
+            read_mem_table(File::open("./src/data/sorted_mem_table.json").unwrap()).unwrap();
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        MemTableCircuit::new(table)
+            .generate_constraints(cs.clone())
+            .unwrap();
+        cs.finalize();
+        let (r1cs, wtns) = export_to_iden3(&cs).unwrap();
+        assert_eq!(&r1cs[..12], b"r1cs\x01\0\0\0\x03\0\0\0");
+        assert_eq!(&wtns[..12], b"wtns\x02\0\0\0\x02\0\0\0");
+        // the field size, then the modulus
+        assert_eq!(&r1cs[24..28], &[32, 0, 0, 0]);
+        assert_eq!(r1cs[28], 1);
+
+        let matrices = cs.to_matrices().unwrap();
+        let read = read_r1cs::<Fr>(&r1cs).unwrap();
+        assert_eq!(read.num_instance_variables, matrices.num_instance_variables);
+        assert_eq!(read.num_witness_variables, matrices.num_witness_variables);
+        assert_eq!(read.num_constraints, matrices.num_constraints);
+        assert_eq!(read.a_num_non_zero, matrices.a_num_non_zero);
+        assert_eq!(
+            (read.a, read.b, read.c),
+            (matrices.a, matrices.b, matrices.c)
+        );
+        let cs = cs.borrow().unwrap();
+        let assignment = [&cs.instance_assignment[..], &cs.witness_assignment].concat();
+        assert_eq!(read_wtns::<Fr>(&wtns).unwrap(), assignment);
+
+        let mut tampered = r1cs.clone();
+        tampered[28] ^= 1;
+        assert_eq!(
+            read_r1cs::<Fr>(&tampered).err(),
+            Some(Iden3Error::FieldMismatch)
+        );
+        assert_eq!(read_r1cs::<Fr>(&wtns).err(), Some(Iden3Error::InvalidMagic));
+        // a huge number of sections in a short file
+        let mut tampered = r1cs[..12].to_vec();
+        tampered[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            read_r1cs::<Fr>(&tampered).err(),
+            Some(Iden3Error::UnexpectedEnd)
+        );
+        assert_eq!(
+            read_wtns::<Fr>(&wtns[..wtns.len() - 1]).err(),
+            Some(Iden3Error::UnexpectedEnd)
+        );
+        let mut tampered = wtns;
+        let len = tampered.len();
+        tampered[len - 32..].copy_from_slice(&[0xff; 32]);
+        assert_eq!(
+            read_wtns::<Fr>(&tampered).err(),
+            Some(Iden3Error::InvalidFieldElement)
+        );
+    }
+    #[test]
+    fn iden3_circom_fixtures() {
+        use crate::iden3::*;
+        use ark_bn254::Fr;
+        // the multiplier of `multiplier.circom` compiled by circom 1 and 2 over
+        // bn254, with the witness of its circom 2 wasm for a = 3, b = 11
+        let wtns = std::fs::read("./src/data/circom/multiplier.wtns").unwrap();
+        let z = read_wtns::<Fr>(&wtns).unwrap();
+        assert_eq!(z, [1u64, 33, 3, 11].map(Fr::from));
+        let dot =
+            |row: &Vec<(Fr, usize)>, z: &[Fr]| row.iter().map(|(v, i)| *v * z[*i]).sum::<Fr>();
+        for file in ["multiplier_circom1.r1cs", "multiplier_circom2.r1cs"] {
+            let r1cs = std::fs::read(format!("./src/data/circom/{}", file)).unwrap();
+            let r1cs = read_r1cs::<Fr>(&r1cs).unwrap();
+            // the constant and the output c are public, a and b are private
+            assert_eq!(r1cs.num_instance_variables, 2);
+            assert_eq!(r1cs.num_witness_variables, 2);
+            assert_eq!(r1cs.num_constraints, 1);
+            let satisfied = |z: &[Fr]| {
+                (0..r1cs.num_constraints)
+                    .all(|k| dot(&r1cs.a[k], z) * dot(&r1cs.b[k], z) == dot(&r1cs.c[k], z))
+            };
+            assert!(satisfied(&z));
+            let mut tampered = z.clone();
+            tampered[1] += Fr::from(1u64);
+            assert!(!satisfied(&tampered));
+        }
+        // the witness is over bn254, not the scalar field of bls12-381
+        assert_eq!(
+            read_wtns::<ark_bls12_381::Fr>(&wtns).err(),
+            Some(Iden3Error::FieldMismatch)
+        );
+    }
+    #[test]
+    fn mem_table_consistency() {
+        use crate::mem_gen::mem_table::*;
+        let read = |path| {
+            read_mem_table(File::open(path).expect("file should open read only"))
+                .expect("file should be a memory table")
+        };
+        let sorted = read("./src/data/sorted_mem_table.json");
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        MemTableCircuit::new(sorted.clone())
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        assert_eq!(
+            MemTableCircuit::new(sorted.clone()).failing_row::<Fr>(),
+            Ok(None)
+        );
+
+        // rows in step order, the address changes without a last access
+        let unsorted = MemTableCircuit::new(read("./src/data/tx_mem_table.json"));
+        assert_eq!(unsorted.failing_row::<Fr>(), Ok(Some(1)));
+
+        // a read returning another value than the previous write
+        let mut rows = sorted.clone();
+        assert_eq!(
+            (rows[2].address, rows[3].address, rows[3].m_wr),
+            (16, 16, 0)
+        );
+        rows[3].val_src_1 += 1;
+        rows[3].val_dst_1 += 1;
+        assert_eq!(MemTableCircuit::new(rows).failing_row::<Fr>(), Ok(Some(3)));
+
+        // a first read of a non zero value, and a missing last access
+        let mut rows = sorted.clone();
+        rows[0].m_wr = 0;
+        assert_eq!(MemTableCircuit::new(rows).failing_row::<Fr>(), Ok(Some(0)));
+        let mut rows = sorted;
+        rows.last_mut().unwrap().last_access = 0;
+        assert_eq!(
+            MemTableCircuit::new(rows.clone()).failing_row::<Fr>(),
+            Ok(Some(rows.len() - 1))
+        );
+    }
+    #[test]
+    fn mem_check_permutation() {
+        use crate::gadgets::permutation::Challenges;
+        use crate::mem_gen::mem_table::*;
+        use crate::read_file::read_trace;
+        use ark_std::test_rng;
+        let open = |path| File::open(path).expect("file should open read only");
+        let table = read_mem_table(open("./src/data/sorted_mem_table.json")).unwrap();
+        let trace = read_trace(open("./src/data/trace.json")).unwrap();
+        let mut rng = test_rng();
+        let mut satisfied = |trace, table| {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            MemCheckCircuit {
+                trace,
+                table: MemTableCircuit::new(table),
+                challenges: Challenges::sample(&mut rng),
+            }
+            .generate_constraints(cs.clone())
+            .unwrap();
+            assert_eq!(cs.num_instance_variables(), 3 + 16 * 8);
+            cs.is_satisfied().unwrap()
+        };
+        assert!(satisfied(trace.clone(), table.clone()));
+
+        // the trace reads another value than the table
+        let mut tampered = trace.clone();
+        assert!(!tampered[6].m_wr);
+        tampered[6].value[3] += 1;
+        assert!(!satisfied(tampered, table.clone()));
+
+        // a table consistent on its own, with a read at another step
+        let mut tampered = table;
+        assert_eq!(tampered[3].step, 282);
+        tampered[3].step += 1;
+        assert_eq!(
+            MemTableCircuit::new(tampered.clone()).failing_row::<Fr>(),
+            Ok(None)
+        );
+        assert!(!satisfied(trace, tampered));
+    }
+    #[test]
+    fn range_checks() {
+        use crate::gadgets::range::*;
+        let in_range = |value: u64, bits| {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let x = cs.new_witness_variable(|| Ok(Fr::from(value))).unwrap();
+            enforce_range(&cs, lc!() + x, Fr::from(value), bits).unwrap();
+            assert_eq!(cs.num_constraints(), bits + 1);
+            cs.is_satisfied().unwrap()
+        };
+        assert!(in_range(u32::MAX as u64, 32));
+        assert!(!in_range(1 << 32, 32));
+        assert!(in_range(4095, 12));
+        assert!(!in_range(4096, 12));
+        assert!(in_range(u64::MAX, 64));
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let value = Fr::from(0x1234_5678_9abc_def0u64);
+        let x = cs.new_witness_variable(|| Ok(value)).unwrap();
+        let limbs = decompose(&cs, lc!() + x, value, 32, 2).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        assert_eq!(limbs[0].1, Fr::from(0x9abc_def0u64));
+        assert_eq!(limbs[1].1, Fr::from(0x1234_5678u64));
+
+        // the sum of the bits would wrap around the modulus
+        let cs = ConstraintSystem::<Fp61>::new_ref();
+        let x = cs.new_witness_variable(|| Ok(Fp61::from(1u64))).unwrap();
+        assert!(enforce_range(&cs, lc!() + x, Fp61::from(1u64), 60).is_ok());
+        assert_eq!(
+            enforce_range(&cs, lc!() + x, Fp61::from(1u64), 61),
+            Err(SynthesisError::Unsatisfiable)
+        );
+        assert_eq!(
+            decompose(&cs, lc!() + x, Fp61::from(1u64), 32, 2).map(|_| ()),
+            Err(SynthesisError::Unsatisfiable)
+        );
     }
 }
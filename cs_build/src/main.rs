@@ -1,24 +1,54 @@
-use serde::Deserialize;
-
 use std::error::Error;
-
-use std::io::BufReader;
+use std::fs::File;
+use std::io::{BufReader, Write};
 use std::path::Path;
-mod mem_gen;
-mod r1cs;
-use crate::mem_gen::*;
-use crate::r1cs::*;
 
-use ark_ff::PrimeField;
-use ark_test_curves::bls12_381::Fr;
-use std::{fs::File, io::Write};
-//use mem_gen::{mem_gen, r1cs_to_qap, *};
-// use crate::mem_gen::generate_qap;
-use crate::mem_gen::mem_gen::*;
-// use crate::mem_gen::r1cs_to_qap::{LibsnarkReduction, R1CStoQAP};
-// use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
-fn print_type_of<T>(_: &T) {
-    println!("{}", std::any::type_name::<T>())
-}
+use ark_bls12_381::Bls12_381;
+use cs_build::groth16::*;
+use cs_build::mem_gen::mem_table::{mem_table_from_trace, MemCheckCircuit};
+use cs_build::read_file::{read_trace, validate_trace, ReadError};
+
+/// Proves the memory checks of the trace of `src/data` with Groth16 and writes
+/// the keys and the proof in the directory given as argument, `target/groth16`
+/// by default.
+fn main() -> std::result::Result<(), Box<dyn Error>> {
+    use std::time::Instant;
 
-fn main() {}
+    let out = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "target/groth16".to_string());
+    let out = Path::new(&out);
+    std::fs::create_dir_all(out)?;
+
+    let trace = read_trace(BufReader::new(File::open("./src/data/trace.json")?))?;
+    validate_trace(&trace).map_err(ReadError::Rows)?;
+    let mut rng = rand::rngs::OsRng;
+    let circuit = Synthesized::new(MemCheckCircuit::from_trace(trace.clone()))?;
+    println!("{} constraints", circuit.matrices.num_constraints);
+
+    let start = Instant::now();
+    let pk = setup::<Bls12_381, _>(circuit.clone(), &mut rng)?;
+    println!("setup: {:?}", start.elapsed());
+    let start = Instant::now();
+    let proof = prove(&pk, circuit, &mut rng)?;
+    println!("prove: {:?}", start.elapsed());
+    // the verifier derives the challenges from the trace
+    let start = Instant::now();
+    let public_inputs = MemCheckCircuit::instance(&trace, &mem_table_from_trace(&trace));
+    let verified = verify(&pk.vk, &public_inputs, &proof)?;
+    println!("verify: {:?} ({})", start.elapsed(), verified);
+    if !verified {
+        return Err("the proof does not verify".into());
+    }
+
+    for (name, bytes) in [
+        ("proving_key.bin", to_bytes(&pk)?),
+        ("verifying_key.bin", to_bytes(&pk.vk)?),
+        ("public_inputs.bin", to_bytes(&public_inputs)?),
+        ("proof.bin", to_bytes(&proof)?),
+    ] {
+        File::create(out.join(name))?.write_all(&bytes)?;
+        println!("{}: {} bytes", name, bytes.len());
+    }
+    Ok(())
+}
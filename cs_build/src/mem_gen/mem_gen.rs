@@ -1,103 +1,324 @@
+//! Checks of a single row of a memory table, or of a row and the next one, as
+//! in `R1CS/constraint_systems/docs/MemoryOperationMatricesConfiguration.md`.
+//! [`MemTableCircuit`](super::mem_table::MemTableCircuit) enforces them on
+//! every row of a table.
+
+use ark_ff::PrimeField;
+
+use super::mem_table::{MemRow, REGISTER_BITS};
+use crate::gadgets::range::decompose;
 use crate::lc;
 use crate::r1cs::*;
-use ark_ff::BigInteger256;
-use ark_test_curves::bls12_381::Fr;
 
-// Constraint: (1+mWr*mWr8)(val_src-val_dst) = 0
+/// `limbs` of `bits` bits, least significant first, as a field element
+fn pack<F: PrimeField>(limbs: &[u64], bits: u64) -> F {
+    let base = F::from(2u64).pow([bits]);
+    limbs
+        .iter()
+        .rev()
+        .fold(F::zero(), |acc, limb| acc * base + F::from(*limb))
+}
+
+// A*B=C
+/// `a * b = c`, with a public `a`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MulCircuit {
+    /// Public factor
+    pub a: u64,
+    /// Private factor
+    pub b: u64,
+    /// Product
+    pub c: u64,
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for MulCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<()> {
+        let a = cs.new_input_variable(|| Ok(F::from(self.a)))?;
+        let b = cs.new_witness_variable(|| Ok(F::from(self.b)))?;
+        let c = cs.new_witness_variable(|| Ok(F::from(self.c)))?;
+        cs.enforce_constraint(lc!() + a, lc!() + b, lc!() + c)
+    }
+}
+
+// Constraint: (x)*(x-1)=0
+// x∈(lastAccess,mOp,mWr)
+/// A flag, such as `lastAccess`, `mOp` or `mWr`, is zero or one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoolCheckCircuit {
+    /// Flag
+    pub x: u64,
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for BoolCheckCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<()> {
+        let x = cs.new_witness_variable(|| Ok(F::from(self.x)))?;
+        let out = cs.new_input_variable(|| Ok(F::zero()))?;
+        cs.enforce_constraint(lc!() + x, lc!() + x - Variable::One, lc!() + out)
+    }
+}
+
+// Constraint: (1+mWr*mWr8)(val_src-val_dst) = 0 on each limb
 // mid_1 = mWr*mWr8
 // out = (1+mid_1)*(val_src-val_dst)
-pub fn val_check_matrix_gen(
-    mWr_in: u64,
-    mWr8_in: u64,
-    val_src_0_in: u64,
-    val_src_1_in: u64,
-    val_src_2_in: u64,
-    val_src_3_in: u64,
-    val_dst_0_in: u64,
-    val_dst_1_in: u64,
-    val_dst_2_in: u64,
-    val_dst_3_in: u64,
-) -> ConstraintMatrices<Fr> {
-    let cs = ConstraintSystem::<Fr>::new_ref();
-    let mWr = Fr::from(mWr_in);
-    let mWr8 = Fr::from(mWr8_in);
-    let mid_1 = mWr * mWr8;
-    let val_src = Fr::from(BigInteger256::new([
-        val_src_0_in,
-        val_src_1_in,
-        val_src_2_in,
-        val_src_3_in,
-    ]));
-    let val_dst = Fr::from(BigInteger256::new([
-        val_dst_0_in,
-        val_dst_1_in,
-        val_dst_2_in,
-        val_dst_3_in,
-    ]));
-    let one = Fr::from(1u64);
-
-    let mWr = cs.new_witness_variable(|| Ok(mWr)).unwrap();
-    let mWr8 = cs.new_witness_variable(|| Ok(mWr8)).unwrap();
-    let mid_1 = cs.new_witness_variable(|| Ok(mid_1)).unwrap();
-    let val_src = cs.new_witness_variable(|| Ok(val_src)).unwrap();
-    let val_dst = cs.new_witness_variable(|| Ok(val_dst)).unwrap();
-    let out = cs.new_input_variable(|| Ok(Fr::from(0u64))).unwrap();
-    cs.enforce_constraint(lc!() + mWr, lc!() + mWr8, lc!() + mid_1)
-        .unwrap();
-    cs.enforce_constraint(
-        lc!() + (one, Variable::One) + mid_1,
-        lc!() + val_src - val_dst,
-        lc!() + out,
-    )
-    .unwrap();
-    cs.finalize();
-
-    assert!(cs.is_satisfied().is_ok());
-    let matrices = cs.to_matrices().unwrap();
-    // 1, out, mWr, mWr8, mid_1, val_src, val_dst
-    // A [0,0,1,0,0,0,0]
-    // [1,0,0,0,1,0,0]
-    // B [0,0,0,1,0,0,0]
-    // [0,0,0,0,0,1,-1]
-    // C [0,0,0,0,1,0,0]
-    // [0,1,0,0,0,0,0]
-    assert_eq!(matrices.a[0], vec![(Fr::from(1u64), 2)]);
-    assert_eq!(
-        matrices.a[1],
-        vec![(Fr::from(1u64), 0), (Fr::from(1u64), 4)]
-    );
-    assert_eq!(matrices.b[0], vec![(Fr::from(1u64), 3)]);
-    assert_eq!(matrices.b[1], vec![(Fr::from(1u64), 5), (Fr::from(-1), 6)]);
-    assert_eq!(matrices.c[0], vec![(Fr::from(1u64), 4)]);
-    assert_eq!(matrices.c[1], vec![(Fr::from(1u64), 1)]);
-    matrices
+/// The value never changes: `1 + mWr*mWr8` is one or two for boolean flags,
+/// so the constraint forbids any change, byte writes included. The limbs are
+/// range checked as two registers of [`REGISTER_BITS`] bits, as in
+/// [`MemTableCircuit`].
+///
+/// [`MemTableCircuit`]: super::mem_table::MemTableCircuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValCheckCircuit {
+    /// Write
+    pub m_wr: u64,
+    /// Write of a single byte
+    pub m_wr8: u64,
+    /// Limbs of the value before the access
+    pub val_src: [u64; 4],
+    /// Limbs of the value after the access
+    pub val_dst: [u64; 4],
+}
+
+impl From<&MemRow> for ValCheckCircuit {
+    fn from(row: &MemRow) -> Self {
+        Self {
+            m_wr: row.m_wr,
+            m_wr8: row.m_wr8,
+            val_src: row.val_src(),
+            val_dst: row.val_dst(),
+        }
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for ValCheckCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<()> {
+        let m_wr = F::from(self.m_wr);
+        let m_wr8 = F::from(self.m_wr8);
+        let m_wr = cs.new_witness_variable(|| Ok(m_wr))?;
+        let m_wr8 = cs.new_witness_variable(|| Ok(m_wr8))?;
+        let mid_1 = cs.new_witness_variable(|| Ok(F::from(self.m_wr) * F::from(self.m_wr8)))?;
+        let out = cs.new_input_variable(|| Ok(F::zero()))?;
+        cs.enforce_constraint(lc!() + m_wr, lc!() + m_wr8, lc!() + mid_1)?;
+        let mut limbs = Vec::with_capacity(8);
+        for (src, dst) in self.val_src.into_iter().zip(self.val_dst) {
+            let src = (cs.new_witness_variable(|| Ok(F::from(src)))?, src);
+            let dst = (cs.new_witness_variable(|| Ok(F::from(dst)))?, dst);
+            cs.enforce_constraint(
+                lc!() + Variable::One + mid_1,
+                lc!() + src.0 - dst.0,
+                lc!() + out,
+            )?;
+            limbs.extend([src, dst]);
+        }
+        for (x, value) in limbs {
+            decompose(&cs, lc!() + x, F::from(value), REGISTER_BITS, 2)?;
+        }
+        Ok(())
+    }
 }
 
 // Constraint: (1-mWr)mWr8 = 0
-pub fn mWr_mWr8_check_matrix_gen(mWr_in: u64, mWr8_in: u64) -> ConstraintMatrices<Fr> {
-    let cs = ConstraintSystem::<Fr>::new_ref();
-    let mWr = Fr::from(mWr_in);
-    let mWr8 = Fr::from(mWr8_in);
-    let one = Fr::from(1u64);
-    let mWr = cs.new_witness_variable(|| Ok(mWr)).unwrap();
-    let mWr8 = cs.new_witness_variable(|| Ok(mWr8)).unwrap();
-    let out = cs.new_input_variable(|| Ok(Fr::from(0u64))).unwrap();
-    cs.enforce_constraint(
-        lc!() + (one, Variable::One) - mWr,
-        lc!() + mWr8,
-        lc!() + out,
-    )
-    .unwrap();
-    cs.finalize();
-
-    assert!(cs.is_satisfied().is_ok());
-    let matrices = cs.to_matrices().unwrap();
-    // 1, out, mOp, mWr
-    // A [1,0,-1,0]
-    // B [0,0,0,1]
-    // C [0,1,0,0]
-    assert_eq!(matrices.a[0], vec![(Fr::from(1u64), 0), (Fr::from(-1), 2)]);
-    assert_eq!(matrices.b[0], vec![(Fr::from(1u64), 3)]);
-    assert_eq!(matrices.c[0], vec![(Fr::from(1u64), 1)]);
-    matrices
+/// Only writes write a single byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteCheckCircuit {
+    /// Write
+    pub m_wr: u64,
+    /// Write of a single byte
+    pub m_wr8: u64,
+}
+
+impl From<&MemRow> for WriteCheckCircuit {
+    fn from(row: &MemRow) -> Self {
+        Self {
+            m_wr: row.m_wr,
+            m_wr8: row.m_wr8,
+        }
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for WriteCheckCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<()> {
+        let m_wr = cs.new_witness_variable(|| Ok(F::from(self.m_wr)))?;
+        let m_wr8 = cs.new_witness_variable(|| Ok(F::from(self.m_wr8)))?;
+        let out = cs.new_input_variable(|| Ok(F::zero()))?;
+        cs.enforce_constraint(lc!() + Variable::One - m_wr, lc!() + m_wr8, lc!() + out)
+    }
+}
+
+/*---------------------------circuit refer from polygon zkevm----------------------------*/
+
+// Constraint: (1-lastAccess)*(addr'[0..1]-addr[0..1])*(addr'[2..3]-addr[2..3])=0
+// mid_1 = (1-lastAccess)*(addr'[0..1]-addr[0..1])
+// out = mid_1*(addr'[2..3]-addr[2..3])
+/// Unless this is the last access to the address, the next row keeps one of
+/// the halves of the address. The limbs have 64 bits, least significant
+/// first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddrIncCheckCircuit {
+    /// Last access to the address
+    pub last_access: u64,
+    /// Limbs of the address of the next row
+    pub addr_next: [u64; 4],
+    /// Limbs of the address
+    pub addr: [u64; 4],
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for AddrIncCheckCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<()> {
+        let last_access = F::from(self.last_access);
+        let addr_next: [F; 2] = [
+            pack(&self.addr_next[..2], 64),
+            pack(&self.addr_next[2..], 64),
+        ];
+        let addr: [F; 2] = [pack(&self.addr[..2], 64), pack(&self.addr[2..], 64)];
+        let mid_1 = (F::one() - last_access) * (addr_next[0] - addr[0]);
+
+        let last_access = cs.new_witness_variable(|| Ok(last_access))?;
+        let addr_next_0 = cs.new_witness_variable(|| Ok(addr_next[0]))?;
+        let addr_next_1 = cs.new_witness_variable(|| Ok(addr_next[1]))?;
+        let addr_0 = cs.new_witness_variable(|| Ok(addr[0]))?;
+        let addr_1 = cs.new_witness_variable(|| Ok(addr[1]))?;
+        let mid_1 = cs.new_witness_variable(|| Ok(mid_1))?;
+        let out = cs.new_input_variable(|| Ok(F::zero()))?;
+        cs.enforce_constraint(
+            lc!() + Variable::One - last_access,
+            lc!() + addr_next_0 - addr_0,
+            lc!() + mid_1,
+        )?;
+        cs.enforce_constraint(lc!() + mid_1, lc!() + addr_next_1 - addr_1, lc!() + out)
+    }
+}
+
+// Constraint: (1-mOp)*(mWr)=0
+/// Only memory operations write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpCheckCircuit {
+    /// Memory operation
+    pub m_op: u64,
+    /// Write
+    pub m_wr: u64,
+}
+
+impl From<&MemRow> for OpCheckCircuit {
+    fn from(row: &MemRow) -> Self {
+        Self {
+            m_op: row.m_op,
+            m_wr: row.m_wr,
+        }
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for OpCheckCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<()> {
+        let m_op = cs.new_witness_variable(|| Ok(F::from(self.m_op)))?;
+        let m_wr = cs.new_witness_variable(|| Ok(F::from(self.m_wr)))?;
+        let out = cs.new_input_variable(|| Ok(F::zero()))?;
+        cs.enforce_constraint(lc!() + Variable::One - m_op, lc!() + m_wr, lc!() + out)
+    }
+}
+
+// Constraint: (1-mOp'*mWr')(1-lastAccess)(val'[0..3]-val[0..3])(val'[4..7]-val[4..7])=0
+// mid_1 = mOp'*mWr'
+// mid_2 = (1-mid_1)(1-lastAccess)
+// mid_3 = mid_2*(val'[0..3]-val[0..3])
+// out = mid_3*(val'[4..7]-val[4..7])
+/// Unless the next row writes or this is the last access to the address, the
+/// next row keeps one of the halves of the value. The limbs have 32 bits,
+/// least significant first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpdateValueCheckCircuit {
+    /// Memory operation of the next row
+    pub m_op_next: u64,
+    /// Write of the next row
+    pub m_wr_next: u64,
+    /// Last access to the address
+    pub last_access: u64,
+    /// Limbs of the value of the next row
+    pub val_next: [u64; 8],
+    /// Limbs of the value
+    pub val: [u64; 8],
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for UpdateValueCheckCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<()> {
+        let m_op = F::from(self.m_op_next);
+        let m_wr = F::from(self.m_wr_next);
+        let last_access = F::from(self.last_access);
+        let val_next: [F; 2] = [pack(&self.val_next[..4], 32), pack(&self.val_next[4..], 32)];
+        let val: [F; 2] = [pack(&self.val[..4], 32), pack(&self.val[4..], 32)];
+        let mid_1 = m_op * m_wr;
+        let mid_2 = (F::one() - mid_1) * (F::one() - last_access);
+        let mid_3 = mid_2 * (val_next[0] - val[0]);
+
+        let mid_1 = cs.new_witness_variable(|| Ok(mid_1))?;
+        let mid_2 = cs.new_witness_variable(|| Ok(mid_2))?;
+        let mid_3 = cs.new_witness_variable(|| Ok(mid_3))?;
+        let m_op = cs.new_witness_variable(|| Ok(m_op))?;
+        let m_wr = cs.new_witness_variable(|| Ok(m_wr))?;
+        let last_access = cs.new_witness_variable(|| Ok(last_access))?;
+        let val_next_0 = cs.new_witness_variable(|| Ok(val_next[0]))?;
+        let val_0 = cs.new_witness_variable(|| Ok(val[0]))?;
+        let val_next_1 = cs.new_witness_variable(|| Ok(val_next[1]))?;
+        let val_1 = cs.new_witness_variable(|| Ok(val[1]))?;
+        let out = cs.new_input_variable(|| Ok(F::zero()))?;
+
+        cs.enforce_constraint(lc!() + m_op, lc!() + m_wr, lc!() + mid_1)?;
+        cs.enforce_constraint(
+            lc!() + Variable::One - mid_1,
+            lc!() + Variable::One - last_access,
+            lc!() + mid_2,
+        )?;
+        cs.enforce_constraint(lc!() + mid_2, lc!() + val_next_0 - val_0, lc!() + mid_3)?;
+        cs.enforce_constraint(lc!() + mid_3, lc!() + val_next_1 - val_1, lc!() + out)
+    }
+}
+
+// Constraint: (1-mOp'*mWr')lastAccess(val'[0..3])(val'[4..7])=0
+// mid_1 = mOp'*mWr'
+// mid_2 = (1-mid_1)*lastAccess
+// mid_3 = mid_2*(val'[0..3])
+// out = mid_3*(val'[4..7])
+/// After the last access to an address, unless the next row writes, one of
+/// the halves of the value of the next row is zero. The limbs have 32 bits,
+/// least significant first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpdateValueMulCheckCircuit {
+    /// Memory operation of the next row
+    pub m_op_next: u64,
+    /// Write of the next row
+    pub m_wr_next: u64,
+    /// Last access to the address
+    pub last_access: u64,
+    /// Limbs of the value of the next row
+    pub val_next: [u64; 8],
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for UpdateValueMulCheckCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<()> {
+        let m_op = F::from(self.m_op_next);
+        let m_wr = F::from(self.m_wr_next);
+        let last_access = F::from(self.last_access);
+        let val_next: [F; 2] = [pack(&self.val_next[..4], 32), pack(&self.val_next[4..], 32)];
+        let mid_1 = m_op * m_wr;
+        let mid_2 = (F::one() - mid_1) * last_access;
+        let mid_3 = mid_2 * val_next[0];
+
+        let mid_1 = cs.new_witness_variable(|| Ok(mid_1))?;
+        let mid_2 = cs.new_witness_variable(|| Ok(mid_2))?;
+        let mid_3 = cs.new_witness_variable(|| Ok(mid_3))?;
+        let m_op = cs.new_witness_variable(|| Ok(m_op))?;
+        let m_wr = cs.new_witness_variable(|| Ok(m_wr))?;
+        let last_access = cs.new_witness_variable(|| Ok(last_access))?;
+        let val_next_0 = cs.new_witness_variable(|| Ok(val_next[0]))?;
+        let val_next_1 = cs.new_witness_variable(|| Ok(val_next[1]))?;
+        let out = cs.new_input_variable(|| Ok(F::zero()))?;
+
+        cs.enforce_constraint(lc!() + m_op, lc!() + m_wr, lc!() + mid_1)?;
+        cs.enforce_constraint(
+            lc!() + Variable::One - mid_1,
+            lc!() + last_access,
+            lc!() + mid_2,
+        )?;
+        cs.enforce_constraint(lc!() + mid_2, lc!() + val_next_0, lc!() + mid_3)?;
+        cs.enforce_constraint(lc!() + mid_3, lc!() + val_next_1, lc!() + out)
+    }
 }
@@ -0,0 +1,586 @@
+//! Memory consistency of a whole memory table, such as
+//! `src/data/tx_mem_table.json`, as a single constraint system.
+//!
+//! The rows are sorted by address, then by step. `lastAccess` marks the last
+//! access to an address and `ISNOTLAST` every row but the last one of the
+//! table. On top of the checks of every row, each row is constrained against
+//! the previous one:
+//! - the address changes only after a last access, and then increases,
+//! - the step increases between accesses to the same address,
+//! - a read returns the value of the previous access to its address, or zero
+//!   on the first access.
+//!
+//! Tables are read from either layout of `src/data`: four 64 bits limbs
+//! before and after the access, or eight 32 bits registers as in
+//! `src/data/mem_test.json`. [`mem_table_from_trace`] generates the table of
+//! an execution trace.
+
+use std::io::Read;
+
+use ark_ff::PrimeField;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::gadgets::permutation::{enforce_permutation, Challenges};
+use crate::gadgets::range::{decompose, enforce_range};
+use crate::lc;
+use crate::r1cs::*;
+use crate::read_file::{
+    malformed, out_of_range, read_rows, ReadError, RowError, RowErrorKind, Trace,
+};
+
+/// Bits of the increment of the address, or of the step, between two rows
+pub const INCREMENT_BITS: usize = 32;
+
+/// Bits of the addresses and of the steps
+pub const ADDRESS_BITS: usize = 32;
+
+/// Bits of the registers of a value, as in
+/// `R1CS/constraint_systems/docs/MemoryOperationMatricesConfiguration.md`:
+/// each 64 bits limb of the table holds two registers
+pub const REGISTER_BITS: usize = 32;
+
+/// A row of a memory table, the 256 bits values are split in four 64 bits
+/// limbs from the most significant one.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemRow {
+    /// Zero on the last row of the table only
+    #[serde(rename = "ISNOTLAST", alias = "ISNOLAST")]
+    pub is_not_last: u64,
+    /// Memory address
+    pub address: u64,
+    /// Step of the execution trace
+    pub step: u64,
+    /// Memory operation
+    #[serde(alias = "mOp")]
+    pub m_op: u64,
+    /// Write
+    #[serde(alias = "mWr")]
+    pub m_wr: u64,
+    /// Write of a single byte
+    #[serde(alias = "mWr8")]
+    pub m_wr8: u64,
+    /// Last access to the address
+    #[serde(rename = "lastAccess")]
+    pub last_access: u64,
+    /// Limb of the value before the access
+    pub val_src_0: u64,
+    /// Limb of the value before the access
+    pub val_src_1: u64,
+    /// Limb of the value before the access
+    pub val_src_2: u64,
+    /// Limb of the value before the access
+    pub val_src_3: u64,
+    /// Limb of the value after the access
+    pub val_dst_0: u64,
+    /// Limb of the value after the access
+    pub val_dst_1: u64,
+    /// Limb of the value after the access
+    pub val_dst_2: u64,
+    /// Limb of the value after the access
+    pub val_dst_3: u64,
+}
+
+impl MemRow {
+    /// Limbs of the value before the access
+    pub fn val_src(&self) -> [u64; 4] {
+        [
+            self.val_src_0,
+            self.val_src_1,
+            self.val_src_2,
+            self.val_src_3,
+        ]
+    }
+
+    /// Limbs of the value after the access
+    pub fn val_dst(&self) -> [u64; 4] {
+        [
+            self.val_dst_0,
+            self.val_dst_1,
+            self.val_dst_2,
+            self.val_dst_3,
+        ]
+    }
+
+    /// The step, the address, `mOp`, `mWr` and the value after the access,
+    /// as they appear in the trace
+    pub fn tuple(&self) -> [u64; 8] {
+        let [v0, v1, v2, v3] = self.val_dst();
+        [
+            self.step,
+            self.address,
+            self.m_op,
+            self.m_wr,
+            v0,
+            v1,
+            v2,
+            v3,
+        ]
+    }
+}
+
+const REGISTERS: [&str; 8] = [
+    "val0", "val1", "val2", "val3", "val4", "val5", "val6", "val7",
+];
+
+/// A row with the value in eight registers from the most significant one,
+/// which the access does not change
+#[derive(Deserialize)]
+struct RegisterRow {
+    #[serde(rename = "ISNOTLAST", alias = "ISNOLAST")]
+    is_not_last: u64,
+    address: u64,
+    step: u64,
+    #[serde(rename = "mOp", alias = "m_op")]
+    m_op: u64,
+    #[serde(rename = "mWr", alias = "m_wr")]
+    m_wr: u64,
+    #[serde(rename = "lastAccess")]
+    last_access: u64,
+    val0: u64,
+    val1: u64,
+    val2: u64,
+    val3: u64,
+    val4: u64,
+    val5: u64,
+    val6: u64,
+    val7: u64,
+}
+
+impl TryFrom<RegisterRow> for MemRow {
+    type Error = RowErrorKind;
+
+    fn try_from(row: RegisterRow) -> core::result::Result<Self, RowErrorKind> {
+        let registers = [
+            row.val0, row.val1, row.val2, row.val3, row.val4, row.val5, row.val6, row.val7,
+        ];
+        for (&register, field) in registers.iter().zip(REGISTERS) {
+            if let Some(e) = out_of_range(register, field, REGISTER_BITS) {
+                return Err(e);
+            }
+        }
+        let limb = |k: usize| registers[2 * k] << REGISTER_BITS | registers[2 * k + 1];
+        Ok(MemRow {
+            is_not_last: row.is_not_last,
+            address: row.address,
+            step: row.step,
+            m_op: row.m_op,
+            m_wr: row.m_wr,
+            m_wr8: 0,
+            last_access: row.last_access,
+            val_src_0: limb(0),
+            val_src_1: limb(1),
+            val_src_2: limb(2),
+            val_src_3: limb(3),
+            val_dst_0: limb(0),
+            val_dst_1: limb(1),
+            val_dst_2: limb(2),
+            val_dst_3: limb(3),
+        })
+    }
+}
+
+fn parse_mem_row(value: Value) -> core::result::Result<MemRow, RowErrorKind> {
+    if value.get(REGISTERS[0]).is_some() {
+        serde_json::from_value::<RegisterRow>(value)
+            .map_err(malformed)?
+            .try_into()
+    } else {
+        serde_json::from_value(value).map_err(malformed)
+    }
+}
+
+/// Reads the rows of a memory table, stored as `{"data": [...]}` in either
+/// layout.
+pub fn read_mem_table<R: Read>(reader: R) -> core::result::Result<Vec<MemRow>, ReadError> {
+    read_rows(reader, parse_mem_row)
+}
+
+/// Checks that the flags of a memory table are booleans, that addresses and
+/// steps fit in [`ADDRESS_BITS`] and that the rows are sorted by address,
+/// then by step. The consistency of the accesses is left to
+/// [`MemTableCircuit`].
+pub fn validate_mem_table(rows: &[MemRow]) -> core::result::Result<(), Vec<RowError>> {
+    let mut errors = Vec::new();
+    for (row, r) in rows.iter().enumerate() {
+        let flags = [
+            (r.is_not_last, "ISNOTLAST"),
+            (r.m_op, "m_op"),
+            (r.m_wr, "m_wr"),
+            (r.m_wr8, "m_wr8"),
+            (r.last_access, "lastAccess"),
+        ];
+        let kinds = flags
+            .into_iter()
+            .map(|(flag, field)| (flag > 1).then_some(RowErrorKind::NotBoolean(field)))
+            .chain([
+                out_of_range(r.address, "address", ADDRESS_BITS),
+                out_of_range(r.step, "step", ADDRESS_BITS),
+                (row > 0 && (r.address, r.step) <= (rows[row - 1].address, rows[row - 1].step))
+                    .then_some(RowErrorKind::NotSorted),
+            ]);
+        errors.extend(kinds.flatten().map(|kind| RowError { row, kind }));
+    }
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+/// The memory table of an execution trace: its accesses sorted by address,
+/// then by step, with the value of every access before and after it. The
+/// trace does not tell single byte writes, so `m_wr8` is zero.
+pub fn mem_table_from_trace(trace: &[Trace]) -> Vec<MemRow> {
+    let mut accesses = trace.to_vec();
+    accesses.sort_by_key(|access| (access.addr, access.step));
+    let num_rows = accesses.len();
+    accesses
+        .iter()
+        .enumerate()
+        .map(|(i, access)| {
+            let last_access = accesses
+                .get(i + 1)
+                .map_or(true, |next| next.addr != access.addr);
+            let [v0, v1, v2, v3] = access.value;
+            MemRow {
+                is_not_last: (i + 1 < num_rows) as u64,
+                address: access.addr,
+                step: access.step,
+                m_op: access.m_op as u64,
+                m_wr: access.m_wr as u64,
+                m_wr8: 0,
+                last_access: last_access as u64,
+                val_src_0: v0,
+                val_src_1: v1,
+                val_src_2: v2,
+                val_src_3: v3,
+                val_dst_0: v0,
+                val_dst_1: v1,
+                val_dst_2: v2,
+                val_dst_3: v3,
+            }
+        })
+        .collect()
+}
+
+/// Checks the memory consistency of every row of a memory table.
+#[derive(Debug, Clone)]
+pub struct MemTableCircuit {
+    /// Rows sorted by address, then by step
+    pub rows: Vec<MemRow>,
+}
+
+/// Checks the memory consistency of an execution trace: the memory table
+/// passes [`MemTableCircuit`] and its rows are a permutation of the accesses
+/// of the trace, over the step, the address, `mOp`, `mWr` and the value after
+/// the access.
+///
+/// The instance is the challenges of the permutation argument, then these
+/// tuples of the trace and of the table, see [`MemCheckCircuit::instance`].
+/// The tuples being public, the challenges are derived from them with
+/// [`MemCheckCircuit::challenges`], which the verifier checks.
+///
+/// This is a deliberate limitation: the whole memory trace is public, and a
+/// verifier holding it could check the permutation directly, so the grand
+/// product only matters once the tuples are committed witnesses and the
+/// challenges are derived from the commitment, which Groth16 alone does not
+/// provide.
+#[derive(Debug, Clone)]
+pub struct MemCheckCircuit<F: PrimeField> {
+    /// Accesses in step order
+    pub trace: Vec<Trace>,
+    /// Accesses sorted by address
+    pub table: MemTableCircuit,
+    /// Challenges of the permutation argument
+    pub challenges: Challenges<F>,
+}
+
+/// Variables of a row
+struct RowVars {
+    is_not_last: Variable,
+    address: Variable,
+    step: Variable,
+    m_op: Variable,
+    m_wr: Variable,
+    last_access: Variable,
+    /// `m_op * m_wr`
+    write: Variable,
+    val_src: [Variable; 4],
+    val_dst: [Variable; 4],
+}
+
+impl RowVars {
+    /// Variables of [`MemRow::tuple`]
+    fn tuple(&self) -> [Variable; 8] {
+        let [v0, v1, v2, v3] = self.val_dst;
+        [
+            self.step,
+            self.address,
+            self.m_op,
+            self.m_wr,
+            v0,
+            v1,
+            v2,
+            v3,
+        ]
+    }
+}
+
+fn witness<F: PrimeField>(cs: &ConstraintSystemRef<F>, value: F) -> Result<Variable> {
+    cs.new_witness_variable(|| Ok(value))
+}
+
+fn enforce_boolean<F: PrimeField>(cs: &ConstraintSystemRef<F>, x: Variable) -> Result<()> {
+    cs.enforce_constraint(lc!() + x, lc!() + x - Variable::One, lc!())
+}
+
+impl MemTableCircuit {
+    /// Circuit over `rows`, sorted by address, then by step.
+    pub fn new(rows: Vec<MemRow>) -> Self {
+        Self { rows }
+    }
+
+    /// Allocates `row` and enforces the checks involving it alone.
+    fn row<F: PrimeField>(cs: &ConstraintSystemRef<F>, row: &MemRow) -> Result<RowVars> {
+        let value = |x: u64| witness(cs, F::from(x));
+        let is_not_last = value(row.is_not_last)?;
+        let address = value(row.address)?;
+        let step = value(row.step)?;
+        let m_op = value(row.m_op)?;
+        let m_wr = value(row.m_wr)?;
+        let m_wr8 = value(row.m_wr8)?;
+        let last_access = value(row.last_access)?;
+        let write = witness(cs, F::from(row.m_op) * F::from(row.m_wr))?;
+        let byte_write = witness(cs, F::from(row.m_wr) * F::from(row.m_wr8))?;
+        let mut val_src = [Variable::Zero; 4];
+        let mut val_dst = [Variable::Zero; 4];
+        for k in 0..4 {
+            val_src[k] = value(row.val_src()[k])?;
+            val_dst[k] = value(row.val_dst()[k])?;
+        }
+
+        for x in [is_not_last, m_op, m_wr, m_wr8, last_access] {
+            enforce_boolean(cs, x)?;
+        }
+        for (x, value) in [(address, row.address), (step, row.step)] {
+            enforce_range(cs, lc!() + x, F::from(value), ADDRESS_BITS)?;
+        }
+        for (limbs, values) in [(val_src, row.val_src()), (val_dst, row.val_dst())] {
+            for (x, value) in limbs.into_iter().zip(values) {
+                decompose(cs, lc!() + x, F::from(value), REGISTER_BITS, 2)?;
+            }
+        }
+        // (1-mOp)*mWr = 0, (1-mWr)*mWr8 = 0
+        cs.enforce_constraint(lc!() + Variable::One - m_op, lc!() + m_wr, lc!())?;
+        cs.enforce_constraint(lc!() + Variable::One - m_wr, lc!() + m_wr8, lc!())?;
+        cs.enforce_constraint(lc!() + m_op, lc!() + m_wr, lc!() + write)?;
+        cs.enforce_constraint(lc!() + m_wr, lc!() + m_wr8, lc!() + byte_write)?;
+        // (1+mWr*mWr8)(val_src-val_dst) = 0
+        for k in 0..4 {
+            cs.enforce_constraint(
+                lc!() + Variable::One + byte_write,
+                lc!() + val_src[k] - val_dst[k],
+                lc!(),
+            )?;
+        }
+        Ok(RowVars {
+            is_not_last,
+            address,
+            step,
+            m_op,
+            m_wr,
+            last_access,
+            write,
+            val_src,
+            val_dst,
+        })
+    }
+
+    /// Enforces the constraints of `row` following `prev`.
+    fn transition<F: PrimeField>(
+        cs: &ConstraintSystemRef<F>,
+        prev: (&MemRow, &RowVars),
+        next: (&MemRow, &RowVars),
+    ) -> Result<()> {
+        let ((prev_row, prev), (next_row, next)) = (prev, next);
+        let one = lc!() + Variable::One;
+        cs.enforce_constraint(one.clone() - prev.is_not_last, one.clone(), lc!())?;
+        // (1-lastAccess)*(addr'-addr) = 0
+        cs.enforce_constraint(
+            one.clone() - prev.last_access,
+            lc!() + next.address - prev.address,
+            lc!(),
+        )?;
+
+        // the address increases after a last access, the step otherwise
+        let [last_access, address, step, next_address, next_step] = [
+            prev_row.last_access,
+            prev_row.address,
+            prev_row.step,
+            next_row.address,
+            next_row.step,
+        ]
+        .map(F::from);
+        let address_part = last_access * (next_address - address - next_step + step);
+        let address_part_var = witness(cs, address_part)?;
+        cs.enforce_constraint(
+            lc!() + prev.last_access,
+            lc!() + next.address - prev.address - next.step + prev.step,
+            lc!() + address_part_var,
+        )?;
+        enforce_range(
+            cs,
+            lc!() + address_part_var + next.step - prev.step - Variable::One,
+            address_part + next_step - step - F::one(),
+            INCREMENT_BITS,
+        )?;
+
+        // (1-mOp'*mWr')(1-lastAccess)(val'-val) = 0, (1-mOp'*mWr')lastAccess*val' = 0
+        let read = F::one() - F::from(next_row.m_op) * F::from(next_row.m_wr);
+        let same = witness(cs, read * (F::one() - last_access))?;
+        let fresh = witness(cs, read * last_access)?;
+        cs.enforce_constraint(
+            one.clone() - next.write,
+            one.clone() - prev.last_access,
+            lc!() + same,
+        )?;
+        cs.enforce_constraint(one - next.write, lc!() + prev.last_access, lc!() + fresh)?;
+        for k in 0..4 {
+            cs.enforce_constraint(
+                lc!() + same,
+                lc!() + next.val_src[k] - prev.val_dst[k],
+                lc!(),
+            )?;
+            cs.enforce_constraint(lc!() + fresh, lc!() + next.val_src[k], lc!())?;
+        }
+        Ok(())
+    }
+
+    /// Enforces the constraints of the table, returns the number of
+    /// constraints enforced once each row is added and the variables of the
+    /// rows.
+    fn synthesize<F: PrimeField>(
+        &self,
+        cs: &ConstraintSystemRef<F>,
+    ) -> Result<(Vec<usize>, Vec<RowVars>)> {
+        let mut row_ends = Vec::with_capacity(self.rows.len());
+        let mut vars: Vec<RowVars> = Vec::with_capacity(self.rows.len());
+        for (i, row) in self.rows.iter().enumerate() {
+            let row_vars = Self::row(cs, row)?;
+            match vars.last() {
+                Some(prev_vars) => {
+                    Self::transition(cs, (&self.rows[i - 1], prev_vars), (row, &row_vars))?;
+                }
+                // the first access to the first address reads zero
+                None => {
+                    for k in 0..4 {
+                        cs.enforce_constraint(
+                            lc!() + Variable::One - row_vars.write,
+                            lc!() + row_vars.val_src[k],
+                            lc!(),
+                        )?;
+                    }
+                }
+            }
+            row_ends.push(cs.num_constraints());
+            vars.push(row_vars);
+        }
+        if let Some(last) = vars.last() {
+            cs.enforce_constraint(lc!() + last.is_not_last, lc!() + Variable::One, lc!())?;
+            cs.enforce_constraint(
+                lc!() + Variable::One - last.last_access,
+                lc!() + Variable::One,
+                lc!(),
+            )?;
+        }
+        if let Some(end) = row_ends.last_mut() {
+            *end = cs.num_constraints();
+        }
+        Ok((row_ends, vars))
+    }
+
+    /// The first row whose constraints, or constraints against the previous
+    /// row, are unsatisfied.
+    pub fn failing_row<F: PrimeField>(&self) -> Result<Option<usize>> {
+        let cs = ConstraintSystem::<F>::new_ref();
+        let (row_ends, _) = self.synthesize(&cs)?;
+        Ok(cs
+            .first_unsatisfied()?
+            .map(|i| row_ends.partition_point(|&end| end <= i)))
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for MemTableCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<()> {
+        self.synthesize(&cs).map(|_| ())
+    }
+}
+
+impl<F: PrimeField> MemCheckCircuit<F> {
+    /// Circuit over `trace` and its memory table, see
+    /// [`mem_table_from_trace`].
+    pub fn from_trace(trace: Vec<Trace>) -> Self {
+        let table = mem_table_from_trace(&trace);
+        Self {
+            challenges: Self::challenges(&trace, &table),
+            table: MemTableCircuit::new(table),
+            trace,
+        }
+    }
+
+    /// Challenges of the permutation of `trace` and `table`, hashed from
+    /// their tuples.
+    pub fn challenges(trace: &[Trace], table: &[MemRow]) -> Challenges<F> {
+        let trace: Vec<u64> = trace.iter().flat_map(Trace::tuple).collect();
+        let table: Vec<u64> = table.iter().flat_map(MemRow::tuple).collect();
+        Challenges::derive(&[&trace, &table])
+    }
+
+    /// The public inputs of the circuit over `trace` and `table`, with the
+    /// challenges derived from them.
+    pub fn instance(trace: &[Trace], table: &[MemRow]) -> Vec<F> {
+        let Challenges { alpha, gamma } = Self::challenges(trace, table);
+        let tuples = trace
+            .iter()
+            .map(Trace::tuple)
+            .chain(table.iter().map(MemRow::tuple));
+        [alpha, gamma]
+            .into_iter()
+            .chain(tuples.flatten().map(F::from))
+            .collect()
+    }
+}
+
+/// Allocates `tuples` as instance variables.
+fn public_tuples<F: PrimeField>(
+    cs: &ConstraintSystemRef<F>,
+    tuples: impl Iterator<Item = [u64; 8]>,
+) -> Result<Vec<Vec<(Variable, F)>>> {
+    tuples
+        .map(|tuple| {
+            tuple
+                .into_iter()
+                .map(|x| {
+                    let value = F::from(x);
+                    Ok((cs.new_input_variable(|| Ok(value))?, value))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for MemCheckCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<()> {
+        let challenges = self.challenges.allocate(&cs)?;
+        let trace = public_tuples(&cs, self.trace.iter().map(Trace::tuple))?;
+        let table = public_tuples(&cs, self.table.rows.iter().map(MemRow::tuple))?;
+        let (_, table_vars) = self.table.synthesize(&cs)?;
+        // the rows checked by the table circuit are the public ones
+        for (public, vars) in table.iter().zip(&table_vars) {
+            for (&(public, _), var) in public.iter().zip(vars.tuple()) {
+                cs.enforce_constraint(lc!() + public - var, lc!() + Variable::One, lc!())?;
+            }
+        }
+        enforce_permutation(&cs, &challenges, &table, &trace)
+    }
+}
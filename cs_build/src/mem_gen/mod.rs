@@ -1 +1,2 @@
 pub mod mem_gen;
+pub mod mem_table;
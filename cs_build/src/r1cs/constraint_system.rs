@@ -588,40 +588,49 @@ impl<F: Field> ConstraintSystem<F> {
     /// the first unsatisfied constraint. If `self.is_in_setup_mode()`, outputs
     /// `Err(())`.
     pub fn which_is_unsatisfied(&self) -> crate::r1cs::Result<Option<String>> {
+        Ok(self.first_unsatisfied()?.map(|i| {
+            let trace;
+            #[cfg(feature = "std")]
+            {
+                trace = self.constraint_traces[i].as_ref().map_or_else(
+                    || {
+                        eprintln!("Constraint trace requires enabling `ConstraintLayer`");
+                        format!("{}", i)
+                    },
+                    |t| format!("{}", t),
+                );
+            }
+            #[cfg(not(feature = "std"))]
+            {
+                trace = format!("{}", i);
+            }
+            trace
+        }))
+    }
+
+    /// If `self` is satisfied, outputs `Ok(None)`.
+    /// If `self` is unsatisfied, outputs `Some(i)`, where `i` is the index of
+    /// the first unsatisfied constraint, whether or not constraint traces are
+    /// enabled. If `self.is_in_setup_mode()`, outputs `Err(())`.
+    pub fn first_unsatisfied(&self) -> crate::r1cs::Result<Option<usize>> {
         if self.is_in_setup_mode() {
-            Err(SynthesisError::AssignmentMissing)
-        } else {
-            for i in 0..self.num_constraints {
-                let a = self
-                    .eval_lc(self.a_constraints[i])
-                    .ok_or(SynthesisError::AssignmentMissing)?;
-                let b = self
-                    .eval_lc(self.b_constraints[i])
-                    .ok_or(SynthesisError::AssignmentMissing)?;
-                let c = self
-                    .eval_lc(self.c_constraints[i])
-                    .ok_or(SynthesisError::AssignmentMissing)?;
-                if a * b != c {
-                    let trace;
-                    #[cfg(feature = "std")]
-                    {
-                        trace = self.constraint_traces[i].as_ref().map_or_else(
-                            || {
-                                eprintln!("Constraint trace requires enabling `ConstraintLayer`");
-                                format!("{}", i)
-                            },
-                            |t| format!("{}", t),
-                        );
-                    }
-                    #[cfg(not(feature = "std"))]
-                    {
-                        trace = format!("{}", i);
-                    }
-                    return Ok(Some(trace));
-                }
+            return Err(SynthesisError::AssignmentMissing);
+        }
+        for i in 0..self.num_constraints {
+            let a = self
+                .eval_lc(self.a_constraints[i])
+                .ok_or(SynthesisError::AssignmentMissing)?;
+            let b = self
+                .eval_lc(self.b_constraints[i])
+                .ok_or(SynthesisError::AssignmentMissing)?;
+            let c = self
+                .eval_lc(self.c_constraints[i])
+                .ok_or(SynthesisError::AssignmentMissing)?;
+            if a * b != c {
+                return Ok(Some(i));
             }
-            Ok(None)
         }
+        Ok(None)
     }
 
     /// Obtain the assignment corresponding to the `Variable` `v`.
@@ -964,6 +973,17 @@ impl<F: Field> ConstraintSystemRef<F> {
             })
     }
 
+    /// If `self` is satisfied, outputs `Ok(None)`.
+    /// If `self` is unsatisfied, outputs `Some(i)`, where `i` is the index of
+    /// the first unsatisfied constraint.
+    /// If `self.is_in_setup_mode()` or `self == None`, outputs `Err(())`.
+    pub fn first_unsatisfied(&self) -> crate::r1cs::Result<Option<usize>> {
+        self.inner()
+            .map_or(Err(SynthesisError::AssignmentMissing), |cs| {
+                cs.borrow().first_unsatisfied()
+            })
+    }
+
     /// Obtain the assignment corresponding to the `Variable` `v`.
     pub fn assigned_value(&self, v: Variable) -> Option<F> {
         self.inner().and_then(|cs| cs.borrow().assigned_value(v))
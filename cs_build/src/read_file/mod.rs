@@ -0,0 +1,177 @@
+//! Execution traces of the memory accesses, as collected from geth in step
+//! order, and the errors of the ingestion of traces and memory tables.
+//!
+//! Files are read row by row, so that a row which does not match the schema
+//! is reported with its index. Reading does not validate the rows against
+//! each other, see [`validate_trace`] and
+//! [`validate_mem_table`](crate::mem_gen::mem_table::validate_mem_table).
+
+use core::fmt;
+use std::io::Read;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::mem_gen::mem_table::ADDRESS_BITS;
+
+/// A memory access of an execution trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Trace {
+    /// Step of the execution
+    pub step: u64,
+    /// Memory operation
+    pub m_op: bool,
+    /// Write
+    pub m_wr: bool,
+    /// Memory address
+    pub addr: u64,
+    /// Value after the access, in the limbs of the memory table
+    pub value: [u64; 4],
+}
+
+impl Trace {
+    /// The step, the address, `mOp`, `mWr` and the value after the access, as
+    /// in [`MemRow::tuple`](crate::mem_gen::mem_table::MemRow::tuple)
+    pub fn tuple(&self) -> [u64; 8] {
+        let [v0, v1, v2, v3] = self.value;
+        let (m_op, m_wr) = (self.m_op as u64, self.m_wr as u64);
+        [self.step, self.addr, m_op, m_wr, v0, v1, v2, v3]
+    }
+}
+
+/// What is wrong with a row of a trace or of a memory table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RowErrorKind {
+    /// The row does not match the schema
+    Malformed(String),
+    /// A flag is neither zero nor one
+    NotBoolean(&'static str),
+    /// A field does not fit in its bits
+    OutOfRange {
+        /// Name of the field in the file
+        field: &'static str,
+        /// Bits of the field
+        bits: usize,
+    },
+    /// The row is not strictly after the previous one
+    NotSorted,
+}
+
+/// An invalid row of a trace or of a memory table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowError {
+    /// Index of the row in the file
+    pub row: usize,
+    /// What is wrong with the row
+    pub kind: RowErrorKind,
+}
+
+impl ark_std::error::Error for RowError {}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "row {}: ", self.row)?;
+        match &self.kind {
+            RowErrorKind::Malformed(e) => write!(f, "{}", e),
+            RowErrorKind::NotBoolean(field) => write!(f, "{} is not a boolean", field),
+            RowErrorKind::OutOfRange { field, bits } => {
+                write!(f, "{} does not fit in {} bits", field, bits)
+            }
+            RowErrorKind::NotSorted => write!(f, "not sorted after the previous row"),
+        }
+    }
+}
+
+/// Errors of the ingestion of a trace or of a memory table
+#[derive(Debug)]
+pub enum ReadError {
+    /// The file is not `{"data": [...]}`
+    Json(serde_json::Error),
+    /// Rows do not match the schema
+    Rows(Vec<RowError>),
+}
+
+impl ark_std::error::Error for ReadError {}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            ReadError::Json(e) => write!(f, "{}", e),
+            ReadError::Rows(errors) => {
+                write!(f, "{} invalid rows", errors.len())?;
+                errors.iter().try_for_each(|e| write!(f, "\n{}", e))
+            }
+        }
+    }
+}
+
+impl From<serde_json::Error> for ReadError {
+    fn from(e: serde_json::Error) -> Self {
+        ReadError::Json(e)
+    }
+}
+
+/// `value` of `field` does not fit in `bits`
+pub(crate) fn out_of_range(value: u64, field: &'static str, bits: usize) -> Option<RowErrorKind> {
+    (bits < 64 && value >> bits != 0).then_some(RowErrorKind::OutOfRange { field, bits })
+}
+
+pub(crate) fn malformed(e: serde_json::Error) -> RowErrorKind {
+    RowErrorKind::Malformed(e.to_string())
+}
+
+#[derive(Deserialize)]
+struct RowsFile {
+    data: Vec<Value>,
+}
+
+/// Reads the rows of a file stored as `{"data": [...]}`, with `parse`.
+pub(crate) fn read_rows<T, R: Read>(
+    reader: R,
+    parse: impl Fn(Value) -> Result<T, RowErrorKind>,
+) -> Result<Vec<T>, ReadError> {
+    let file: RowsFile = serde_json::from_reader(reader)?;
+    let mut rows = Vec::with_capacity(file.data.len());
+    let mut errors = Vec::new();
+    for (row, value) in file.data.into_iter().enumerate() {
+        match parse(value) {
+            Ok(value) => rows.push(value),
+            Err(kind) => errors.push(RowError { row, kind }),
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(rows),
+        false => Err(ReadError::Rows(errors)),
+    }
+}
+
+/// Reads the accesses of a trace, stored as `{"data": [...]}`.
+pub fn read_trace<R: Read>(reader: R) -> Result<Vec<Trace>, ReadError> {
+    read_rows(reader, |value| {
+        serde_json::from_value(value).map_err(malformed)
+    })
+}
+
+/// Checks that the accesses of a trace are in step order and that steps and
+/// addresses fit in [`ADDRESS_BITS`].
+pub fn validate_trace(trace: &[Trace]) -> Result<(), Vec<RowError>> {
+    let mut errors = Vec::new();
+    for (row, access) in trace.iter().enumerate() {
+        let kinds = [
+            out_of_range(access.step, "Step", ADDRESS_BITS),
+            out_of_range(access.addr, "Addr", ADDRESS_BITS),
+            (row > 0 && access.step <= trace[row - 1].step).then_some(RowErrorKind::NotSorted),
+        ];
+        errors.extend(
+            kinds
+                .into_iter()
+                .flatten()
+                .map(|kind| RowError { row, kind }),
+        );
+    }
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
@@ -1,5 +1,7 @@
-
 pub mod merkle_tree;
 pub mod my_hash;
+pub mod poseidon;
+pub mod proof_format;
 pub mod rs_polynomial;
+pub mod transcript;
 pub mod utility;
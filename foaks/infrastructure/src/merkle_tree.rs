@@ -3,17 +3,18 @@ use std::{
   vec::Vec,
 };
 
-use crate::my_hash::{my_hash, HashDigest};
+use crate::my_hash::{HashDigest, MerkleHasher};
 use prime_field::FieldElement;
+use serde::{Deserialize, Serialize};
 
-pub fn hash_single_field_element(x: FieldElement) -> HashDigest {
+pub fn hash_single_field_element<H: MerkleHasher>(x: FieldElement) -> HashDigest {
   let mut data = [HashDigest::default(); 2];
   data[0].h0 = HashDigest::memcpy_from_field_element(x).h0; // merkle_tree.cpp 9
   assert_eq!(size_of_val(&x), size_of_val(&data[0].h0));
-  my_hash(data)
+  H::hash(data)
 }
 
-pub fn hash_double_field_element_merkle_damgard(
+pub fn hash_double_field_element_merkle_damgard<H: MerkleHasher>(
   x: FieldElement,
   y: FieldElement,
   prev_hash: HashDigest,
@@ -22,10 +23,14 @@ pub fn hash_double_field_element_merkle_damgard(
   let element = [x, y];
   data[1] = HashDigest::memcpy_from_field_elements(element); // merkle_tree.cpp 22
   assert_eq!(size_of::<HashDigest>(), 2 * size_of::<FieldElement>());
-  my_hash(data)
+  H::hash(data)
 }
 
-pub fn create_tree(dst: &mut Vec<HashDigest>, src_data: &[HashDigest], alloc_required: bool) {
+pub fn create_tree<H: MerkleHasher>(
+  dst: &mut Vec<HashDigest>,
+  src_data: &[HashDigest],
+  alloc_required: bool,
+) {
   let element_num = src_data.len();
   let size_after_padding = 1 << (element_num as f64).log2().ceil() as usize;
 
@@ -51,7 +56,7 @@ pub fn create_tree(dst: &mut Vec<HashDigest>, src_data: &[HashDigest], alloc_req
 
     for i in 0..current_lvl_size {
       let data = [dst[chunk_start + i * 2], dst[chunk_start + i * 2 + 1]];
-      dst[start_idx + i] = my_hash(data);
+      dst[start_idx + i] = H::hash(data);
     }
 
     current_lvl_size >>= 1;
@@ -59,28 +64,187 @@ pub fn create_tree(dst: &mut Vec<HashDigest>, src_data: &[HashDigest], alloc_req
   }
 }
 
-// New way to implement verify_claim()
-pub fn verify_claim(
-  root_hash: HashDigest,
-  tree: &[HashDigest],
+/// Authentication data for leaves of a tree built by [`create_tree`], checked
+/// against the root alone
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MerkleProof {
+  /// Siblings of one leaf from the bottom level up to the root
+  Path(Vec<HashDigest>),
+  /// Siblings of a batch of leaves, every node stored once, in the order the
+  /// paths of the batch first reach it
+  MultiPath(Vec<HashDigest>),
+}
+
+impl MerkleProof {
+  pub fn open(tree: &[HashDigest], pos: usize, n: usize) -> Self {
+    assert_eq!(((n as i64) & -(n as i64)), n as i64);
+    let mut pos_element = pos + n;
+    let mut path = Vec::with_capacity(n.trailing_zeros() as usize);
+    while pos_element != 1 {
+      path.push(tree[pos_element ^ 1]);
+      pos_element /= 2;
+    }
+    Self::Path(path)
+  }
+
+  pub fn open_batch(tree: &[HashDigest], positions: &[usize], n: usize) -> Self {
+    assert_eq!(((n as i64) & -(n as i64)), n as i64);
+    let mut visited = vec![false; 2 * n];
+    let mut siblings = vec![];
+    for &pos in positions {
+      let mut pos_element = pos + n;
+      while pos_element != 1 {
+        if !visited[pos_element ^ 1] {
+          visited[pos_element ^ 1] = true;
+          siblings.push(tree[pos_element ^ 1]);
+        }
+        pos_element /= 2;
+      }
+    }
+    Self::MultiPath(siblings)
+  }
+
+  pub fn hashes(&self) -> &[HashDigest] {
+    match self {
+      Self::Path(hashes) | Self::MultiPath(hashes) => hashes,
+    }
+  }
+
+  pub fn size(&self) -> usize { size_of_val(self.hashes()) }
+
+  /// Checks `leaves`, pairs of leaf position and leaf hash, against `root_hash`.
+  /// A multi path expects them in the order given to [`MerkleProof::open_batch`].
+  pub fn verify<H: MerkleHasher>(
+    &self,
+    root_hash: HashDigest,
+    leaves: &[(usize, HashDigest)],
+    n: usize,
+  ) -> bool {
+    assert_eq!(((n as i64) & -(n as i64)), n as i64);
+    let depth = n.trailing_zeros() as usize;
+    match self {
+      Self::Path(path) => match leaves {
+        [(pos, leaf_hash)] if *pos < n && path.len() == depth => {
+          root_hash == hash_path::<H>(*leaf_hash, *pos + n, path.iter().copied())
+        }
+        _ => false,
+      },
+      Self::MultiPath(siblings) => {
+        let mut known: Vec<Option<HashDigest>> = vec![None; 2 * n];
+        let mut siblings = siblings.iter();
+        for &(pos, leaf_hash) in leaves {
+          if pos >= n {
+            return false;
+          }
+          let mut pos_element = pos + n;
+          let mut path = Vec::with_capacity(depth);
+          while pos_element != 1 {
+            let sibling = match known[pos_element ^ 1] {
+              Some(sibling) => sibling,
+              None => match siblings.next() {
+                Some(&sibling) => *known[pos_element ^ 1].insert(sibling),
+                None => return false,
+              },
+            };
+            path.push(sibling);
+            pos_element /= 2;
+          }
+          if root_hash != hash_path::<H>(leaf_hash, pos + n, path) {
+            return false;
+          }
+        }
+        siblings.next().is_none()
+      }
+    }
+  }
+}
+
+fn hash_path<H: MerkleHasher>(
   mut leaf_hash: HashDigest,
-  pos_element_arr: usize,
-  n: usize,
-  visited: &mut [bool],
-  proof_size: &mut usize,
-) -> bool {
-  assert_eq!(((n as i64) & -(n as i64)), n as i64);
-  let mut pos_element = pos_element_arr + n;
+  mut pos_element: usize,
+  path: impl IntoIterator<Item = HashDigest>,
+) -> HashDigest {
   let mut data = [HashDigest::default(); 2];
-  while pos_element != 1 {
+  for sibling in path {
     data[pos_element & 1] = leaf_hash;
-    data[(pos_element & 1) ^ 1] = tree[pos_element ^ 1];
-    if !visited[pos_element ^ 1] {
-      visited[pos_element ^ 1] = true;
-      *proof_size += size_of_val(&leaf_hash)
-    }
-    leaf_hash = my_hash(data);
+    data[(pos_element & 1) ^ 1] = sibling;
+    leaf_hash = H::hash(data);
     pos_element /= 2;
   }
-  root_hash == leaf_hash
+  leaf_hash
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    my_hash::{Blake3Hasher, Keccak256Hasher, Sha256Hasher},
+    poseidon::PoseidonHasher,
+  };
+
+  fn tree<H: MerkleHasher>(n: usize) -> (Vec<HashDigest>, Vec<HashDigest>) {
+    let leaves: Vec<HashDigest> = (0..n)
+      .map(|i| hash_single_field_element::<H>(FieldElement::from_real(i as u64)))
+      .collect();
+    let mut tree = vec![];
+    create_tree::<H>(&mut tree, &leaves, true);
+    (leaves, tree)
+  }
+
+  fn single_path<H: MerkleHasher>() {
+    let (leaves, tree) = tree::<H>(16);
+    let proof = MerkleProof::open(&tree, 5, 16);
+    assert_eq!(proof.size(), 4 * size_of::<HashDigest>());
+    assert!(proof.verify::<H>(tree[1], &[(5, leaves[5])], 16));
+    assert!(!proof.verify::<H>(tree[1], &[(5, leaves[4])], 16));
+    assert!(!proof.verify::<H>(tree[1], &[(4, leaves[5])], 16));
+    assert!(!proof.verify::<H>(tree[1], &[(21, leaves[5])], 16));
+  }
+
+  fn multi_path<H: MerkleHasher>() {
+    let (leaves, tree) = tree::<H>(16);
+    let positions = [3, 2, 3, 12, 0];
+    let proof = MerkleProof::open_batch(&tree, &positions, 16);
+    // 4 new siblings for leaf 3, then 1, 0, 4 and 2
+    assert_eq!(proof.size(), 11 * size_of::<HashDigest>());
+
+    let claims: Vec<_> = positions.iter().map(|&i| (i, leaves[i])).collect();
+    assert!(proof.verify::<H>(tree[1], &claims, 16));
+
+    let mut reordered = claims.clone();
+    reordered.swap(0, 3);
+    assert!(!proof.verify::<H>(tree[1], &reordered, 16));
+    assert!(!proof.verify::<H>(tree[1], &claims[..4], 16));
+
+    let mut tampered = claims;
+    tampered[2].1 = leaves[4];
+    assert!(!proof.verify::<H>(tree[1], &tampered, 16));
+  }
+
+  #[test]
+  fn single_path_all_hashers() {
+    single_path::<Sha256Hasher>();
+    single_path::<Blake3Hasher>();
+    single_path::<Keccak256Hasher>();
+    single_path::<PoseidonHasher>();
+  }
+
+  #[test]
+  fn multi_path_stores_each_sibling_once() {
+    multi_path::<Sha256Hasher>();
+    multi_path::<Blake3Hasher>();
+    multi_path::<Keccak256Hasher>();
+    multi_path::<PoseidonHasher>();
+  }
+
+  #[test]
+  fn hashers_build_different_trees() {
+    let root = |tree: Vec<HashDigest>| tree[1];
+    let sha256 = root(tree::<Sha256Hasher>(8).1);
+    let blake3 = root(tree::<Blake3Hasher>(8).1);
+    let keccak = root(tree::<Keccak256Hasher>(8).1);
+    let poseidon = root(tree::<PoseidonHasher>(8).1);
+    assert!(sha256 != blake3 && blake3 != keccak && keccak != sha256);
+    assert!(poseidon != sha256 && poseidon != blake3 && poseidon != keccak);
+  }
 }
@@ -1,8 +1,12 @@
+use std::fmt::Debug;
+
 use prime_field::FieldElement;
 use ring::digest::{Context, SHA256};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
 
 /// TODO: https://doc.rust-lang.org/beta/core/arch/x86_64/struct.__m128i.html
-#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct HashDigest {
   pub h0: u128,
   pub h1: u128,
@@ -52,28 +56,114 @@ impl HashDigest {
   }
 }
 
-#[inline]
-pub fn my_hash(src: [HashDigest; 2]) -> HashDigest {
-  // the original sha256_update_shani type signature is an optimised function for
-  // SHA-NI instruction sets machine, this is the fallback one
-  // https://www.ic.unicamp.br/~ra142685/sok-apkc.pdf
-  // let mut hasher = Sha3_256::new();
-  let mut ctx = Context::new(&SHA256);
-
-  src.iter().for_each(|h| {
-    ctx.update(&h.h0.to_be_bytes());
-    ctx.update(&h.h1.to_be_bytes());
-  });
-
-  let mut hash = HashDigest::default();
-  let digest = ctx.finish();
-  let digest_bytes = digest.as_ref();
-  let mut digest_u128 = [0u8; 16];
+/// Two to one compression function of the merkle trees, the merkle-damgard
+/// column hashing and the Fiat-Shamir transcript
+pub trait MerkleHasher: Default + Debug + Clone + Copy + Send + Sync {
+  fn hash(src: [HashDigest; 2]) -> HashDigest;
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Sha256Hasher;
+
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Blake3Hasher;
+
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Keccak256Hasher;
+
+/// Both digests as big endian `h0 || h1`
+fn input_bytes(src: [HashDigest; 2]) -> [u8; 64] {
+  let mut bytes = [0u8; 64];
+  for (chunk, h) in bytes.chunks_exact_mut(32).zip(src.iter()) {
+    chunk[..16].copy_from_slice(&h.h0.to_be_bytes());
+    chunk[16..].copy_from_slice(&h.h1.to_be_bytes());
+  }
+  bytes
+}
 
+fn digest_from_bytes(digest_bytes: &[u8]) -> HashDigest {
+  let mut digest_u128 = [0u8; 16];
   digest_u128.copy_from_slice(&digest_bytes[..16]);
-  hash.h0 = u128::from_be_bytes(digest_u128);
-  digest_u128.copy_from_slice(&digest_bytes[16..]);
-  hash.h1 = u128::from_be_bytes(digest_u128);
+  let h0 = u128::from_be_bytes(digest_u128);
+  digest_u128.copy_from_slice(&digest_bytes[16..32]);
+  let h1 = u128::from_be_bytes(digest_u128);
+  HashDigest { h0, h1 }
+}
+
+impl MerkleHasher for Sha256Hasher {
+  #[inline]
+  fn hash(src: [HashDigest; 2]) -> HashDigest {
+    // the original sha256_update_shani type signature is an optimised function for
+    // SHA-NI instruction sets machine, this is the fallback one
+    // https://www.ic.unicamp.br/~ra142685/sok-apkc.pdf
+    let mut ctx = Context::new(&SHA256);
+    ctx.update(&input_bytes(src));
+    digest_from_bytes(ctx.finish().as_ref())
+  }
+}
+
+impl MerkleHasher for Blake3Hasher {
+  #[inline]
+  fn hash(src: [HashDigest; 2]) -> HashDigest {
+    digest_from_bytes(blake3::hash(&input_bytes(src)).as_bytes())
+  }
+}
+
+impl MerkleHasher for Keccak256Hasher {
+  #[inline]
+  fn hash(src: [HashDigest; 2]) -> HashDigest {
+    digest_from_bytes(&Keccak256::digest(input_bytes(src)))
+  }
+}
+
+#[inline]
+pub fn my_hash(src: [HashDigest; 2]) -> HashDigest { Sha256Hasher::hash(src) }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-  hash
+  fn counting_input() -> [HashDigest; 2] {
+    let bytes: Vec<u8> = (0..64).collect();
+    [digest_from_bytes(&bytes[..32]), digest_from_bytes(&bytes[32..])]
+  }
+
+  fn hex(h: HashDigest) -> String { format!("{:032x}{:032x}", h.h0, h.h1) }
+
+  #[test]
+  fn sha256_vectors() {
+    assert_eq!(
+      hex(Sha256Hasher::hash([HashDigest::default(); 2])),
+      "f5a5fd42d16a20302798ef6ed309979b43003d2320d9f0e8ea9831a92759fb4b"
+    );
+    assert_eq!(
+      hex(Sha256Hasher::hash(counting_input())),
+      "fdeab9acf3710362bd2658cdc9a29e8f9c757fcf9811603a8c447cd1d9151108"
+    );
+    assert_eq!(
+      my_hash(counting_input()),
+      Sha256Hasher::hash(counting_input())
+    );
+  }
+
+  #[test]
+  fn blake3_vectors() {
+    assert_eq!(
+      hex(Blake3Hasher::hash([HashDigest::default(); 2])),
+      "4d006976636a8696d909a630a4081aad4d7c50f81afdee04020bf05086ab6a55"
+    );
+    assert_eq!(
+      hex(Blake3Hasher::hash(counting_input())),
+      "4eed7141ea4a5cd4b788606bd23f46e212af9cacebacdc7d1f4c6dc7f2511b98"
+    );
+  }
+
+  #[test]
+  fn keccak256_vectors() {
+    // keccak256(abi.encodePacked(bytes32(0), bytes32(0)))
+    assert_eq!(
+      hex(Keccak256Hasher::hash([HashDigest::default(); 2])),
+      "ad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5"
+    );
+  }
 }
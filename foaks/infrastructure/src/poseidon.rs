@@ -0,0 +1,253 @@
+//! Poseidon permutation over the quadratic extension of `2^61 - 1`.
+//!
+//! The two to one compression absorbs both digests as four field elements
+//! into a width 6 state (rate 4, capacity 2) and squeezes two field elements,
+//! packed the same way as merkle leaves. Unlike the byte oriented hashers it
+//! is cheap to recompute inside a circuit over `2^61 - 1`, the matching gadget
+//! lives in `ark_r1cs_std::poseidon`.
+//!
+//! Parameters:
+//! - S-box `x^17`, the smallest `alpha` coprime to `p^2 - 1`.
+//! - 8 full and 30 partial rounds. The interpolation bound for a 122 bit field
+//!   with `alpha = 17` needs 31 rounds, the rest is margin. These parameters
+//!   have not been audited.
+//! - Round constants come from the Grain LFSR of the Poseidon reference
+//!   implementation seeded with `(n = 61, t = 6, R_F = 8, R_P = 30)`, sampling
+//!   61 bit values by rejection, real part first.
+//! - The MDS matrix is the Cauchy matrix `1 / (i + j + 6)` for `i, j < 6`.
+
+use std::sync::OnceLock;
+
+use prime_field::{constants::MOD, FieldElement};
+
+use crate::my_hash::{HashDigest, MerkleHasher};
+
+pub const WIDTH: usize = 6;
+pub const RATE: usize = 4;
+pub const CAPACITY: usize = WIDTH - RATE;
+pub const FULL_ROUNDS: usize = 8;
+pub const PARTIAL_ROUNDS: usize = 30;
+pub const ALPHA: u64 = 17;
+pub const FIELD_BITS: usize = 61;
+
+#[derive(Debug, Clone)]
+pub struct PoseidonParams {
+  pub round_constants: Vec<[FieldElement; WIDTH]>,
+  pub mds: [[FieldElement; WIDTH]; WIDTH],
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+pub struct PoseidonHasher;
+
+/// Grain LFSR in self-shrinking mode, as in the Poseidon reference scripts
+struct GrainLfsr {
+  state: [bool; 80],
+  head: usize,
+}
+
+impl GrainLfsr {
+  fn new(field_bits: usize, width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+    let mut state = [false; 80];
+    let mut pos = 0;
+    let mut push = |value: usize, len: usize| {
+      for i in (0..len).rev() {
+        state[pos] = (value >> i) & 1 == 1;
+        pos += 1;
+      }
+    };
+    // prime field, x^alpha S-box
+    push(1, 2);
+    push(0, 4);
+    push(field_bits, 12);
+    push(width, 12);
+    push(full_rounds, 10);
+    push(partial_rounds, 10);
+    push((1 << 30) - 1, 30);
+
+    let mut lfsr = Self { state, head: 0 };
+    for _ in 0..160 {
+      lfsr.clock();
+    }
+    lfsr
+  }
+
+  fn clock(&mut self) -> bool {
+    let bit = |offset: usize| self.state[(self.head + offset) % 80];
+    let new_bit = bit(62) ^ bit(51) ^ bit(38) ^ bit(23) ^ bit(13) ^ bit(0);
+    self.state[self.head] = new_bit;
+    self.head = (self.head + 1) % 80;
+    new_bit
+  }
+
+  fn next_bit(&mut self) -> bool {
+    loop {
+      let keep = self.clock();
+      let bit = self.clock();
+      if keep {
+        return bit;
+      }
+    }
+  }
+
+  fn next_base_element(&mut self) -> u64 {
+    loop {
+      let value = (0..FIELD_BITS).fold(0u64, |acc, _| (acc << 1) | self.next_bit() as u64);
+      if value < MOD {
+        return value;
+      }
+    }
+  }
+
+  fn next_field_element(&mut self) -> FieldElement {
+    let real = self.next_base_element();
+    let img = self.next_base_element();
+    FieldElement::new(real, img)
+  }
+}
+
+impl PoseidonParams {
+  fn generate() -> Self {
+    let mut lfsr = GrainLfsr::new(FIELD_BITS, WIDTH, FULL_ROUNDS, PARTIAL_ROUNDS);
+    let round_constants = (0..FULL_ROUNDS + PARTIAL_ROUNDS)
+      .map(|_| [(); WIDTH].map(|_| lfsr.next_field_element()))
+      .collect();
+
+    let mut mds = [[FieldElement::zero(); WIDTH]; WIDTH];
+    for (i, row) in mds.iter_mut().enumerate() {
+      for (j, entry) in row.iter_mut().enumerate() {
+        *entry = FieldElement::from_real((i + j + WIDTH) as u64).inverse();
+      }
+    }
+
+    Self {
+      round_constants,
+      mds,
+    }
+  }
+}
+
+pub fn params() -> &'static PoseidonParams {
+  static PARAMS: OnceLock<PoseidonParams> = OnceLock::new();
+  PARAMS.get_or_init(PoseidonParams::generate)
+}
+
+#[inline]
+fn sbox(x: FieldElement) -> FieldElement {
+  let x2 = x * x;
+  let x4 = x2 * x2;
+  let x8 = x4 * x4;
+  x8 * x8 * x
+}
+
+pub fn permute(state: &mut [FieldElement; WIDTH]) {
+  let params = params();
+  let half_full = FULL_ROUNDS / 2;
+
+  for (round, constants) in params.round_constants.iter().enumerate() {
+    for (x, c) in state.iter_mut().zip(constants.iter()) {
+      *x = *x + *c;
+    }
+
+    if round < half_full || round >= half_full + PARTIAL_ROUNDS {
+      for x in state.iter_mut() {
+        *x = sbox(*x);
+      }
+    } else {
+      state[0] = sbox(state[0]);
+    }
+
+    let mut mixed = [FieldElement::zero(); WIDTH];
+    for (out, row) in mixed.iter_mut().zip(params.mds.iter()) {
+      for (m, x) in row.iter().zip(state.iter()) {
+        *out = *out + *m * *x;
+      }
+    }
+    *state = mixed;
+  }
+}
+
+/// Inverse of the leaf packing, `u128` halves above the modulus are reduced
+fn digest_elements(digest: HashDigest) -> [FieldElement; 2] {
+  let element = |h: u128| FieldElement::new(((h >> 64) as u64) % MOD, (h as u64) % MOD);
+  [element(digest.h0), element(digest.h1)]
+}
+
+impl MerkleHasher for PoseidonHasher {
+  fn hash(src: [HashDigest; 2]) -> HashDigest {
+    let mut state = [FieldElement::zero(); WIDTH];
+    let [a0, a1] = digest_elements(src[0]);
+    let [b0, b1] = digest_elements(src[1]);
+    state[CAPACITY..].copy_from_slice(&[a0, a1, b0, b1]);
+
+    permute(&mut state);
+    HashDigest::memcpy_from_field_elements([state[CAPACITY], state[CAPACITY + 1]])
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // also checked by the in-circuit gadget tests of ark-r1cs-std
+  #[test]
+  fn parameters() {
+    let params = params();
+    assert_eq!(params.round_constants.len(), FULL_ROUNDS + PARTIAL_ROUNDS);
+    assert_eq!(
+      params.round_constants[0][0],
+      FieldElement::new(2025068226995972209, 460696318347934662)
+    );
+    assert_eq!(
+      params.mds[1][2] * FieldElement::from_real(9),
+      FieldElement::real_one()
+    );
+  }
+
+  #[test]
+  fn permutation_vectors() {
+    let mut state = [FieldElement::zero(); WIDTH];
+    permute(&mut state);
+    assert_eq!(
+      state,
+      [
+        FieldElement::new(359328414208176523, 644084420742128250),
+        FieldElement::new(1548504500088249010, 83733432390816422),
+        FieldElement::new(2062391404226044146, 104344056491147863),
+        FieldElement::new(1903417851960681286, 1224020731669523446),
+        FieldElement::new(2010365227268782606, 289927892770311901),
+        FieldElement::new(1973665981007036655, 1548501366178755857),
+      ]
+    );
+
+    let mut state = [FieldElement::zero(); WIDTH];
+    for (i, x) in state.iter_mut().enumerate() {
+      *x = FieldElement::new(i as u64, (i + WIDTH) as u64);
+    }
+    permute(&mut state);
+    assert_eq!(
+      state,
+      [
+        FieldElement::new(1148174422229025470, 388482865900556289),
+        FieldElement::new(404086809262610216, 1855407273105801168),
+        FieldElement::new(1089296707972025237, 1883085730324888203),
+        FieldElement::new(510504912242687085, 570277774103056198),
+        FieldElement::new(794118730322698530, 231188705230079989),
+        FieldElement::new(2220924848000554619, 2130591855111670392),
+      ]
+    );
+  }
+
+  #[test]
+  fn hasher_vectors() {
+    let leaf = |i| {
+      HashDigest::memcpy_from_field_elements([FieldElement::new(i, 1), FieldElement::new(2, i)])
+    };
+    assert_eq!(
+      PoseidonHasher::hash([leaf(3), leaf(4)]),
+      HashDigest::memcpy_from_field_elements([
+        FieldElement::new(2232180723706229168, 956883930679334679),
+        FieldElement::new(642474643030698172, 816363376046804525),
+      ])
+    );
+  }
+}
@@ -0,0 +1,252 @@
+//! Versioned binary encoding of proofs and public parameters.
+//!
+//! An encoded proof is a 16 byte header followed by the payload:
+//!
+//! | offset | size | content                                     |
+//! |--------|------|---------------------------------------------|
+//! | 0      | 4    | magic `b"FOAK"`                             |
+//! | 4      | 2    | [`FORMAT_VERSION`], little endian           |
+//! | 6      | 1    | [`ProofKind`] of the payload                |
+//! | 7      | 1    | reserved, always zero                       |
+//! | 8      | 8    | payload length in bytes, little endian      |
+//!
+//! The payload is the proof serialized with bincode using fixed width little
+//! endian integers. Structs are their fields in declaration order, sequences
+//! are prefixed by their length as a `u64`, a `FieldElement` is `real` then
+//! `img`, both reduced below `2^61 - 1`, and a `HashDigest` is `h0` then `h1`.
+//!
+//! Decoding rejects a wrong magic, version, kind or length, trailing bytes,
+//! unreduced field elements and proofs failing [`ProofFormat::validate`].
+
+use bincode::Options;
+use serde::{de::DeserializeOwned, Serialize};
+
+pub const MAGIC: [u8; 4] = *b"FOAK";
+pub const FORMAT_VERSION: u16 = 1;
+pub const HEADER_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ProofKind {
+  LdtCommitment = 1,
+  CommitPhaseData = 2,
+  PolyCommit = 3,
+  GkrLayer = 4,
+  Gkr = 5,
+  LinearPcOpening = 6,
+  ExpanderGraphs = 7,
+  Sumcheck = 8,
+  Spartan = 9,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ProofFormatError {
+  Truncated,
+  BadMagic,
+  UnsupportedVersion(u16),
+  UnexpectedKind { expected: ProofKind, found: u8 },
+  LengthMismatch { expected: u64, found: usize },
+  BincodeError(bincode::Error),
+  Malformed(&'static str),
+}
+
+impl From<bincode::Error> for ProofFormatError {
+  fn from(err: bincode::Error) -> Self { Self::BincodeError(err) }
+}
+
+pub trait ProofFormat: Serialize + DeserializeOwned {
+  const KIND: ProofKind;
+
+  /// Shape checks run on every decoded proof, they do not replace verification
+  fn validate(&self) -> Result<(), ProofFormatError> { Ok(()) }
+
+  fn to_bytes(&self) -> Result<Vec<u8>, ProofFormatError> { encode(Self::KIND, self) }
+
+  fn from_bytes(bytes: &[u8]) -> Result<Self, ProofFormatError> {
+    let proof: Self = decode(Self::KIND, bytes)?;
+    proof.validate()?;
+    Ok(proof)
+  }
+}
+
+fn options() -> impl Options {
+  bincode::DefaultOptions::new()
+    .with_fixint_encoding()
+    .with_little_endian()
+    .reject_trailing_bytes()
+}
+
+fn encode<T: Serialize + ?Sized>(kind: ProofKind, value: &T) -> Result<Vec<u8>, ProofFormatError> {
+  let payload = options().serialize(value)?;
+
+  let mut bytes = Vec::with_capacity(HEADER_SIZE + payload.len());
+  bytes.extend_from_slice(&MAGIC);
+  bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+  bytes.push(kind as u8);
+  bytes.push(0);
+  bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+  bytes.extend_from_slice(&payload);
+  Ok(bytes)
+}
+
+fn decode<T: DeserializeOwned>(kind: ProofKind, bytes: &[u8]) -> Result<T, ProofFormatError> {
+  if bytes.len() < HEADER_SIZE {
+    return Err(ProofFormatError::Truncated);
+  }
+  let (header, payload) = bytes.split_at(HEADER_SIZE);
+
+  if header[0..4] != MAGIC {
+    return Err(ProofFormatError::BadMagic);
+  }
+  let version = u16::from_le_bytes([header[4], header[5]]);
+  if version != FORMAT_VERSION {
+    return Err(ProofFormatError::UnsupportedVersion(version));
+  }
+  if header[6] != kind as u8 {
+    return Err(ProofFormatError::UnexpectedKind {
+      expected: kind,
+      found: header[6],
+    });
+  }
+  if header[7] != 0 {
+    return Err(ProofFormatError::Malformed("reserved header byte is set"));
+  }
+  let length = u64::from_le_bytes(header[8..16].try_into().expect("header is 16 bytes"));
+  if length != payload.len() as u64 {
+    return Err(ProofFormatError::LengthMismatch {
+      expected: length,
+      found: payload.len(),
+    });
+  }
+
+  // the limit keeps forged sequence lengths from allocating past the payload
+  Ok(
+    options()
+      .with_limit(payload.len() as u64)
+      .deserialize(payload)?,
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use prime_field::{constants::MOD, FieldElement};
+
+  use super::*;
+  use crate::my_hash::HashDigest;
+
+  impl ProofFormat for Vec<FieldElement> {
+    const KIND: ProofKind = ProofKind::GkrLayer;
+  }
+
+  fn sample() -> Vec<FieldElement> {
+    vec![
+      FieldElement::new(1, 2),
+      FieldElement::new(MOD - 1, 0),
+      FieldElement::new(0, MOD - 1),
+    ]
+  }
+
+  #[test]
+  fn field_elements_round_trip() {
+    let bytes = sample().to_bytes().unwrap();
+    assert_eq!(bytes.len(), HEADER_SIZE + 8 + 3 * 16);
+    assert_eq!(Vec::<FieldElement>::from_bytes(&bytes).unwrap(), sample());
+  }
+
+  #[test]
+  fn field_element_encoding_is_canonical() {
+    let unreduced = vec![FieldElement::new(MOD, MOD + 2)];
+    let bytes = unreduced.to_bytes().unwrap();
+    assert_eq!(
+      Vec::<FieldElement>::from_bytes(&bytes).unwrap(),
+      vec![FieldElement::new(0, 2)]
+    );
+
+    let mut forged = sample().to_bytes().unwrap();
+    forged[HEADER_SIZE + 8..HEADER_SIZE + 16].copy_from_slice(&MOD.to_le_bytes());
+    assert!(matches!(
+      Vec::<FieldElement>::from_bytes(&forged),
+      Err(ProofFormatError::BincodeError(_))
+    ));
+  }
+
+  #[test]
+  fn hash_digest_round_trip() {
+    let digest = HashDigest::new_from_c(1, 2, 3, u64::MAX);
+    let bytes = options().serialize(&digest).unwrap();
+    assert_eq!(bytes.len(), 32);
+    assert_eq!(options().deserialize::<HashDigest>(&bytes).unwrap(), digest);
+  }
+
+  #[test]
+  fn rejects_bad_header() {
+    let bytes = sample().to_bytes().unwrap();
+
+    assert!(matches!(
+      Vec::<FieldElement>::from_bytes(&bytes[..HEADER_SIZE - 1]),
+      Err(ProofFormatError::Truncated)
+    ));
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] = b'X';
+    assert!(matches!(
+      Vec::<FieldElement>::from_bytes(&bad_magic),
+      Err(ProofFormatError::BadMagic)
+    ));
+
+    let mut bad_version = bytes.clone();
+    bad_version[4] = 2;
+    assert!(matches!(
+      Vec::<FieldElement>::from_bytes(&bad_version),
+      Err(ProofFormatError::UnsupportedVersion(2))
+    ));
+
+    let mut bad_kind = bytes.clone();
+    bad_kind[6] = ProofKind::Gkr as u8;
+    assert!(matches!(
+      Vec::<FieldElement>::from_bytes(&bad_kind),
+      Err(ProofFormatError::UnexpectedKind { found: 5, .. })
+    ));
+
+    let mut reserved = bytes;
+    reserved[7] = 1;
+    assert!(matches!(
+      Vec::<FieldElement>::from_bytes(&reserved),
+      Err(ProofFormatError::Malformed(_))
+    ));
+  }
+
+  #[test]
+  fn rejects_bad_lengths() {
+    let bytes = sample().to_bytes().unwrap();
+
+    assert!(matches!(
+      Vec::<FieldElement>::from_bytes(&bytes[..bytes.len() - 1]),
+      Err(ProofFormatError::LengthMismatch { .. })
+    ));
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(matches!(
+      Vec::<FieldElement>::from_bytes(&trailing),
+      Err(ProofFormatError::LengthMismatch { .. })
+    ));
+
+    // sequence length larger than the payload
+    let mut forged = bytes.clone();
+    forged[HEADER_SIZE..HEADER_SIZE + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(matches!(
+      Vec::<FieldElement>::from_bytes(&forged),
+      Err(ProofFormatError::BincodeError(_))
+    ));
+
+    // shorter sequence leaves trailing payload bytes
+    let mut short = bytes;
+    short[HEADER_SIZE..HEADER_SIZE + 8].copy_from_slice(&2u64.to_le_bytes());
+    assert!(matches!(
+      Vec::<FieldElement>::from_bytes(&short),
+      Err(ProofFormatError::BincodeError(_))
+    ));
+  }
+}
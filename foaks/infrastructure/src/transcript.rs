@@ -0,0 +1,102 @@
+use std::marker::PhantomData;
+
+use prime_field::{constants::MOD, FieldElement};
+
+use crate::my_hash::{HashDigest, MerkleHasher, Sha256Hasher};
+
+/// Prefix of the squeezes, separates them from the absorbs of any digest
+const SQUEEZE_TAG: HashDigest = HashDigest { h0: 1, h1: 0 };
+
+/// Fiat-Shamir transcript built on top of a [`MerkleHasher`].
+///
+/// Every message sent by the prover is absorbed into a running hash chain and
+/// every verifier challenge is squeezed out of it, so both sides derive the same
+/// randomness without interaction.
+#[derive(Default, Debug, Clone)]
+pub struct Transcript<H: MerkleHasher = Sha256Hasher> {
+  state: HashDigest,
+  hasher: PhantomData<H>,
+}
+
+impl<H: MerkleHasher> Transcript<H> {
+  pub fn new() -> Self { Default::default() }
+
+  pub fn absorb_digest(&mut self, digest: HashDigest) {
+    self.state = H::hash([self.state, digest]);
+  }
+
+  pub fn absorb_field_element(&mut self, x: FieldElement) {
+    self.absorb_digest(HashDigest::memcpy_from_field_element(x));
+  }
+
+  /// Absorbs the number of elements, then the elements two at a time, the same
+  /// packing used for merkle leaves, so that `[x]` and `[x, 0]` differ
+  pub fn absorb_field_elements(&mut self, xs: &[FieldElement]) {
+    self.absorb_digest(HashDigest {
+      h0: xs.len() as u128,
+      h1: 0,
+    });
+    for chunk in xs.chunks(2) {
+      match chunk {
+        [x, y] => self.absorb_digest(HashDigest::memcpy_from_field_elements([*x, *y])),
+        [x] => self.absorb_field_element(*x),
+        _ => unreachable!(),
+      }
+    }
+  }
+
+  /// The state comes second, so a squeeze matches an absorb only if the state
+  /// is [`SQUEEZE_TAG`], which takes a preimage of the hash
+  fn squeeze_digest(&mut self) -> HashDigest {
+    self.state = H::hash([SQUEEZE_TAG, self.state]);
+    self.state
+  }
+
+  pub fn squeeze_field_element(&mut self) -> FieldElement {
+    let digest = self.squeeze_digest();
+    let real = ((digest.h0 >> 64) as u64) % MOD;
+    let img = (digest.h0 as u64) % MOD;
+    FieldElement::new(real, img)
+  }
+
+  pub fn squeeze_field_elements(&mut self, size: usize) -> Vec<FieldElement> {
+    (0..size).map(|_| self.squeeze_field_element()).collect()
+  }
+
+  /// Returns an index in `0..bound`
+  pub fn squeeze_index(&mut self, bound: usize) -> usize {
+    assert!(bound > 0);
+    (self.squeeze_digest().h0 % bound as u128) as usize
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn absorbs_the_length() {
+    let x = FieldElement::new(3, 5);
+    let squeeze = |xs: &[FieldElement]| {
+      let mut transcript = Transcript::<Sha256Hasher>::new();
+      transcript.absorb_field_elements(xs);
+      transcript.squeeze_field_element()
+    };
+    assert_ne!(squeeze(&[x]), squeeze(&[x, FieldElement::zero()]));
+    assert_ne!(squeeze(&[]), squeeze(&[FieldElement::zero()]));
+    assert_eq!(squeeze(&[x]), squeeze(&[x]));
+  }
+
+  #[test]
+  fn squeezes_differ_from_absorbs() {
+    let mut squeezed = Transcript::<Sha256Hasher>::new();
+    squeezed.squeeze_field_element();
+    let mut absorbed = Transcript::<Sha256Hasher>::new();
+    absorbed.absorb_digest(HashDigest::default());
+    assert_ne!(squeezed.state, absorbed.state);
+    assert_ne!(
+      squeezed.squeeze_field_element(),
+      absorbed.squeeze_field_element()
+    );
+  }
+}
@@ -1,13 +1,18 @@
 use std::vec::Vec;
 
+use infrastructure::proof_format::{ProofFormat, ProofFormatError, ProofKind};
 use prime_field::FieldElement;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::parameter::DISTANCE_THRESHOLD;
 use crate::parameter::*;
 
 use global::constants::*;
 
-#[derive(Default, Clone)]
+/// Only the left side is serialized, the right side is rebuilt on load
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(into = "GraphEdges", try_from = "GraphEdges")]
 pub struct Graph {
   pub degree: usize,
   pub neighbor: Vec<Vec<usize>>,
@@ -18,23 +23,157 @@ pub struct Graph {
   pub r: usize,
 }
 
-#[derive(Default)]
+#[derive(Clone, Serialize, Deserialize)]
+struct GraphEdges {
+  degree: usize,
+  l: usize,
+  r: usize,
+  neighbor: Vec<Vec<usize>>,
+  weight: Vec<Vec<FieldElement>>,
+}
+
+impl From<Graph> for GraphEdges {
+  fn from(graph: Graph) -> Self {
+    Self {
+      degree: graph.degree,
+      l: graph.l,
+      r: graph.r,
+      neighbor: graph.neighbor,
+      weight: graph.weight,
+    }
+  }
+}
+
+impl TryFrom<GraphEdges> for Graph {
+  type Error = &'static str;
+
+  fn try_from(edges: GraphEdges) -> Result<Self, Self::Error> {
+    if edges.neighbor.len() != edges.l || edges.weight.len() != edges.l {
+      return Err("expander graph has the wrong number of left vertices");
+    }
+
+    let mut r_neighbor = vec![vec![]; edges.r];
+    let mut r_weight = vec![vec![]; edges.r];
+    for (i, (targets, weights)) in edges.neighbor.iter().zip(edges.weight.iter()).enumerate() {
+      if targets.len() != edges.degree || weights.len() != edges.degree {
+        return Err("expander graph vertex has the wrong degree");
+      }
+      for (&target, &weight) in targets.iter().zip(weights.iter()) {
+        if target >= edges.r {
+          return Err("expander graph edge is out of range");
+        }
+        r_neighbor[target].push(i);
+        r_weight[target].push(weight);
+      }
+    }
+
+    Ok(Self {
+      degree: edges.degree,
+      neighbor: edges.neighbor,
+      r_neighbor,
+      weight: edges.weight,
+      r_weight,
+      l: edges.l,
+      r: edges.r,
+    })
+  }
+}
+
+/// The expander graphs of every recursion level of the code, `c` from the
+/// message to the next level and `d` from that level's codeword to the output
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExpanderGraphs {
+  pub params: CodeParams,
+  pub c: Vec<Graph>,
+  pub d: Vec<Graph>,
+}
+
+impl ProofFormat for ExpanderGraphs {
+  const KIND: ProofKind = ProofKind::ExpanderGraphs;
+
+  /// The sizes of every level follow from the message size, the left size
+  /// of the first graph
+  fn validate(&self) -> Result<(), ProofFormatError> {
+    if self.params.validate().is_err() {
+      return Err(ProofFormatError::Malformed("invalid code parameters"));
+    }
+    let n = self.c.first().map_or(0, |c| c.l);
+    let (levels, _) = level_sizes(&self.params, n);
+    if self.c.len() != levels.len() || self.d.len() != levels.len() || levels.len() > MAX_DEPTH {
+      return Err(ProofFormatError::Malformed(
+        "wrong number of expander levels",
+      ));
+    }
+    if self
+      .c
+      .iter()
+      .zip(self.d.iter())
+      .zip(levels)
+      .any(|((c, d), level)| level.c != (c.l, c.r) || level.d != (d.l, d.r))
+    {
+      return Err(ProofFormatError::Malformed(
+        "expander graph sizes differ from the code",
+      ));
+    }
+    let degrees = (self.params.cn, self.params.dn);
+    if self
+      .c
+      .iter()
+      .zip(self.d.iter())
+      .any(|(c, d)| (c.degree, d.degree) != degrees)
+    {
+      return Err(ProofFormatError::Malformed(
+        "expander degree differs from parameters",
+      ));
+    }
+    Ok(())
+  }
+}
+
+const MAX_DEPTH: usize = 100;
+
+/// Working memory of [`LinearCodeEncodeContext::encode_with`], a codeword and
+/// a message buffer per recursion level. Reusing one across calls avoids
+/// reallocating them for every message.
+#[derive(Default, Clone)]
+pub struct EncodeScratch {
+  codeword: Vec<Vec<FieldElement>>,
+  message: Vec<Vec<FieldElement>>,
+}
+
+impl EncodeScratch {
+  fn reserve(&mut self, n: usize) {
+    if self.codeword.first().map_or(0, Vec::len) >= 2 * n {
+      return;
+    }
+    self.codeword.clear();
+    self.message.clear();
+    let mut i = 0;
+    while (n >> i) > 1 {
+      let size = (2 * n) >> i;
+      self.codeword.push(vec![FE_ZERO; size]);
+      self.message.push(vec![FE_ZERO; size]);
+      i += 1
+    }
+  }
+}
+
+#[derive(Default, Clone)]
 pub struct LinearCodeEncodeContext {
-  pub scratch: Vec<Vec<Vec<FieldElement>>>,
+  pub params: CodeParams,
+  pub scratch: EncodeScratch,
   pub c: Vec<Graph>,
   pub d: Vec<Graph>,
-  pub encode_initialized: bool,
 }
 
 impl LinearCodeEncodeContext {
   // Todo: Refactor, use Vec::with_capacity(100) instead of vec![vec![]; 100]
-  pub fn init() -> Self {
-    let scratch = vec![vec![vec![]; 100]; 2];
-    let c = vec![Graph::default(); 100];
-    let d = vec![Graph::default(); 100];
+  pub fn init(params: CodeParams) -> Self {
+    let c = vec![Graph::default(); MAX_DEPTH];
+    let d = vec![Graph::default(); MAX_DEPTH];
 
     Self {
-      scratch,
+      params,
       c,
       d,
       ..Default::default()
@@ -42,122 +181,157 @@ impl LinearCodeEncodeContext {
   }
 
   pub fn encode(&mut self, src: &[FieldElement]) -> Vec<FieldElement> {
-    // Todo Refactor, reuse code from encode_scratch
-    // Todo Refactor, delete self.c[dep].degree, self.d[dep].degree
+    let mut scratch = std::mem::take(&mut self.scratch);
+    let dst = self.encode_with(&mut scratch, src);
+    self.scratch = scratch;
+    dst
+  }
+
+  /// Same as [`Self::encode`] with caller owned scratch buffers, so several
+  /// threads can encode with one context
+  pub fn encode_with(
+    &self,
+    scratch: &mut EncodeScratch,
+    src: &[FieldElement],
+  ) -> Vec<FieldElement> {
     let n = src.len();
     let dep = 0;
-    if !self.encode_initialized {
-      self.encode_initialized = true;
-      let mut i = 0;
-      while (n >> i) > 1 {
-        let size = (2 * n) >> i;
-        self.scratch[0][i] = vec![FE_ZERO; size];
-        self.scratch[1][i] = vec![FE_ZERO; size];
-        i += 1
-      }
-    }
+    scratch.reserve(n);
 
-    self.scratch[0][dep][..n].copy_from_slice(src);
-    let mut r: usize = (ALPHA * (n as f64)) as usize;
+    scratch.codeword[dep][..n].copy_from_slice(src);
+    scratch.message[dep].fill(FE_ZERO);
+    expander_mult(&self.c[dep], src, &mut scratch.message[dep]);
 
-    self.scratch[1][dep].fill(FE_ZERO);
+    let r = (self.params.alpha * (n as f64)) as usize;
+    let l = self.encode_scratch(scratch, r, n, dep + 1);
+    assert_eq!(self.d[dep].l, l);
+
+    let r = self.d[dep].r;
+    let (message, output) = scratch.codeword[dep].split_at_mut(n + l);
+    output[..r].fill(FE_ZERO);
+    expander_mult(&self.d[dep], &message[n..], &mut output[..r]);
+
+    scratch.codeword[dep][..n + l + r].to_vec()
+  }
+
+  /// Encodes the message of level `dep - 1` into the codeword of level `dep`
+  /// and appends it after the first `pre_n` elements of level `dep - 1`
+  fn encode_scratch(
+    &self,
+    scratch: &mut EncodeScratch,
+    n: usize,
+    pre_n: usize,
+    dep: usize,
+  ) -> usize {
+    {
+      let (parent_codeword, codeword) = scratch.codeword.split_at_mut(dep);
+      let (parent_message, message) = scratch.message.split_at_mut(dep);
+      let (parent_codeword, parent_message) =
+        (&mut parent_codeword[dep - 1], &parent_message[dep - 1]);
 
-    //expander mult
-    for (i, elem) in src.iter().enumerate() {
-      for d in 0..self.c[dep].degree {
-        let target = self.c[dep].neighbor[i][d];
-        self.scratch[1][dep][target] =
-          self.scratch[1][dep][target] + self.c[dep].weight[i][d] * *elem;
+      if n <= self.params.distance_threshold() {
+        parent_codeword[pre_n..pre_n + n].copy_from_slice(&parent_message[..n]);
+        return n;
       }
+      codeword[0][..n].copy_from_slice(&parent_message[..n]);
+      message[0].fill(FE_ZERO);
+      expander_mult(&self.c[dep], &parent_message[..n], &mut message[0]);
     }
-    let l: usize = self.encode_scratch(r, n, dep + 1);
 
+    let r = (self.params.alpha * (n as f64)) as usize;
+    let l = self.encode_scratch(scratch, r, n, dep + 1);
     assert_eq!(self.d[dep].l, l);
-    // R consumed
-    r = self.d[dep].r;
-    let zeros = vec![FE_ZERO; r];
-    self.scratch[0][dep][n + l..n + l + r].copy_from_slice(&zeros);
-
-    for (i, val) in self.scratch[0][dep][n..n + l].to_owned().iter().enumerate() {
-      for d in 0..self.d[dep].degree {
-        let target = self.d[dep].neighbor[i][d];
-        self.scratch[0][dep][n + l + target] =
-          self.scratch[0][dep][n + l + target] + *val * self.d[dep].weight[i][d];
-      }
-    }
 
-    let dst = &self.scratch[0][dep][..n + l + r];
-    dst.to_owned()
+    let r = self.d[dep].r;
+    let (parent_codeword, codeword) = scratch.codeword.split_at_mut(dep);
+    let (message, output) = codeword[0].split_at_mut(n + l);
+    output[..r].fill(FE_ZERO);
+    expander_mult(&self.d[dep], &message[n..], &mut output[..r]);
+
+    parent_codeword[dep - 1][pre_n..pre_n + n + l + r].copy_from_slice(&codeword[0][..n + l + r]);
+    n + l + r
   }
 
-  pub fn encode_scratch(&mut self, n: usize, pre_n: usize, dep: usize) -> usize {
-    if n <= DISTANCE_THRESHOLD {
-      let slc = self.scratch[1][dep - 1][..n].to_owned();
-      self.scratch[0][dep - 1][pre_n..pre_n + n].copy_from_slice(&slc);
-      return n;
+  /// Generates the expander graphs for messages of size `n` from a ChaCha20
+  /// stream keyed by `seed`, so any party knowing `n` and the seed rebuilds
+  /// the same code. Returns the codeword size.
+  pub fn expander_init(&mut self, n: usize, seed: &ExpanderSeed) -> usize {
+    let mut rng = ChaCha20Rng::from_seed(*seed);
+    let (levels, codeword_size) = level_sizes(&self.params, n);
+    // the deepest level is sampled first
+    for (dep, level) in levels.iter().enumerate().rev() {
+      let ((c_l, c_r), (d_l, d_r)) = (level.c, level.d);
+      self.c[dep] = generate_random_expander(c_l, c_r, self.params.cn, &mut rng);
+      self.d[dep] = generate_random_expander(d_l, d_r, self.params.dn, &mut rng);
     }
-    let slc = self.scratch[1][dep - 1][..n].to_owned();
-    self.scratch[0][dep][..n].copy_from_slice(&slc);
-
-    let mut r = (ALPHA * (n as f64)) as usize;
-    self.scratch[1][dep].fill(FE_ZERO);
+    codeword_size
+  }
 
-    //expander mult
-    for (i, val) in self.scratch[1][dep - 1]
-      .to_owned()
-      .iter()
-      .enumerate()
-      .take(n)
-    {
-      for d in 0..self.c[dep].degree {
-        let target = self.c[dep].neighbor[i][d];
-        self.scratch[1][dep][target] =
-          self.scratch[1][dep][target] + self.c[dep].weight[i][d] * *val;
-      }
-    }
-    let l: usize = self.encode_scratch(r, n, dep + 1);
-    assert_eq!(self.d[dep].l, l);
-    // R consumed
-    r = self.d[dep].r;
-    let zeros = vec![FE_ZERO; r];
-    self.scratch[0][dep][n + l..n + l + r].copy_from_slice(&zeros);
-
-    for (i, val) in self.scratch[0][dep][n..n + l].to_owned().iter().enumerate() {
-      for d in 0..self.d[dep].degree {
-        let target = self.d[dep].neighbor[i][d];
-        self.scratch[0][dep][n + l + target] =
-          self.scratch[0][dep][n + l + target] + *val * self.d[dep].weight[i][d];
-      }
+  /// The initialized levels of the code
+  pub fn graphs(&self) -> ExpanderGraphs {
+    let depth = self.c.iter().take_while(|graph| graph.l > 0).count();
+    ExpanderGraphs {
+      params: self.params,
+      c: self.c[..depth].to_vec(),
+      d: self.d[..depth].to_vec(),
     }
+  }
 
-    let slc = self.scratch[0][dep][..n + l + r].to_owned();
-    self.scratch[0][dep - 1][pre_n..pre_n + n + l + r].copy_from_slice(&slc);
-
-    n + l + r
+  /// Context encoding with graphs from [`Self::graphs`], e.g. loaded with
+  /// [`ProofFormat::from_bytes`]
+  pub fn from_graphs(graphs: ExpanderGraphs) -> Result<Self, ProofFormatError> {
+    graphs.validate()?;
+    let mut ctx = Self::init(graphs.params);
+    for (dep, (c, d)) in graphs.c.into_iter().zip(graphs.d).enumerate() {
+      ctx.c[dep] = c;
+      ctx.d[dep] = d;
+    }
+    Ok(ctx)
   }
+}
 
-  pub fn expander_init(&mut self, n: usize, dep: Option<usize>) -> usize {
-    match n <= DISTANCE_THRESHOLD {
-      true => n,
-      false => {
-        let dep = dep.unwrap_or(0);
-        let alpha_n = (ALPHA * n as f64) as usize;
-        let (l, expander_size) = {
-          let l = self.expander_init(alpha_n, Some(dep + 1));
-          let expander_size = ((n as f64) * (R - 1.0) - l as f64) as usize;
-          (l, expander_size)
-        };
+/// Left and right sizes of the graphs of one recursion level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LevelSizes {
+  c: (usize, usize),
+  d: (usize, usize),
+}
 
-        self.c[dep] = generate_random_expander(n, alpha_n, CN);
-        self.d[dep] = generate_random_expander(l, expander_size, DN);
+/// Graph sizes of every level of the code of messages of size `n`, from the
+/// top level down, and the codeword size
+fn level_sizes(params: &CodeParams, n: usize) -> (Vec<LevelSizes>, usize) {
+  if n <= params.distance_threshold() {
+    return (vec![], n);
+  }
+  let alpha_n = (params.alpha * n as f64) as usize;
+  let (mut levels, l) = level_sizes(params, alpha_n);
+  let expander_size = ((n as f64) * (params.r - 1.0) - l as f64) as usize;
+  levels.insert(
+    0,
+    LevelSizes {
+      c: (n, alpha_n),
+      d: (l, expander_size),
+    },
+  );
+  (levels, n + l + expander_size)
+}
 
-        n + l + expander_size
-      }
+/// Adds `weight * src[i]` to `dst[target]` for every edge of `graph`
+fn expander_mult(graph: &Graph, src: &[FieldElement], dst: &mut [FieldElement]) {
+  for (i, val) in src.iter().enumerate() {
+    for (&target, &weight) in graph.neighbor[i].iter().zip(graph.weight[i].iter()) {
+      dst[target] = dst[target] + weight * *val;
     }
   }
 }
 
-pub fn generate_random_expander(l: usize, r: usize, degree: usize) -> Graph {
+/// Targets are drawn as `u64` so the graph does not depend on the pointer width
+pub fn generate_random_expander<R: Rng + ?Sized>(
+  l: usize,
+  r: usize,
+  degree: usize,
+  rng: &mut R,
+) -> Graph {
   let mut neighbor = Vec::with_capacity(l);
   let mut weight = Vec::with_capacity(l);
 
@@ -168,8 +342,8 @@ pub fn generate_random_expander(l: usize, r: usize, degree: usize) -> Graph {
     neighbor.push(Vec::with_capacity(degree));
     weight.push(Vec::with_capacity(degree));
     for _ in 0..degree {
-      let target = rand::random::<usize>() % r;
-      let tmp_weight = FieldElement::new_random();
+      let target = rng.gen_range(0..r as u64) as usize;
+      let tmp_weight = FieldElement::new_random_from_rng(rng);
       neighbor[i].push(target);
       r_neighbor[target].push(i);
       r_weight[target].push(tmp_weight);
@@ -187,3 +361,109 @@ pub fn generate_random_expander(l: usize, r: usize, degree: usize) -> Graph {
     r_weight,
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const N: usize = 1 << 10;
+
+  fn context(seed: &ExpanderSeed) -> LinearCodeEncodeContext {
+    let mut ctx = LinearCodeEncodeContext::init(CodeParams::brakedown());
+    ctx.expander_init(N, seed);
+    ctx
+  }
+
+  fn message() -> Vec<FieldElement> { (0..N).map(|i| FieldElement::new(i as u64, 7)).collect() }
+
+  #[test]
+  fn seeded_expanders_are_reproducible() {
+    let graphs = context(&DEFAULT_EXPANDER_SEED).graphs();
+    assert!(!graphs.c.is_empty());
+    assert_eq!(graphs, context(&DEFAULT_EXPANDER_SEED).graphs());
+    assert_ne!(graphs, context(&[1; 32]).graphs());
+
+    assert_eq!(
+      context(&DEFAULT_EXPANDER_SEED).encode(&message()),
+      context(&DEFAULT_EXPANDER_SEED).encode(&message())
+    );
+  }
+
+  /// Pins the code of the default seed, a change of the sampling or of the
+  /// stream changes every commitment
+  #[test]
+  fn default_seed_known_answer() {
+    let mut ctx = context(&DEFAULT_EXPANDER_SEED);
+    assert_eq!(
+      ctx.c[0].neighbor[0],
+      [201, 207, 114, 200, 114, 8, 140, 100, 171, 0]
+    );
+    assert_eq!(
+      ctx.c[0].weight[0][0],
+      FieldElement::new(2288324755130227385, 879283797420202683)
+    );
+
+    let codeword = ctx.encode(&message());
+    assert_eq!(codeword.len(), 1761);
+    assert_eq!(codeword[..N], message());
+    assert_eq!(
+      codeword[N],
+      FieldElement::new(1860091942013775931, 2198163122274950141)
+    );
+    assert_eq!(
+      codeword[codeword.len() - 1],
+      FieldElement::new(1009316685735701179, 1420020832243150044)
+    );
+  }
+
+  #[test]
+  fn graphs_round_trip() {
+    let mut ctx = context(&DEFAULT_EXPANDER_SEED);
+    let bytes = ctx.graphs().to_bytes().unwrap();
+    let graphs = ExpanderGraphs::from_bytes(&bytes).unwrap();
+    assert_eq!(graphs, ctx.graphs());
+
+    let mut loaded = LinearCodeEncodeContext::from_graphs(graphs).unwrap();
+    assert_eq!(loaded.encode(&message()), ctx.encode(&message()));
+  }
+
+  #[test]
+  fn rejects_malformed_graphs() {
+    let mut graphs = context(&DEFAULT_EXPANDER_SEED).graphs();
+    graphs.c[0].neighbor[3][1] = graphs.c[0].r;
+    assert!(matches!(
+      ExpanderGraphs::from_bytes(&graphs.to_bytes().unwrap()),
+      Err(ProofFormatError::BincodeError(_))
+    ));
+
+    let mut graphs = context(&DEFAULT_EXPANDER_SEED).graphs();
+    graphs.d.pop();
+    assert!(matches!(
+      ExpanderGraphs::from_bytes(&graphs.to_bytes().unwrap()),
+      Err(ProofFormatError::Malformed(_))
+    ));
+
+    // a smaller graph would make the encoding index out of range
+    let mut graphs = context(&DEFAULT_EXPANDER_SEED).graphs();
+    let last = graphs.d.len() - 1;
+    graphs.d[last] = generate_random_expander(
+      graphs.d[last].l,
+      graphs.d[last].r - 1,
+      graphs.params.dn,
+      &mut ChaCha20Rng::from_seed([0; 32]),
+    );
+    assert!(matches!(
+      ExpanderGraphs::from_bytes(&graphs.to_bytes().unwrap()),
+      Err(ProofFormatError::Malformed(_))
+    ));
+    assert!(LinearCodeEncodeContext::from_graphs(graphs).is_err());
+
+    let mut graphs = context(&DEFAULT_EXPANDER_SEED).graphs();
+    graphs.c.truncate(1);
+    graphs.d.truncate(1);
+    assert!(matches!(
+      ExpanderGraphs::from_bytes(&graphs.to_bytes().unwrap()),
+      Err(ProofFormatError::Malformed(_))
+    ));
+  }
+}
@@ -1,11 +1,212 @@
-// use the same distance parameter from brakedown
-pub const TARGET_DISTANCE: f32 = 0.07;
-//pub const DISTANCE_THRESHOLD: i32 = ((1.0 / TARGET_DISTANCE) as i32) - 1;
-pub const DISTANCE_THRESHOLD: usize = 13;
-//pub const RS_RATE: usize = 2; //never used
-pub const ALPHA: f64 = 0.238;
-pub const BETA: f64 = 0.1205;
-pub const R: f64 = 1.72;
-pub const CN: usize = 10;
-pub const DN: usize = 20;
-pub const COLUMN_SIZE: usize = 128;
+use serde::{Deserialize, Serialize};
+
+pub type ExpanderSeed = [u8; 32];
+/// Public seed of the expander graphs used when none is configured
+pub const DEFAULT_EXPANDER_SEED: ExpanderSeed = *b"zkFOAKS linear code expanders v1";
+
+/// Parameters of the Brakedown linear-time code and of the LinearPC matrix.
+///
+/// Every level of the code maps a message of size `n` with an expander of left
+/// degree `cn` to `alpha * n` elements, encodes those recursively and maps the
+/// result with an expander of left degree `dn` to the rest of the `r * n`
+/// codeword. The relative distance is `beta / r`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CodeParams {
+  pub alpha: f64,
+  pub beta: f64,
+  /// Inverse rate, codeword size over message size
+  pub r: f64,
+  pub cn: usize,
+  pub dn: usize,
+  /// Rows of the committed matrix
+  pub column_size: usize,
+  /// Soundness of the column checks against a far codeword
+  pub security_bits: u32,
+}
+
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum CodeParamsError {
+  /// Only `1 < r <= 2` fits the committed rows of size `2 * n`
+  RateOutOfRange,
+  /// `alpha` and `beta` admit no expanders, see [`CodeParams::min_degrees`]
+  NoExpander,
+  DegreeTooSmall {
+    cn: usize,
+    dn: usize,
+  },
+  ColumnSizeNotPowerOfTwo,
+}
+
+impl Default for CodeParams {
+  fn default() -> Self { Self::brakedown() }
+}
+
+impl CodeParams {
+  /// The parameters of the Brakedown and Orion implementations, rate
+  /// `1 / 1.72` and distance `0.07`
+  pub const fn brakedown() -> Self {
+    Self {
+      alpha: 0.238,
+      beta: 0.1205,
+      r: 1.72,
+      cn: 10,
+      dn: 20,
+      column_size: 128,
+      security_bits: 128,
+    }
+  }
+
+  /// Rate `1 / 1.47`, encodes faster with a shorter codeword but halves the
+  /// distance so the openings query twice as many columns
+  pub const fn high_rate() -> Self {
+    Self {
+      alpha: 0.138,
+      beta: 0.0532,
+      r: 1.47,
+      cn: 9,
+      dn: 26,
+      column_size: 128,
+      security_bits: 128,
+    }
+  }
+
+  /// Rate `1 / 2` and distance `0.09`, a slower and longer encoding that
+  /// needs about a quarter fewer queried columns than [`Self::brakedown`]
+  pub const fn small_proof() -> Self {
+    Self {
+      alpha: 0.3,
+      beta: 0.18,
+      r: 2.0,
+      cn: 14,
+      dn: 20,
+      column_size: 128,
+      security_bits: 128,
+    }
+  }
+
+  pub fn preset(name: &str) -> Option<Self> {
+    match name {
+      "brakedown" => Some(Self::brakedown()),
+      "high_rate" => Some(Self::high_rate()),
+      "small_proof" => Some(Self::small_proof()),
+      _ => None,
+    }
+  }
+
+  pub fn validate(&self) -> Result<(), CodeParamsError> {
+    if !(self.r > 1.0 && self.r <= 2.0) {
+      return Err(CodeParamsError::RateOutOfRange);
+    }
+    if !self.column_size.is_power_of_two() || self.column_size < 2 {
+      return Err(CodeParamsError::ColumnSizeNotPowerOfTwo);
+    }
+    let (cn, dn) = self.min_degrees().ok_or(CodeParamsError::NoExpander)?;
+    if self.cn < cn || self.dn < dn {
+      return Err(CodeParamsError::DegreeTooSmall { cn, dn });
+    }
+    Ok(())
+  }
+
+  pub fn distance(&self) -> f64 { self.beta / self.r }
+
+  /// Messages up to this size are their own codeword
+  pub fn distance_threshold(&self) -> usize { (1.0 / self.distance()) as usize - 1 }
+
+  /// Columns to open so a codeword far from the code passes with probability
+  /// at most `2^-security_bits`
+  pub fn query_count(&self) -> usize {
+    (self.security_bits as f64 / self.bits_per_query()).ceil() as usize
+  }
+
+  /// Security level of the column checks with `query_count` queries
+  pub fn security_level(&self, query_count: usize) -> f64 {
+    query_count as f64 * self.bits_per_query()
+  }
+
+  fn bits_per_query(&self) -> f64 { -(1.0 - self.distance()).log2() }
+
+  /// Smallest expander degrees `(cn, dn)` for which random expanders have the
+  /// distance with overwhelming probability, the bounds of the Brakedown paper
+  /// without the `O(1/n)` terms as in the reference implementations. `None`
+  /// if `alpha` and `beta` are out of range.
+  pub fn min_degrees(&self) -> Option<(usize, usize)> {
+    let (alpha, beta, r) = (self.alpha, self.beta, self.r);
+    let mu = r - 1.0 - r * alpha;
+    let nu = beta + alpha * beta + 0.03;
+    let valid = alpha > 0.0
+      && beta > 0.0
+      && 1.28 * beta < alpha
+      && alpha < 1.0
+      && beta < r
+      && nu < mu
+      && mu < 1.0;
+    if !valid {
+      return None;
+    }
+
+    let cn = (entropy(beta) + alpha * entropy(1.28 * beta / alpha))
+      / (beta * (alpha / (1.28 * beta)).log2());
+    let dn =
+      (r * alpha * entropy(beta / r) + mu * entropy(nu / mu)) / (alpha * beta * (mu / nu).log2());
+    Some((cn.ceil() as usize, dn.ceil() as usize))
+  }
+}
+
+/// Binary entropy
+fn entropy(p: f64) -> f64 { -p * p.log2() - (1.0 - p) * (1.0 - p).log2() }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn presets_are_valid() {
+    for params in [
+      CodeParams::brakedown(),
+      CodeParams::high_rate(),
+      CodeParams::small_proof(),
+    ] {
+      assert_eq!(params.validate(), Ok(()));
+      assert!(params.security_level(params.query_count()) >= 128.0);
+      assert!(params.security_level(params.query_count() - 1) < 128.0);
+    }
+  }
+
+  #[test]
+  fn brakedown_matches_the_reference_constants() {
+    let params = CodeParams::brakedown();
+    assert_eq!(params.min_degrees(), Some((10, 20)));
+    assert_eq!(params.distance_threshold(), 13);
+    assert_eq!(params.query_count(), 1222);
+  }
+
+  #[test]
+  fn rejects_invalid_params() {
+    let params = CodeParams::brakedown();
+    assert_eq!(
+      CodeParams { r: 2.5, ..params }.validate(),
+      Err(CodeParamsError::RateOutOfRange)
+    );
+    assert_eq!(
+      CodeParams { cn: 9, ..params }.validate(),
+      Err(CodeParamsError::DegreeTooSmall { cn: 10, dn: 20 })
+    );
+    assert_eq!(
+      CodeParams {
+        beta: 0.3,
+        ..params
+      }
+      .validate(),
+      Err(CodeParamsError::NoExpander)
+    );
+    assert_eq!(
+      CodeParams {
+        column_size: 96,
+        ..params
+      }
+      .validate(),
+      Err(CodeParamsError::ColumnSizeNotPowerOfTwo)
+    );
+  }
+}
@@ -0,0 +1,226 @@
+//! Builds a [`LayeredCircuit`] from a computation described as a DAG.
+//!
+//! Inputs are the first gates of the input layer and outputs the first gates
+//! of the output layer, both in the order they were declared. A gate is placed
+//! one layer above its deepest operand, operands from lower layers are carried
+//! up by relay gates, and every layer is padded to a power of two. As the
+//! prover and verifier expect, the input layer is followed by a layer of
+//! direct relay gates, so the circuit has at least three layers.
+
+use std::collections::HashMap;
+
+use prime_field::FieldElement;
+
+use crate::{
+  circuit_fast_track::{Gate, Layer, LayeredCircuit},
+  gate::{BinaryRule, GateRegistry, GateType},
+};
+
+/// Value computed by a [`CircuitBuilder`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Wire(usize);
+
+#[derive(Debug, Clone)]
+enum Node {
+  Input,
+  Binary { ty: GateType, u: Wire, v: Wire },
+  LinearComb(Vec<(Wire, FieldElement)>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CircuitBuilder {
+  nodes: Vec<Node>,
+  /// Layer of every node, the inputs are relayed to layer 1
+  depth: Vec<usize>,
+  inputs: Vec<Wire>,
+  outputs: Vec<Wire>,
+  registry: GateRegistry,
+}
+
+/// Places the nodes and their relays in the layers
+struct Layout<'a> {
+  builder: &'a CircuitBuilder,
+  layers: Vec<Vec<Gate>>,
+  position: HashMap<(Wire, usize), usize>,
+}
+
+impl CircuitBuilder {
+  pub fn new() -> Self { Self::default() }
+
+  /// Builder of a circuit which can use the gate kinds of `registry`
+  pub fn with_registry(registry: GateRegistry) -> Self {
+    Self {
+      registry,
+      ..Default::default()
+    }
+  }
+
+  pub fn register(&mut self, name: impl Into<String>, rule: BinaryRule) -> GateType {
+    self.registry.register(name, rule)
+  }
+
+  fn push(&mut self, node: Node, depth: usize) -> Wire {
+    self.nodes.push(node);
+    self.depth.push(depth);
+    Wire(self.nodes.len() - 1)
+  }
+
+  fn depth(&self, wire: Wire) -> usize {
+    assert!(wire.0 < self.nodes.len(), "wire of another circuit");
+    self.depth[wire.0]
+  }
+
+  pub fn input(&mut self) -> Wire {
+    let wire = self.push(Node::Input, 1);
+    self.inputs.push(wire);
+    wire
+  }
+
+  pub fn inputs(&mut self, n: usize) -> Vec<Wire> { (0..n).map(|_| self.input()).collect() }
+
+  /// Gate of type `ty` on `u` and `v`, any gate with a [`BinaryRule`]. Gates
+  /// whose rule only reads `u` ignore `v`.
+  pub fn gate(&mut self, ty: GateType, u: Wire, v: Wire) -> Wire {
+    let rule = self
+      .registry
+      .rule(ty)
+      .unwrap_or_else(|| panic!("{:?} gates have no rule, use linear_combination", ty));
+    let v = if rule.is_unary() { u } else { v };
+    let depth = self.depth(u).max(self.depth(v)) + 1;
+    self.push(Node::Binary { ty, u, v }, depth)
+  }
+
+  pub fn add(&mut self, a: Wire, b: Wire) -> Wire { self.gate(GateType::Add, a, b) }
+
+  pub fn sub(&mut self, a: Wire, b: Wire) -> Wire { self.gate(GateType::Minus, a, b) }
+
+  pub fn mul(&mut self, a: Wire, b: Wire) -> Wire { self.gate(GateType::Mult, a, b) }
+
+  /// `weight_0 * wire_0 + weight_1 * wire_1 + ...` in a single gate
+  pub fn linear_combination(&mut self, terms: &[(Wire, FieldElement)]) -> Wire {
+    assert!(!terms.is_empty(), "empty linear combination");
+    let depth = terms
+      .iter()
+      .map(|(wire, _)| self.depth(*wire))
+      .max()
+      .unwrap()
+      + 1;
+    self.push(Node::LinearComb(terms.to_vec()), depth)
+  }
+
+  pub fn output(&mut self, wire: Wire) {
+    self.depth(wire);
+    self.outputs.push(wire);
+  }
+
+  pub fn build(self) -> LayeredCircuit {
+    assert!(!self.outputs.is_empty(), "circuit without outputs");
+    let output_depth = self
+      .outputs
+      .iter()
+      .map(|wire| self.depth(*wire))
+      .max()
+      .unwrap()
+      .max(2);
+
+    // only the nodes the outputs depend on are laid out
+    let mut live = vec![false; self.nodes.len()];
+    let mut stack = self.outputs.clone();
+    while let Some(wire) = stack.pop() {
+      if !std::mem::replace(&mut live[wire.0], true) {
+        match &self.nodes[wire.0] {
+          Node::Input => {}
+          Node::Binary { u, v, .. } => stack.extend([*u, *v]),
+          Node::LinearComb(terms) => stack.extend(terms.iter().map(|(wire, _)| *wire)),
+        }
+      }
+    }
+
+    let mut layout = Layout {
+      builder: &self,
+      layers: vec![vec![]; output_depth + 1],
+      position: HashMap::new(),
+    };
+    for (i, wire) in self.inputs.iter().enumerate() {
+      layout.layers[0].push(Gate::from_params(GateType::Input, 0, 0));
+      layout.position.insert((*wire, 1), i);
+    }
+    // the outputs of the last layer are laid out below
+    for wire in (0..self.nodes.len()).map(Wire) {
+      let depth = self.depth[wire.0];
+      if live[wire.0] && depth > 1 && depth < output_depth {
+        let gate = layout.gate(wire);
+        layout.layers[depth].push(gate);
+        layout
+          .position
+          .insert((wire, depth), layout.layers[depth].len() - 1);
+      }
+    }
+    for wire in &self.outputs {
+      let gate = if self.depth(*wire) == output_depth {
+        layout.gate(*wire)
+      } else {
+        Gate::from_params(GateType::Relay, layout.fetch(*wire, output_depth - 1), 0)
+      };
+      layout.layers[output_depth].push(gate);
+    }
+
+    let Layout { mut layers, .. } = layout;
+    let input_layer = Layer::padded(std::mem::take(&mut layers[0]));
+    layers[1] = (0..input_layer.gates.len())
+      .map(|g| Gate::from_params(GateType::DirectRelay, g, 0))
+      .collect();
+    let circuit: Vec<Layer> = std::iter::once(input_layer)
+      .chain(layers.into_iter().skip(1).map(Layer::padded))
+      .collect();
+
+    LayeredCircuit {
+      total_depth: circuit.len(),
+      circuit,
+      inputs: vec![],
+      registry: self.registry,
+    }
+  }
+}
+
+impl<'a> Layout<'a> {
+  /// Position of `wire` in `layer`, relaying it up from its own layer
+  fn fetch(&mut self, wire: Wire, layer: usize) -> usize {
+    if let Some(position) = self.position.get(&(wire, layer)) {
+      return *position;
+    }
+    assert!(layer > self.builder.depth[wire.0]);
+    let below = self.fetch(wire, layer - 1);
+    self.layers[layer].push(Gate::from_params(GateType::Relay, below, 0));
+    let position = self.layers[layer].len() - 1;
+    self.position.insert((wire, layer), position);
+    position
+  }
+
+  /// Gate computing the node of `wire` from the layer below its own
+  fn gate(&mut self, wire: Wire) -> Gate {
+    let layer = self.builder.depth[wire.0] - 1;
+    match &self.builder.nodes[wire.0] {
+      Node::Input => unreachable!("inputs are relayed"),
+      Node::Binary { ty, u, v } => {
+        let u = self.fetch(*u, layer);
+        let v = self.fetch(*v, layer);
+        let unary = self.builder.registry.rule(*ty).unwrap().is_unary();
+        Gate::from_params(*ty, u, if unary { 0 } else { v })
+      }
+      Node::LinearComb(terms) => {
+        let (src, weight): (Vec<usize>, Vec<FieldElement>) = terms
+          .iter()
+          .map(|(wire, weight)| (self.fetch(*wire, layer), *weight))
+          .unzip();
+        Gate {
+          ty: GateType::CustomLinearComb,
+          parameter_length: src.len(),
+          src,
+          weight,
+          ..Default::default()
+        }
+      }
+    }
+  }
+}
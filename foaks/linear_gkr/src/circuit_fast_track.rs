@@ -1,11 +1,14 @@
 //use std::sync::{Arc, Mutex};
 //use std::thread;
 
+use global::constants::FE_ZERO;
 use prime_field::FieldElement;
 
-#[derive(Debug, Clone, Default)]
+use crate::gate::{GateRegistry, GateType};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Gate {
-  pub ty: usize,
+  pub ty: GateType,
   pub u: usize,
   pub v: usize,
   pub src: Vec<usize>,
@@ -14,14 +17,9 @@ pub struct Gate {
 }
 
 impl Gate {
-  pub fn new() -> Self {
-    Self {
-      ty: 2,
-      ..Default::default()
-    }
-  }
+  pub fn new() -> Self { Self::default() }
 
-  pub fn from_params(ty: usize, u: usize, v: usize) -> Self {
+  pub fn from_params(ty: GateType, u: usize, v: usize) -> Self {
     Self {
       ty,
       u,
@@ -29,8 +27,41 @@ impl Gate {
       ..Default::default()
     }
   }
+
+  /// Same gate reading the gates `offset` further in the previous layer, the
+  /// `v = 0` of the gates reading a single input is kept
+  pub fn shifted(&self, offset: usize, registry: &GateRegistry) -> Self {
+    let mut gate = self.clone();
+    match gate.ty {
+      GateType::Dummy | GateType::Input => {}
+      GateType::CustomLinearComb => gate.src.iter_mut().for_each(|src| *src += offset),
+      ty => {
+        gate.u += offset;
+        if !registry.rule(ty).map_or(false, |rule| rule.is_unary()) {
+          gate.v += offset;
+        }
+      }
+    }
+    gate
+  }
+
+  /// Whether the inputs of the gate are among the first `size` gates of the
+  /// previous layer
+  fn reads_within(&self, size: usize, registry: &GateRegistry) -> bool {
+    match self.ty {
+      GateType::Dummy | GateType::Input => true,
+      GateType::Sum => self.u < size && self.v <= size,
+      GateType::CustomLinearComb => self.src.iter().all(|src| *src < size),
+      GateType::ExpSum => self.u < size && self.v < size,
+      ty => self.u < size && (self.v < size || registry.rule(ty).map_or(false, |r| r.is_unary())),
+    }
+  }
 }
 
+/// A parallel layer is made of `repeat_num` blocks of `block_size` gates, each
+/// a copy of the first block reading the matching block of the previous layer.
+/// The verifier handles its wiring one block at a time, the prover only
+/// shares the rules of the first block.
 #[derive(Default, Debug, Clone)]
 pub struct Layer {
   pub src_expander_c_mempool: Vec<usize>,
@@ -46,11 +77,171 @@ pub struct Layer {
   pub log_repeat_num: usize,
 }
 
+impl Layer {
+  /// Layer of `gates` padded with dummy gates to a power of two, at least two
+  pub fn padded(mut gates: Vec<Gate>) -> Self {
+    let bit_length = gates.len().next_power_of_two().max(2).trailing_zeros() as usize;
+    gates.resize(1 << bit_length, Gate::new());
+    Self {
+      gates,
+      bit_length,
+      ..Default::default()
+    }
+  }
+
+  /// Parallel layer of `1 << log_repeat_num` copies of `block`, padded as in
+  /// [`Layer::padded`], reading blocks of `previous_block_size` gates
+  pub fn parallel(
+    block: Vec<Gate>,
+    log_repeat_num: usize,
+    previous_block_size: usize,
+    registry: &GateRegistry,
+  ) -> Self {
+    let block = Self::padded(block);
+    let repeat_num = 1 << log_repeat_num;
+    Self {
+      gates: (0..repeat_num)
+        .flat_map(|b| {
+          let offset = b * previous_block_size;
+          block
+            .gates
+            .iter()
+            .map(move |gate| gate.shifted(offset, registry))
+        })
+        .collect(),
+      bit_length: block.bit_length + log_repeat_num,
+      is_parallel: true,
+      block_size: block.gates.len(),
+      log_block_size: block.bit_length,
+      repeat_num,
+      log_repeat_num,
+      ..Default::default()
+    }
+  }
+
+  /// Size of the blocks read in a previous layer of `previous_size` gates
+  pub fn previous_block_size(&self, previous_size: usize) -> usize {
+    previous_size >> self.log_repeat_num
+  }
+
+  /// Whether the gates of a parallel layer match its block sizes and repeat
+  /// its first block, always true for the other layers
+  pub fn repeats_block(&self, previous_size: usize, registry: &GateRegistry) -> bool {
+    if !self.is_parallel {
+      return true;
+    }
+    let sizes = self.log_block_size < usize::BITS as usize
+      && self.log_repeat_num < usize::BITS as usize
+      && self.block_size == 1 << self.log_block_size
+      && self.repeat_num == 1 << self.log_repeat_num
+      && self.log_block_size + self.log_repeat_num == self.bit_length
+      && self.gates.len() == 1 << self.bit_length
+      && previous_size.is_power_of_two()
+      && previous_size >= self.repeat_num;
+    if !sizes {
+      return false;
+    }
+
+    let previous_block_size = self.previous_block_size(previous_size);
+    let first_block = &self.gates[..self.block_size];
+    first_block
+      .iter()
+      .all(|gate| gate.reads_within(previous_block_size, registry))
+      && self
+        .gates
+        .chunks(self.block_size)
+        .enumerate()
+        .all(|(b, block)| {
+          block
+            .iter()
+            .zip(first_block)
+            .all(|(gate, first)| *gate == first.shifted(b * previous_block_size, registry))
+        })
+  }
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct LayeredCircuit {
   pub circuit: Vec<Layer>,
   pub total_depth: usize,
   pub inputs: Vec<FieldElement>,
+  pub registry: GateRegistry,
+}
+
+impl LayeredCircuit {
+  /// Size of the prover and verifier arrays, see [`crate::prover::ZkProver::init_array`]
+  pub fn max_bit_length(&self) -> usize {
+    self
+      .circuit
+      .iter()
+      .map(|layer| layer.bit_length)
+      .max()
+      .unwrap_or(0)
+  }
+
+  /// Data-parallel circuit evaluating `1 << log_repeat_num` copies of this one
+  /// on consecutive blocks of inputs, the outputs are laid out the same way
+  pub fn repeated(&self, log_repeat_num: usize) -> Self {
+    let repeat_num = 1 << log_repeat_num;
+    let input = &self.circuit[0];
+    let mut circuit = vec![Layer {
+      gates: (0..repeat_num).flat_map(|_| input.gates.clone()).collect(),
+      bit_length: input.bit_length + log_repeat_num,
+      ..Default::default()
+    }];
+    for (layer, previous) in self.circuit[1..].iter().zip(&self.circuit) {
+      circuit.push(Layer::parallel(
+        layer.gates.clone(),
+        log_repeat_num,
+        previous.gates.len(),
+        &self.registry,
+      ));
+    }
+
+    Self {
+      total_depth: circuit.len(),
+      circuit,
+      inputs: self.inputs.repeat(repeat_num),
+      registry: self.registry.clone(),
+    }
+  }
+
+  /// Circuit with the input layer doubled, the top half holding random values
+  /// hiding the evaluations of the input in masked proofs, see [`crate::mask`]
+  pub fn with_input_mask(&self) -> Self {
+    assert!(
+      self.circuit.len() >= 3,
+      "the input layer needs a relay layer"
+    );
+    let mut masked = self.clone();
+    let input = &mut masked.circuit[0];
+    let size = input.gates.len();
+    input
+      .gates
+      .extend((0..size).map(|_| Gate::from_params(GateType::Input, 0, 0)));
+    input.bit_length += 1;
+    let bit_length = input.bit_length;
+    masked.circuit[1] = Layer {
+      gates: (0..2 * size)
+        .map(|g| Gate::from_params(GateType::DirectRelay, g, 0))
+        .collect(),
+      bit_length,
+      ..Default::default()
+    };
+    // blocks of the second layer are no longer a fraction of the relay layer
+    masked.circuit[2].is_parallel = false;
+    if !masked.inputs.is_empty() {
+      masked.inputs.resize(2 * size, FE_ZERO);
+    }
+    masked
+  }
+
+  /// First layer whose parallel layout is inconsistent, see
+  /// [`Layer::repeats_block`]
+  pub fn invalid_parallel_layer(&self) -> Option<usize> {
+    (1..self.circuit.len())
+      .find(|&i| !self.circuit[i].repeats_block(self.circuit[i - 1].gates.len(), &self.registry))
+  }
 }
 //advice to implement parallelism
 
@@ -0,0 +1,360 @@
+//! Reader and writer of the layered circuit text format of the Virgo and
+//! Orion reference implementations.
+//!
+//! A circuit file is whitespace separated integers: the number of layers `d`,
+//! then every layer from the input layer (layer `0`) to the output layer
+//! (layer `d - 1`) as its number of gates `n` followed by its `n` gates
+//! `ty g u v`. The gates of a layer are listed in order, `g` running from `0`
+//! to `n - 1`, and `ty` is [`GateType::code`]. The input layer only has input
+//! gates, whose value is `u`, and dummy gates.
+//!
+//! The meta file has a line `is_parallel block_size repeat_num log_block_size
+//! log_repeat_num` per layer, in the same order. The gates of a parallel layer
+//! must repeat its first block, see [`Layer`].
+//!
+//! As in the reference, the loaded circuit has a layer of direct relay gates
+//! on top of the input layer, every layer is padded with dummy gates to a
+//! power of two of at least two gates, and `v` is reset to `0` for gates that
+//! only read `u`.
+
+use std::{fmt::Write, fs, path::Path};
+
+use prime_field::FieldElement;
+
+use crate::{
+  circuit_fast_track::{Gate, Layer, LayeredCircuit},
+  gate::{GateRegistry, GateType},
+};
+
+/// `layer` is the index of the layer in the file, the input layer is `0`
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CircuitFileError {
+  Io(std::io::Error),
+  UnexpectedEnd,
+  InvalidNumber(String),
+  TrailingData,
+  EmptyLayer {
+    layer: usize,
+  },
+  GateOutOfOrder {
+    layer: usize,
+    expected: usize,
+    found: u64,
+  },
+  UnknownGateType {
+    layer: usize,
+    gate: usize,
+    ty: u64,
+  },
+  /// Input and direct relay gates outside of the input layer, or any other
+  /// gate in it
+  MisplacedGate {
+    layer: usize,
+    gate: usize,
+    ty: GateType,
+  },
+  /// A wire past the gates of the previous layer, `bound` is their number
+  WireOutOfRange {
+    layer: usize,
+    gate: usize,
+    wire: u64,
+    bound: usize,
+  },
+  /// A bit-test gate with `u != v`
+  BitTestWires {
+    layer: usize,
+    gate: usize,
+  },
+  /// Block sizes not matching the layer, or a parallel layer not repeating
+  /// its first block
+  InvalidMeta {
+    layer: usize,
+  },
+  /// Gates the text format has no encoding for, such as custom linear
+  /// combinations
+  UnsupportedGate {
+    layer: usize,
+    gate: usize,
+    ty: GateType,
+  },
+  /// The first layer above the input layer is not made of its direct relays
+  UnsupportedLayout,
+}
+
+impl From<std::io::Error> for CircuitFileError {
+  fn from(err: std::io::Error) -> Self { Self::Io(err) }
+}
+
+struct Tokens<'a>(std::str::SplitAsciiWhitespace<'a>);
+
+impl<'a> Tokens<'a> {
+  fn new(text: &'a str) -> Self { Self(text.split_ascii_whitespace()) }
+
+  fn next(&mut self) -> Result<u64, CircuitFileError> {
+    let token = self.0.next().ok_or(CircuitFileError::UnexpectedEnd)?;
+    token
+      .parse()
+      .map_err(|_| CircuitFileError::InvalidNumber(token.to_string()))
+  }
+
+  fn next_usize(&mut self) -> Result<usize, CircuitFileError> {
+    let token = self.next()?;
+    usize::try_from(token).map_err(|_| CircuitFileError::InvalidNumber(token.to_string()))
+  }
+
+  fn finish(mut self) -> Result<(), CircuitFileError> {
+    match self.0.next() {
+      Some(_) => Err(CircuitFileError::TrailingData),
+      None => Ok(()),
+    }
+  }
+}
+
+/// Reads the `(ty, u, v)` of the gates of a layer
+fn read_gates(tokens: &mut Tokens, layer: usize) -> Result<Vec<(u64, u64, u64)>, CircuitFileError> {
+  let n = tokens.next_usize()?;
+  if n == 0 {
+    return Err(CircuitFileError::EmptyLayer { layer });
+  }
+  (0..n)
+    .map(|expected| {
+      let (ty, g, u, v) = (
+        tokens.next()?,
+        tokens.next()?,
+        tokens.next()?,
+        tokens.next()?,
+      );
+      if g != expected as u64 {
+        return Err(CircuitFileError::GateOutOfOrder {
+          layer,
+          expected,
+          found: g,
+        });
+      }
+      Ok((ty, u, v))
+    })
+    .collect()
+}
+
+fn read_input_layer(gates: Vec<(u64, u64, u64)>) -> Result<[Layer; 2], CircuitFileError> {
+  let gates = gates
+    .into_iter()
+    .enumerate()
+    .map(|(g, (ty, u, v))| match GateType::from_code(ty as usize) {
+      Some(ty @ (GateType::Input | GateType::Dummy)) => {
+        Ok(Gate::from_params(ty, u as usize, v as usize))
+      }
+      Some(ty) => Err(CircuitFileError::MisplacedGate {
+        layer: 0,
+        gate: g,
+        ty,
+      }),
+      None => Err(CircuitFileError::UnknownGateType {
+        layer: 0,
+        gate: g,
+        ty,
+      }),
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let input = Layer::padded(gates);
+  let relay = Layer {
+    gates: (0..input.gates.len())
+      .map(|g| Gate::from_params(GateType::DirectRelay, g, 0))
+      .collect(),
+    bit_length: input.bit_length,
+    ..Default::default()
+  };
+  Ok([input, relay])
+}
+
+fn read_layer(
+  gates: Vec<(u64, u64, u64)>,
+  layer: usize,
+  previous_size: usize,
+  registry: &GateRegistry,
+) -> Result<Layer, CircuitFileError> {
+  let gates = gates
+    .into_iter()
+    .enumerate()
+    .map(|(g, (code, u, v))| {
+      let ty = GateType::from_code(code as usize)
+        .filter(|ty| !matches!(ty, GateType::Registered(_)) || registry.kind(*ty).is_some())
+        .ok_or(CircuitFileError::UnknownGateType {
+          layer,
+          gate: g,
+          ty: code,
+        })?;
+      let out_of_range = |wire: u64| CircuitFileError::WireOutOfRange {
+        layer,
+        gate: g,
+        wire,
+        bound: previous_size,
+      };
+      let check = |wire: u64, bound: usize| {
+        if wire < bound as u64 {
+          Ok(wire as usize)
+        } else {
+          Err(out_of_range(wire))
+        }
+      };
+
+      match ty {
+        GateType::Input | GateType::DirectRelay => {
+          Err(CircuitFileError::MisplacedGate { layer, gate: g, ty })
+        }
+        GateType::CustomLinearComb => Err(CircuitFileError::UnsupportedGate { layer, gate: g, ty }),
+        // sums of the inputs u to v - 1
+        GateType::Sum if v <= u => Err(out_of_range(v)),
+        GateType::Sum => Ok(Gate::from_params(
+          ty,
+          check(u, previous_size)?,
+          check(v, previous_size + 1)?,
+        )),
+        // weighted sums of the inputs u to v
+        GateType::ExpSum if v < u => Err(out_of_range(v)),
+        GateType::BitTest if u != v => Err(CircuitFileError::BitTestWires { layer, gate: g }),
+        _ => {
+          let (u, v) = (check(u, previous_size)?, check(v, previous_size)?);
+          match registry.rule(ty) {
+            Some(rule) if rule.is_unary() => Ok(Gate::from_params(ty, u, 0)),
+            _ => Ok(Gate::from_params(ty, u, v)),
+          }
+        }
+      }
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+  Ok(Layer::padded(gates))
+}
+
+fn read_meta(meta: &str, a_c: &mut LayeredCircuit) -> Result<(), CircuitFileError> {
+  let mut tokens = Tokens::new(meta);
+  for layer in 0..a_c.circuit.len() - 1 {
+    let previous_size = a_c.circuit[layer].gates.len();
+    let l = &mut a_c.circuit[layer + 1];
+    l.is_parallel = tokens.next()? != 0;
+    l.block_size = tokens.next_usize()?;
+    l.repeat_num = tokens.next_usize()?;
+    l.log_block_size = tokens.next_usize()?;
+    l.log_repeat_num = tokens.next_usize()?;
+
+    if !l.repeats_block(previous_size, &a_c.registry) {
+      return Err(CircuitFileError::InvalidMeta { layer });
+    }
+  }
+  tokens.finish()
+}
+
+/// Parses a circuit and its optional meta file, gate codes past the built-in
+/// ones refer to the kinds of `registry`
+pub fn read_circuit(
+  circuit: &str,
+  meta: Option<&str>,
+  registry: GateRegistry,
+) -> Result<LayeredCircuit, CircuitFileError> {
+  let mut tokens = Tokens::new(circuit);
+  let depth = tokens.next_usize()?;
+  if depth == 0 {
+    return Err(CircuitFileError::EmptyLayer { layer: 0 });
+  }
+
+  let mut layers = Vec::from(read_input_layer(read_gates(&mut tokens, 0)?)?);
+  for layer in 1..depth {
+    let gates = read_gates(&mut tokens, layer)?;
+    let previous_size = layers[layer].gates.len();
+    layers.push(read_layer(gates, layer, previous_size, &registry)?);
+  }
+  tokens.finish()?;
+
+  let inputs = layers[0]
+    .gates
+    .iter()
+    .map(|gate| match gate.ty {
+      GateType::Input => FieldElement::from_real(gate.u as u64),
+      _ => FieldElement::zero(),
+    })
+    .collect();
+  let mut a_c = LayeredCircuit {
+    total_depth: layers.len(),
+    circuit: layers,
+    inputs,
+    registry,
+  };
+  if let Some(meta) = meta {
+    read_meta(meta, &mut a_c)?;
+  }
+  Ok(a_c)
+}
+
+/// Same as [`read_circuit`] on the contents of the files
+pub fn load_circuit(
+  circuit_path: &Path,
+  meta_path: Option<&Path>,
+  registry: GateRegistry,
+) -> Result<LayeredCircuit, CircuitFileError> {
+  let circuit = fs::read_to_string(circuit_path)?;
+  let meta = meta_path.map(fs::read_to_string).transpose()?;
+  read_circuit(&circuit, meta.as_deref(), registry)
+}
+
+/// Writes `a_c` in the circuit format, the input layer must be followed by a
+/// layer relaying it as the loaded circuits are
+pub fn write_circuit(a_c: &LayeredCircuit) -> Result<String, CircuitFileError> {
+  let relays_input = a_c.circuit.len() >= 2
+    && a_c.circuit[1].gates.len() == a_c.circuit[0].gates.len()
+    && a_c.circuit[1]
+      .gates
+      .iter()
+      .enumerate()
+      .all(|(g, gate)| gate.ty == GateType::DirectRelay && gate.u == g);
+  if !relays_input {
+    return Err(CircuitFileError::UnsupportedLayout);
+  }
+
+  let layers = std::iter::once(&a_c.circuit[0]).chain(&a_c.circuit[2..]);
+  let mut out = format!("{}\n", a_c.circuit.len() - 1);
+  for (layer, l) in layers.enumerate() {
+    write!(out, "{}", l.gates.len()).unwrap();
+    for (g, gate) in l.gates.iter().enumerate() {
+      let allowed = match layer {
+        0 => matches!(gate.ty, GateType::Input | GateType::Dummy),
+        _ => !matches!(gate.ty, GateType::Input | GateType::DirectRelay),
+      };
+      if !allowed {
+        return Err(CircuitFileError::MisplacedGate {
+          layer,
+          gate: g,
+          ty: gate.ty,
+        });
+      }
+      if gate.ty == GateType::CustomLinearComb {
+        return Err(CircuitFileError::UnsupportedGate {
+          layer,
+          gate: g,
+          ty: gate.ty,
+        });
+      }
+      write!(out, " {} {} {} {}", gate.ty.code(), g, gate.u, gate.v).unwrap();
+    }
+    out.push('\n');
+  }
+  Ok(out)
+}
+
+/// Writes the meta file of `a_c`, see [`write_circuit`]
+pub fn write_meta(a_c: &LayeredCircuit) -> String {
+  a_c
+    .circuit
+    .iter()
+    .skip(1)
+    .fold(String::new(), |mut out, l| {
+      writeln!(
+        out,
+        "{} {} {} {} {}",
+        l.is_parallel as u8, l.block_size, l.repeat_num, l.log_block_size, l.log_repeat_num
+      )
+      .unwrap();
+      out
+    })
+}
@@ -0,0 +1,115 @@
+use std::mem;
+
+use global::constants::{FE_REAL_ONE, FE_ZERO};
+use infrastructure::{my_hash::MerkleHasher, transcript::Transcript, utility::my_log};
+use prime_field::FieldElement;
+
+use crate::{
+  circuit_fast_track::{Gate, Layer, LayeredCircuit},
+  gate::GateType,
+  proof::FftProof,
+  prover::ZkProver,
+  verifier::ZkVerifier,
+};
+
+/// Circuit mapping the evaluations of a polynomial over the subgroup of order
+/// `1 << log_order` to its coefficients. Every butterfly stage is one layer of
+/// linear combination gates, the bit reversal is folded into the first stage.
+pub fn inverse_fft_circuit(log_order: usize) -> LayeredCircuit {
+  let order = 1 << log_order;
+  let inv_rou = FieldElement::get_root_of_unity(log_order)
+    .expect("Failed to retrieve root of unity")
+    .inverse();
+  let inv_order = FieldElement::from_real(order as u64).inverse();
+
+  let layer = |gates: Vec<Gate>| Layer {
+    bit_length: log_order,
+    gates,
+    ..Default::default()
+  };
+
+  let mut circuit = Vec::with_capacity(log_order + 2);
+  circuit.push(layer(vec![Gate::from_params(GateType::Input, 0, 0); order]));
+  circuit.push(layer(
+    (0..order)
+      .map(|i| Gate::from_params(GateType::DirectRelay, i, 0))
+      .collect(),
+  ));
+
+  let bit_reverse = |i: usize| i.reverse_bits() >> (usize::BITS as usize - log_order);
+  for stage in 1..=log_order {
+    let half = 1 << (stage - 1);
+    let rou = inv_rou.fast_pow((order >> stage) as u128);
+    let scale = if stage == log_order {
+      inv_order
+    } else {
+      FE_REAL_ONE
+    };
+    let src = |i: usize| if stage == 1 { bit_reverse(i) } else { i };
+
+    let gates = (0..order)
+      .map(|g| {
+        let block = g & !((half << 1) - 1);
+        let j = g & (half - 1);
+        let twiddle = rou.fast_pow(j as u128);
+        let twiddle = if g & half == 0 {
+          twiddle
+        } else {
+          FE_ZERO - twiddle
+        };
+        Gate {
+          ty: GateType::CustomLinearComb,
+          src: vec![src(block + j), src(block + j + half)],
+          weight: vec![scale, scale * twiddle],
+          parameter_length: 2,
+          ..Default::default()
+        }
+      })
+      .collect();
+    circuit.push(layer(gates));
+  }
+
+  LayeredCircuit {
+    total_depth: circuit.len(),
+    circuit,
+    ..Default::default()
+  }
+}
+
+/// Computes the coefficients of `evals` and proves them with the inverse FFT
+/// circuit
+pub fn prove_inverse_fft<H: MerkleHasher>(
+  evals: &[FieldElement],
+  transcript: &mut Transcript<H>,
+) -> FftProof {
+  let log_order = my_log(evals.len()).expect("Failed to compute logarithm");
+  let mut prover = ZkProver::new();
+  prover.init_array(log_order, inverse_fft_circuit(log_order));
+  prover.get_witness(evals.to_vec());
+
+  let layers = prover.prove_public_input(transcript);
+  FftProof {
+    coef: prover.circuit_value[prover.a_c.total_depth - 1].clone(),
+    layers,
+  }
+}
+
+/// Checks that `proof.coef` are the coefficients of the public `evals`,
+/// returns the result and the proof size in bytes
+pub fn verify_inverse_fft<H: MerkleHasher>(
+  evals: &[FieldElement],
+  proof: &FftProof,
+  transcript: &mut Transcript<H>,
+) -> (bool, usize) {
+  let log_order = match my_log(evals.len()) {
+    Ok(log_order) if proof.coef.len() == evals.len() => log_order,
+    _ => return (false, 0),
+  };
+  let mut verifier = ZkVerifier::new();
+  verifier.a_c = inverse_fft_circuit(log_order);
+  verifier.init_array(log_order);
+
+  let result = verifier.verify_public_input(evals, &proof.coef, &proof.layers, transcript);
+  let proof_size = verifier.proof_size + proof.coef.len() * mem::size_of::<FieldElement>();
+  (result, proof_size)
+}
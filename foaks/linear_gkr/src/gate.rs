@@ -0,0 +1,233 @@
+use global::constants::{FE_REAL_ONE, FE_ZERO};
+use prime_field::{constants::MOD, FieldElement};
+
+const FE_MINUS_ONE: FieldElement = FieldElement::new(MOD - 1, 0);
+const FE_MINUS_TWO: FieldElement = FieldElement::new(MOD - 2, 0);
+
+/// Code of the first registered gate kind, the built-in codes are those of
+/// the Virgo and Orion circuit descriptions
+pub const FIRST_REGISTERED_CODE: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum GateType {
+  /// `V(u) + V(v)`
+  Add,
+  /// `V(u) * V(v)`
+  Mult,
+  /// Padding, always zero
+  #[default]
+  Dummy,
+  /// Input layer gate, `u` is its value when the circuit is evaluated
+  /// without a witness
+  Input,
+  /// Copies gate `g` of the input layer, only in the first layer
+  DirectRelay,
+  /// `V(u) + ... + V(v - 1)`
+  Sum,
+  /// `1 - V(u)`
+  Not,
+  /// `V(u) - V(v)`
+  Minus,
+  /// `V(u) + V(v) - 2 * V(u) * V(v)`
+  Xor,
+  /// `(1 - V(u)) * V(v)`
+  Naab,
+  /// `V(u)`
+  Relay,
+  /// `V(u) + 2 * V(u + 1) + ... + 2^(v - u) * V(v)`
+  ExpSum,
+  /// `V(u) * (1 - V(v))`, with `u == v`
+  BitTest,
+  /// `weight[0] * V(src[0]) + ...`
+  CustomLinearComb,
+  /// Gate kind added to the [`GateRegistry`] of the circuit
+  Registered(usize),
+}
+
+/// Rule `constant + u * V(u) + v * V(v) + uv * V(u) * V(v)` of a two input
+/// gate. Every gate the layer sumcheck can prove has such a rule, the value of
+/// the gate and its contribution to the layer claim both follow from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinaryRule {
+  pub constant: FieldElement,
+  pub u: FieldElement,
+  pub v: FieldElement,
+  pub uv: FieldElement,
+}
+
+#[derive(Debug, Clone)]
+pub struct GateKind {
+  pub name: String,
+  pub rule: BinaryRule,
+}
+
+/// Gate kinds of a circuit beyond the built-in ones
+#[derive(Debug, Clone, Default)]
+pub struct GateRegistry {
+  kinds: Vec<GateKind>,
+}
+
+impl GateType {
+  pub const BUILT_IN: [GateType; 14] = [
+    GateType::Add,
+    GateType::Mult,
+    GateType::Dummy,
+    GateType::Input,
+    GateType::DirectRelay,
+    GateType::Sum,
+    GateType::Not,
+    GateType::Minus,
+    GateType::Xor,
+    GateType::Naab,
+    GateType::Relay,
+    GateType::ExpSum,
+    GateType::BitTest,
+    GateType::CustomLinearComb,
+  ];
+
+  pub fn code(self) -> usize {
+    match self {
+      GateType::Add => 0,
+      GateType::Mult => 1,
+      GateType::Dummy => 2,
+      GateType::Input => 3,
+      GateType::DirectRelay => 4,
+      GateType::Sum => 5,
+      GateType::Not => 6,
+      GateType::Minus => 7,
+      GateType::Xor => 8,
+      GateType::Naab => 9,
+      GateType::Relay => 10,
+      GateType::ExpSum => 12,
+      GateType::BitTest => 13,
+      GateType::CustomLinearComb => 14,
+      GateType::Registered(id) => FIRST_REGISTERED_CODE + id,
+    }
+  }
+
+  pub fn from_code(code: usize) -> Option<Self> {
+    if code >= FIRST_REGISTERED_CODE {
+      return Some(GateType::Registered(code - FIRST_REGISTERED_CODE));
+    }
+    Self::BUILT_IN.into_iter().find(|ty| ty.code() == code)
+  }
+
+  /// Rule of the built-in two input gates
+  pub fn built_in_rule(self) -> Option<BinaryRule> {
+    let rule = |constant, u, v, uv| Some(BinaryRule { constant, u, v, uv });
+    let (zero, one) = (FE_ZERO, FE_REAL_ONE);
+    match self {
+      GateType::Add => rule(zero, one, one, zero),
+      GateType::Mult => rule(zero, zero, zero, one),
+      GateType::Not => rule(one, FE_MINUS_ONE, zero, zero),
+      GateType::Minus => rule(zero, one, FE_MINUS_ONE, zero),
+      GateType::Xor => rule(zero, one, one, FE_MINUS_TWO),
+      GateType::Naab => rule(zero, zero, one, FE_MINUS_ONE),
+      GateType::Relay => rule(zero, one, zero, zero),
+      GateType::BitTest => rule(zero, one, zero, FE_MINUS_ONE),
+      _ => None,
+    }
+  }
+}
+
+impl BinaryRule {
+  pub fn eval(&self, value_u: FieldElement, value_v: FieldElement) -> FieldElement {
+    self.constant + self.u * value_u + self.v * value_v + self.uv * value_u * value_v
+  }
+
+  /// Gates whose rule ignores `V(v)` are wired with `v = 0`
+  pub fn is_unary(&self) -> bool { self.v == FE_ZERO && self.uv == FE_ZERO }
+}
+
+impl GateRegistry {
+  pub fn new() -> Self { Self::default() }
+
+  pub fn register(&mut self, name: impl Into<String>, rule: BinaryRule) -> GateType {
+    self.kinds.push(GateKind {
+      name: name.into(),
+      rule,
+    });
+    GateType::Registered(self.kinds.len() - 1)
+  }
+
+  pub fn kind(&self, ty: GateType) -> Option<&GateKind> {
+    match ty {
+      GateType::Registered(id) => self.kinds.get(id),
+      _ => None,
+    }
+  }
+
+  pub fn find(&self, name: &str) -> Option<GateType> {
+    self
+      .kinds
+      .iter()
+      .position(|kind| kind.name == name)
+      .map(GateType::Registered)
+  }
+
+  /// Rule of a two input gate, built-in or registered. `None` for the gates
+  /// with a dedicated sumcheck: dummy, input, direct relay and the linear
+  /// combinations of many inputs.
+  pub fn rule(&self, ty: GateType) -> Option<BinaryRule> {
+    match ty {
+      GateType::Registered(_) => self.kind(ty).map(|kind| kind.rule),
+      _ => ty.built_in_rule(),
+    }
+  }
+
+  /// One past the largest gate code, the size of a table indexed by
+  /// [`GateType::code`]
+  pub fn code_bound(&self) -> usize { FIRST_REGISTERED_CODE + self.kinds.len() }
+
+  /// Contribution of the gates of type `ty` to the claim of a layer, given the
+  /// sum `predicate` of their wiring predicates and the claimed values of the
+  /// previous layer
+  pub fn predicate_contribution(
+    &self,
+    ty: GateType,
+    predicate: FieldElement,
+    v_u: FieldElement,
+    v_v: FieldElement,
+  ) -> FieldElement {
+    match ty {
+      // the predicates of these gates fold the weights of the inputs
+      GateType::Sum | GateType::ExpSum | GateType::CustomLinearComb => predicate * v_u,
+      _ => match self.rule(ty) {
+        Some(rule) => predicate * rule.eval(v_u, v_v),
+        None => FE_ZERO,
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn codes_round_trip() {
+    for ty in GateType::BUILT_IN {
+      assert_eq!(GateType::from_code(ty.code()), Some(ty));
+    }
+    assert_eq!(GateType::from_code(11), None);
+    assert_eq!(GateType::from_code(17), Some(GateType::Registered(2)));
+  }
+
+  #[test]
+  fn built_in_rules() {
+    let (x, y) = (FieldElement::from_real(7), FieldElement::from_real(3));
+    let eval = |ty: GateType| ty.built_in_rule().unwrap().eval(x, y);
+    assert_eq!(eval(GateType::Add), x + y);
+    assert_eq!(eval(GateType::Mult), x * y);
+    assert_eq!(eval(GateType::Not), FE_REAL_ONE - x);
+    assert_eq!(eval(GateType::Minus), x - y);
+    assert_eq!(
+      eval(GateType::Xor),
+      x + y - FieldElement::from_real(2) * x * y
+    );
+    assert_eq!(eval(GateType::Naab), y - x * y);
+    assert_eq!(eval(GateType::Relay), x);
+    assert_eq!(eval(GateType::BitTest), x * (FE_REAL_ONE - y));
+    assert_eq!(GateType::Sum.built_in_rule(), None);
+  }
+}
@@ -1,4 +1,11 @@
+pub mod circuit_builder;
 pub mod circuit_fast_track;
+pub mod circuit_file;
+pub mod fft_gkr;
+pub mod gate;
+pub mod mask;
 pub mod polynomial;
+pub mod proof;
 pub mod prover;
+pub mod sumcheck;
 pub mod verifier;
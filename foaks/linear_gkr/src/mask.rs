@@ -0,0 +1,340 @@
+//! Masked sumchecks for the GKR protocol, following the masking of Libra.
+//!
+//! In masked mode the prover commits to a random [`SumcheckMask`] per layer before
+//! the layer sumchecks. For every layer it sends the sum of the mask, the
+//! verifier draws `rho` and the sumcheck runs on the layer polynomial plus
+//! `rho` times the mask. The round messages are then uniformly random apart
+//! from the sumcheck relation. The values of the masks at the sumcheck points
+//! are opened together at the end. The input layer is extended with as many
+//! random values, see [`LayeredCircuit::with_input_mask`], so the claims left
+//! on the first layers do not reveal the witness either.
+//!
+//! Masked proofs are not zero knowledge, and zero knowledge is not
+//! implemented. Libra and Virgo also need:
+//! - the low-degree masks `V_i(z) + Z(z) R_i(z_1, w)` of every layer, with `Z`
+//!   vanishing on the boolean hypercube, so the claims `v_u` and `v_v` above
+//!   the first layer are no longer sent in the clear. The last round of each
+//!   sumcheck phase becomes cubic and `R_i` is reduced in the sumcheck of the
+//!   next layer.
+//! - a hiding commitment of the input, with salted merkle leaves and a random
+//!   low-degree extension, so the openings reveal nothing.
+//! - a simulator of the whole transcript.
+//!
+//! Only the round messages of each sumcheck are simulatable, see the tests
+//! below.
+//!
+//! [`LayeredCircuit::with_input_mask`]: crate::circuit_fast_track::LayeredCircuit::with_input_mask
+
+use global::constants::{FE_REAL_ONE, FE_ZERO, LOG_SLICE_NUMBER, RS_CODE_RATE};
+use infrastructure::{
+  my_hash::{HashDigest, MerkleHasher},
+  rs_polynomial::{inverse_fast_fourier_transform, ScratchPad},
+  transcript::Transcript,
+  utility::my_log,
+};
+use poly_commitment::{PolyCommitProof, PolyCommitProver, PolyCommitVerifier};
+use prime_field::FieldElement;
+use rand::Rng;
+
+use crate::polynomial::QuadraticPoly;
+
+/// Random polynomial `constant + a_0 x_0 + b_0 x_0^2 + a_1 x_1 + ...` added to
+/// the polynomial of a sumcheck
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SumcheckMask {
+  pub constant: FieldElement,
+  /// `(a_i, b_i)` of every variable
+  pub terms: Vec<(FieldElement, FieldElement)>,
+}
+
+fn pow2(exp: usize) -> FieldElement { FieldElement::from_real(2).fast_pow(exp as u128) }
+
+impl SumcheckMask {
+  pub fn random<R: Rng + ?Sized>(num_vars: usize, rng: &mut R) -> Self {
+    let mut random = || FieldElement::new_random_from_rng(rng);
+    Self {
+      constant: random(),
+      terms: (0..num_vars).map(|_| (random(), random())).collect(),
+    }
+  }
+
+  pub fn num_vars(&self) -> usize { self.terms.len() }
+
+  fn term(&self, i: usize, x: FieldElement) -> FieldElement {
+    let (a, b) = self.terms[i];
+    a * x + b * x * x
+  }
+
+  /// Sum of the mask over the boolean hypercube
+  pub fn sum(&self) -> FieldElement {
+    let n = self.num_vars();
+    let terms = self.terms.iter().fold(FE_ZERO, |acc, (a, b)| acc + *a + *b);
+    match n {
+      0 => self.constant,
+      _ => pow2(n) * self.constant + pow2(n - 1) * terms,
+    }
+  }
+
+  /// Message of the round following the `challenges` of the previous rounds,
+  /// the sum over the remaining variables as a polynomial in the current one
+  pub fn round_poly(&self, challenges: &[FieldElement]) -> QuadraticPoly {
+    let (round, n) = (challenges.len(), self.num_vars());
+    let fixed = challenges
+      .iter()
+      .enumerate()
+      .fold(self.constant, |acc, (i, r)| acc + self.term(i, *r));
+    let remaining = self.terms[round + 1..]
+      .iter()
+      .fold(FE_ZERO, |acc, (a, b)| acc + *a + *b);
+    let (a, b) = self.terms[round];
+    // 2^(n - round - 1) (fixed + a x + b x^2) + 2^(n - round - 2) remaining
+    let scale = pow2(n - round - 1);
+    let remaining = match n - round - 1 {
+      0 => FE_ZERO,
+      k => pow2(k - 1) * remaining,
+    };
+    QuadraticPoly::new(scale * b, scale * a, scale * fixed + remaining)
+  }
+
+  pub fn eval(&self, point: &[FieldElement]) -> FieldElement {
+    assert_eq!(point.len(), self.num_vars());
+    (0..point.len()).fold(self.constant, |acc, i| acc + self.term(i, point[i]))
+  }
+
+  /// The constant then `a_i, b_i` of every variable
+  pub fn coefficients(&self) -> Vec<FieldElement> {
+    std::iter::once(self.constant)
+      .chain(self.terms.iter().flat_map(|(a, b)| [*a, *b]))
+      .collect()
+  }
+
+  /// Weights of the coefficients giving the value of a mask at `point`
+  pub fn eval_weights(point: &[FieldElement]) -> Vec<FieldElement> {
+    std::iter::once(FE_REAL_ONE)
+      .chain(point.iter().flat_map(|x| [*x, *x * *x]))
+      .collect()
+  }
+}
+
+/// Smallest commitment size holding `len` coefficients
+fn log_length(len: usize) -> usize {
+  my_log(len.next_power_of_two())
+    .unwrap()
+    .max(LOG_SLICE_NUMBER + 1)
+}
+
+/// Public array of the batched opening of the masks: the weights of every
+/// mask at its sumcheck point, scaled by a random `lambda` per mask
+fn batched_weights(
+  points: &[Vec<FieldElement>],
+  lambdas: &[FieldElement],
+  log_length: usize,
+) -> Vec<FieldElement> {
+  let mut weights = Vec::with_capacity(1 << log_length);
+  for (point, lambda) in points.iter().zip(lambdas) {
+    weights.extend(
+      SumcheckMask::eval_weights(point)
+        .into_iter()
+        .map(|w| w * *lambda),
+    );
+  }
+  weights.resize(1 << log_length, FE_ZERO);
+  weights
+}
+
+/// Masks of the layers of a masked proof and their commitment
+pub(crate) struct MaskCommitment {
+  pub masks: Vec<SumcheckMask>,
+  pub root: HashDigest,
+  log_length: usize,
+  poly_prover: PolyCommitProver,
+}
+
+impl MaskCommitment {
+  pub fn commit<H: MerkleHasher, R: Rng + ?Sized>(num_vars: &[usize], rng: &mut R) -> Self {
+    let masks: Vec<_> = num_vars
+      .iter()
+      .map(|n| SumcheckMask::random(*n, rng))
+      .collect();
+    let mut coefficients: Vec<_> = masks.iter().flat_map(|m| m.coefficients()).collect();
+    let log_length = log_length(coefficients.len());
+    coefficients.resize(1 << log_length, FE_ZERO);
+
+    let mut poly_prover = PolyCommitProver::default();
+    let root = poly_prover.commit_private_array::<H>(&coefficients, log_length);
+    Self {
+      masks,
+      root,
+      log_length,
+      poly_prover,
+    }
+  }
+
+  /// Opens the values of the masks at their sumcheck `points`
+  pub fn open<H: MerkleHasher>(
+    mut self,
+    points: &[Vec<FieldElement>],
+    transcript: &mut Transcript<H>,
+  ) -> PolyCommitProof {
+    let lambdas = transcript.squeeze_field_elements(self.masks.len());
+    let target = self
+      .masks
+      .iter()
+      .zip(points)
+      .zip(&lambdas)
+      .fold(FE_ZERO, |acc, ((mask, point), lambda)| {
+        acc + mask.eval(point) * *lambda
+      });
+    let weights = batched_weights(points, &lambdas, self.log_length);
+    let mut all_sum = vec![FE_ZERO; 1 << LOG_SLICE_NUMBER];
+    let root_h =
+      self
+        .poly_prover
+        .commit_public_array::<H>(&weights, self.log_length, target, &mut all_sum);
+    self
+      .poly_prover
+      .open(self.log_length, self.root, root_h, all_sum, transcript)
+  }
+}
+
+/// Checks the opening of the masks of `num_vars` variables to `evals` at
+/// `points`
+pub(crate) fn verify_masks<H: MerkleHasher>(
+  proof: &PolyCommitProof,
+  points: &[Vec<FieldElement>],
+  evals: &[FieldElement],
+  transcript: &mut Transcript<H>,
+) -> bool {
+  let len = points.iter().map(|point| 1 + 2 * point.len()).sum();
+  let log_length = log_length(len);
+  let lambdas = transcript.squeeze_field_elements(points.len());
+  let target = evals
+    .iter()
+    .zip(&lambdas)
+    .fold(FE_ZERO, |acc, (eval, lambda)| acc + *eval * *lambda);
+  if proof.all_sum.iter().fold(FE_ZERO, |acc, x| acc + *x) != target {
+    return false;
+  }
+
+  // the commitment reads the public array as a polynomial per slice
+  let weights = batched_weights(points, &lambdas, log_length);
+  let slice_len = 1 << (log_length - LOG_SLICE_NUMBER);
+  let mut scratch_pad = ScratchPad::from_order(1 << (log_length + RS_CODE_RATE));
+  let root_of_unity = FieldElement::get_root_of_unity(log_length - LOG_SLICE_NUMBER)
+    .expect("Failed to retrieve root of unity");
+  let mut coefficients = vec![FE_ZERO; 1 << log_length];
+  for (slice, dst) in weights
+    .chunks(slice_len)
+    .zip(coefficients.chunks_mut(slice_len))
+  {
+    inverse_fast_fourier_transform(
+      &mut scratch_pad,
+      slice,
+      slice_len,
+      slice_len,
+      root_of_unity,
+      dst,
+    );
+  }
+
+  PolyCommitVerifier::default()
+    .verify_poly_commitment(log_length, &coefficients, proof, transcript)
+    .2
+}
+
+#[cfg(test)]
+mod tests {
+  use rand::{rngs::StdRng, SeedableRng};
+
+  use super::*;
+
+  fn random_quadratic(rng: &mut StdRng) -> QuadraticPoly {
+    QuadraticPoly::new(
+      FieldElement::new_random_from_rng(rng),
+      FieldElement::new_random_from_rng(rng),
+      FieldElement::new_random_from_rng(rng),
+    )
+  }
+
+  #[test]
+  fn round_polys_follow_the_sumcheck() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let mask = SumcheckMask::random(5, &mut rng);
+    let mut claim = mask.sum();
+    let mut challenges = vec![];
+    for _ in 0..5 {
+      let poly = mask.round_poly(&challenges);
+      assert_eq!(poly.eval(&FE_ZERO) + poly.eval(&FE_REAL_ONE), claim);
+      let r = FieldElement::new_random_from_rng(&mut rng);
+      claim = poly.eval(&r);
+      challenges.push(r);
+    }
+    assert_eq!(mask.eval(&challenges), claim);
+    let weights = SumcheckMask::eval_weights(&challenges);
+    let inner = mask
+      .coefficients()
+      .iter()
+      .zip(&weights)
+      .fold(FE_ZERO, |acc, (c, w)| acc + *c * *w);
+    assert_eq!(inner, claim);
+  }
+
+  /// The masked messages `f_j + rho g_j` of two sumchecks `f` and `f'` with the
+  /// same challenges are equal for masks `g` and `g'` in bijection, so the
+  /// messages of one masked sumcheck can be simulated without `f`, the layer
+  /// claims between the sumchecks are not
+  #[test]
+  fn masked_messages_are_simulatable() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let n = 4;
+    let rho = FieldElement::new_random_from_rng(&mut rng);
+    let challenges: Vec<_> = (0..n)
+      .map(|_| FieldElement::new_random_from_rng(&mut rng))
+      .collect();
+    let mask = SumcheckMask::random(n, &mut rng);
+
+    // the messages of two unrelated sumchecks, consistent with the challenges
+    let messages = |rng: &mut StdRng| {
+      let mut polys = vec![random_quadratic(rng)];
+      for j in 1..n {
+        let mut poly = random_quadratic(rng);
+        let claim = polys[j - 1].eval(&challenges[j - 1]);
+        poly.c = FE_ZERO;
+        poly.c = (claim - poly.eval(&FE_ZERO) - poly.eval(&FE_REAL_ONE))
+          * FieldElement::from_real(2).inverse();
+        polys.push(poly);
+      }
+      polys
+    };
+    let (f, f_other) = (messages(&mut rng), messages(&mut rng));
+
+    // g' = g + (f - f') / rho, the constant absorbs the difference of the sums
+    let inv_rho = rho.inverse();
+    let mut other = mask.clone();
+    for j in 0..n {
+      let diff = QuadraticPoly::new(
+        f[j].a - f_other[j].a,
+        f[j].b - f_other[j].b,
+        f[j].c - f_other[j].c,
+      ) * inv_rho;
+      let scale = pow2(n - j - 1).inverse();
+      other.terms[j].0 = other.terms[j].0 + diff.b * scale;
+      other.terms[j].1 = other.terms[j].1 + diff.a * scale;
+    }
+    let first = mask.round_poly(&[]) * rho + f[0];
+    let first_other = other.round_poly(&[]) * rho + f_other[0];
+    other.constant = other.constant + (first.c - first_other.c) * (rho * pow2(n - 1)).inverse();
+
+    for j in 0..n {
+      let masked = mask.round_poly(&challenges[..j]) * rho + f[j];
+      let masked_other = other.round_poly(&challenges[..j]) * rho + f_other[j];
+      assert_eq!(masked, masked_other);
+    }
+    // only the final values differ, by those of f and f'
+    let last = f[n - 1].eval(&challenges[n - 1]) - f_other[n - 1].eval(&challenges[n - 1]);
+    assert_eq!(
+      (other.eval(&challenges) - mask.eval(&challenges)) * rho,
+      last
+    );
+  }
+}
@@ -1,5 +1,6 @@
 use global::constants::FE_ZERO;
 use prime_field::FieldElement;
+use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone)]
 
 pub struct LinearPoly {
@@ -42,7 +43,7 @@ impl core::ops::Mul for LinearPoly {
     QuadraticPoly::new(a, b, c)
   }
 }
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct QuadraticPoly {
   pub a: FieldElement,
   pub b: FieldElement,
@@ -74,6 +75,12 @@ impl core::ops::Add for QuadraticPoly {
   }
 }
 
+impl core::ops::Mul<FieldElement> for QuadraticPoly {
+  type Output = QuadraticPoly;
+
+  fn mul(self, x: FieldElement) -> Self::Output { Self::new(self.a * x, self.b * x, self.c * x) }
+}
+
 pub struct CubicPoly {
   pub a: FieldElement,
   pub b: FieldElement,
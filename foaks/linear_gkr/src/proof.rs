@@ -0,0 +1,107 @@
+use infrastructure::{
+  my_hash::MerkleHasher,
+  proof_format::{ProofFormat, ProofFormatError, ProofKind},
+  transcript::Transcript,
+};
+use poly_commitment::PolyCommitProof;
+use prime_field::FieldElement;
+use serde::{Deserialize, Serialize};
+
+use crate::polynomial::QuadraticPoly;
+
+/// Messages of the sumcheck reducing the claim on one layer to claims on the
+/// layer below it
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct LayerProof {
+  pub sumcheck_phase1: Vec<QuadraticPoly>,
+  pub sumcheck_phase2: Vec<QuadraticPoly>,
+  pub v_u: FieldElement,
+  pub v_v: FieldElement,
+  /// Claims on the masking polynomial of the layer in masked proofs
+  pub mask: Option<LayerMask>,
+}
+
+/// Sum of the masking polynomial of a layer over the boolean hypercube and its
+/// value at the sumcheck point, see [`crate::mask`]
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LayerMask {
+  pub sum: FieldElement,
+  pub eval: FieldElement,
+}
+
+/// Coefficients of a public array of evaluations, together with the proof of
+/// the inverse FFT circuit relating the two
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct FftProof {
+  pub coef: Vec<FieldElement>,
+  pub layers: Vec<LayerProof>,
+}
+
+/// Layers are stored from the output layer down to the input layer, the
+/// claim left on the input is settled by the polynomial commitment opening.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct GkrProof {
+  pub layers: Vec<LayerProof>,
+  pub fft_proof: FftProof,
+  pub input_proof: PolyCommitProof,
+  /// Opening of the masking polynomials in masked proofs
+  pub mask_proof: Option<PolyCommitProof>,
+}
+
+impl ProofFormat for LayerProof {
+  const KIND: ProofKind = ProofKind::GkrLayer;
+
+  fn validate(&self) -> Result<(), ProofFormatError> {
+    if self.sumcheck_phase1.len() != self.sumcheck_phase2.len() {
+      return Err(ProofFormatError::Malformed(
+        "sumcheck phases differ in length",
+      ));
+    }
+    Ok(())
+  }
+}
+
+impl FftProof {
+  fn validate(&self) -> Result<(), ProofFormatError> {
+    if !self.coef.len().is_power_of_two() {
+      return Err(ProofFormatError::Malformed(
+        "fft size is not a power of two",
+      ));
+    }
+    self.layers.iter().try_for_each(LayerProof::validate)
+  }
+}
+
+impl ProofFormat for GkrProof {
+  const KIND: ProofKind = ProofKind::Gkr;
+
+  fn validate(&self) -> Result<(), ProofFormatError> {
+    self.layers.iter().try_for_each(LayerProof::validate)?;
+    self.fft_proof.validate()?;
+    self.input_proof.validate()?;
+    let masked = self
+      .layers
+      .iter()
+      .filter(|layer| layer.mask.is_some())
+      .count();
+    match &self.mask_proof {
+      Some(mask_proof) if masked == self.layers.len() => mask_proof.validate(),
+      None if masked == 0 => Ok(()),
+      _ => Err(ProofFormatError::Malformed("masks of some layers only")),
+    }
+  }
+}
+
+/// Claim `value` on the multilinear extension of the input layer at `r_0`
+pub(crate) struct InputClaim {
+  pub r_0: Vec<FieldElement>,
+  pub one_minus_r_0: Vec<FieldElement>,
+  pub value: FieldElement,
+}
+
+pub(crate) fn absorb_quadratic_poly<H: MerkleHasher>(
+  transcript: &mut Transcript<H>,
+  poly: &QuadraticPoly,
+) {
+  transcript.absorb_field_elements(&[poly.a, poly.b, poly.c]);
+}
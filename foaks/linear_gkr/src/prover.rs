@@ -1,4 +1,5 @@
-use global::constants::{FE_REAL_ONE, FE_ZERO, SIZE};
+use global::constants::{FE_REAL_ONE, FE_ZERO, LOG_SLICE_NUMBER, SIZE, SLICE_NUMBER};
+use infrastructure::{my_hash::MerkleHasher, transcript::Transcript};
 use poly_commitment::PolyCommitProver;
 use prime_field::FieldElement;
 //use rayon::prelude::*;
@@ -8,8 +9,12 @@ use std::{
 };
 
 use crate::{
-  circuit_fast_track::LayeredCircuit,
+  circuit_fast_track::{Gate, LayeredCircuit},
+  fft_gkr::prove_inverse_fft,
+  gate::{BinaryRule, GateType},
+  mask::{MaskCommitment, SumcheckMask},
   polynomial::{LinearPoly, QuadraticPoly},
+  proof::{absorb_quadratic_poly, GkrProof, InputClaim, LayerMask, LayerProof},
 };
 
 #[derive(Default, Debug, Clone)]
@@ -18,13 +23,15 @@ pub struct ProverContext {
   pub v_mult_add_new: Vec<LinearPoly>,
   pub add_v_array_new: Vec<LinearPoly>,
   pub add_mult_sum_new: Vec<LinearPoly>,
-  pub gate_meet: Vec<bool>,
   pub rets_prev: Vec<QuadraticPoly>,
   pub rets_cur: Vec<QuadraticPoly>,
 }
 #[derive(Default, Debug, Clone)]
 pub struct ZkProver {
   pub a_c: LayeredCircuit,
+  /// Whether [`ZkProver::prove`] masks the sumchecks and the input, see
+  /// [`crate::mask`]
+  pub masked: bool,
   pub poly_prover: PolyCommitProver,
   /** @name Basic
    * Basic information and variables about the arithmetic circuit */
@@ -91,7 +98,6 @@ impl ZkProver {
 
     let poly_size = 1 << max_bit_length;
     let fe_size = 1 << half_length;
-    self.ctx.gate_meet = vec![false; 15];
     self.ctx.v_mult_add_new = vec![LinearPoly::zero(); poly_size];
     self.ctx.add_v_array_new = vec![LinearPoly::zero(); poly_size];
     self.ctx.add_mult_sum_new = vec![LinearPoly::zero(); poly_size];
@@ -142,7 +148,7 @@ impl ZkProver {
     let t0 = time::Instant::now();
 
     for gate in &self.a_c.circuit[0].gates {
-      assert!(gate.ty == 3 || gate.ty == 2);
+      assert!(matches!(gate.ty, GateType::Input | GateType::Dummy));
     }
 
     assert!(self.a_c.total_depth < 1000000);
@@ -152,47 +158,26 @@ impl ZkProver {
 
       for (g, gate) in self.a_c.circuit[i].gates.iter().enumerate() {
         let (ty, u, v) = (gate.ty, gate.u, gate.v);
-        let (value_u, value_v) = (self.circuit_value[i - 1][u], self.circuit_value[i - 1][v]);
+        let previous = &self.circuit_value[i - 1];
 
         self.circuit_value[i][g] = match ty {
-          0 => value_u + value_v,
-          1 => {
-            let (u_len, v_len) = (
-              self.a_c.circuit[i - 1].gates.len(),
-              self.a_c.circuit[i - 1].gates.len(),
-            );
-            assert!(u < u_len && v < v_len);
-            value_u * value_v
+          GateType::Dummy => FE_ZERO,
+          GateType::Input => FieldElement::from_real(u as u64),
+          GateType::DirectRelay => previous[u],
+          GateType::Sum => (u..v).fold(FE_ZERO, |acc, k| acc + previous[k]),
+          GateType::ExpSum => (u..=v).fold(FE_ZERO, |acc, k| {
+            acc + previous[k] * FieldElement::from_real(1u64 << (k - u))
+          }),
+          GateType::CustomLinearComb => (0..gate.parameter_length).fold(FE_ZERO, |acc, k| {
+            acc + previous[gate.src[k]] * gate.weight[k]
+          }),
+          _ => {
+            if ty == GateType::BitTest {
+              assert_eq!(u, v);
+            }
+            let rule = self.a_c.registry.rule(ty).expect("gate type not supported");
+            rule.eval(previous[u], previous[v])
           }
-          2 => FE_ZERO,
-          3 => FieldElement::from_real(u as u64),
-          4 | 10 => value_u,
-          5 => (u..v)
-            .map(|k| self.circuit_value[i - 1][k])
-            .fold(FE_ZERO, |acc, val| acc + val),
-          6 => FE_REAL_ONE - value_u,
-          7 => value_u - value_v,
-          8 => value_u + value_v - FieldElement::from_real(2) * value_u * value_v,
-          9 => {
-            let (u_len, v_len) = (
-              self.a_c.circuit[i - 1].gates.len(),
-              self.a_c.circuit[i - 1].gates.len(),
-            );
-            assert!(u < u_len && v < v_len);
-            value_v - value_u * value_v
-          }
-          12 => (u..=v)
-            .map(|k| self.circuit_value[i - 1][k] * FieldElement::from_real(1u64 << (k - u)))
-            .fold(FE_ZERO, |acc, val| acc + val),
-          13 => {
-            assert_eq!(u, v);
-            assert!(u < self.a_c.circuit[i - 1].gates.len());
-            value_u * (FE_REAL_ONE - value_v)
-          }
-          14 => (0..gate.parameter_length)
-            .map(|k| self.circuit_value[i - 1][gate.src[k]] * gate.weight[k])
-            .fold(FE_ZERO, |acc, val| acc + val),
-          _ => panic!("gate type not supported"),
         };
       }
     }
@@ -224,6 +209,23 @@ impl ZkProver {
     self.one_minus_r_1 = zkprover.one_minus_r_1;
   }
 
+  /// Rules of the gates of the sumcheck layer, indexed by the gate masked
+  /// with the returned mask. The gates of a parallel layer share the rules of
+  /// its first block.
+  fn layer_rules(&self) -> (Vec<Option<BinaryRule>>, usize) {
+    let layer = &self.a_c.circuit[self.sumcheck_layer_id];
+    let len = if layer.is_parallel {
+      layer.block_size
+    } else {
+      layer.gates.len()
+    };
+    let rules = layer.gates[..len]
+      .iter()
+      .map(|gate| self.a_c.registry.rule(gate.ty))
+      .collect();
+    (rules, len - 1)
+  }
+
   pub fn sumcheck_phase1_init(&mut self) {
     let t0 = time::Instant::now();
     self.total_uv = self.a_c.circuit[self.sumcheck_layer_id - 1].gates.len();
@@ -271,222 +273,65 @@ impl ZkProver {
     //todo
     //	#pragma omp parallel for
 
+    let (rules, rule_mask) = self.layer_rules();
+    let layer = &self.a_c.circuit[self.sumcheck_layer_id];
+    let previous = &self.circuit_value[self.sumcheck_layer_id - 1];
+    let beta_g = |g: usize| {
+      self.beta_g_r0_fhalf[g & mask_fhalf] * self.beta_g_r0_shalf[g >> first_half]
+        + self.beta_g_r1_fhalf[g & mask_fhalf] * self.beta_g_r1_shalf[g >> first_half]
+    };
+
+    // intermediates0 goes to the coefficient of V(u), intermediates1 to the
+    // constant term
     for i in 0..(1 << self.length_g) {
-      let u = self.a_c.circuit[self.sumcheck_layer_id].gates[i].u;
-      let v = self.a_c.circuit[self.sumcheck_layer_id].gates[i].v;
-      let ty = self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty;
+      let Gate { ty, u, v, .. } = layer.gates[i];
       match ty {
-        0 => {
-          //add gate
-          let tmp = self.beta_g_r0_fhalf[i & mask_fhalf] * self.beta_g_r0_shalf[i >> first_half]
-            + self.beta_g_r1_fhalf[i & mask_fhalf] * self.beta_g_r1_shalf[i >> first_half];
-          intermediates0[i] = self.circuit_value[self.sumcheck_layer_id - 1][v] * tmp;
-          intermediates1[i] = tmp;
-        }
-        2 => {}
-        1 => {
-          //mult gate
-          let tmp = self.beta_g_r0_fhalf[i & mask_fhalf] * self.beta_g_r0_shalf[i >> first_half]
-            + self.beta_g_r1_fhalf[i & mask_fhalf] * self.beta_g_r1_shalf[i >> first_half];
-          intermediates0[i] = self.circuit_value[self.sumcheck_layer_id - 1][v] * tmp;
-        }
-        5 => {
-          //sum gate
-          let tmp = self.beta_g_r0_fhalf[i & mask_fhalf] * self.beta_g_r0_shalf[i >> first_half]
-            + self.beta_g_r1_fhalf[i & mask_fhalf] * self.beta_g_r1_shalf[i >> first_half];
-          intermediates1[i] = tmp;
-        }
-        12 => {
-          //exp sum gate
-          let tmp = self.beta_g_r0_fhalf[i & mask_fhalf] * self.beta_g_r0_shalf[i >> first_half]
-            + self.beta_g_r1_fhalf[i & mask_fhalf] * self.beta_g_r1_shalf[i >> first_half];
-          intermediates1[i] = tmp;
-        }
-        4 => {
-          //direct relay gate
-          let tmp = self.beta_g_r0_fhalf[u & mask_fhalf] * self.beta_g_r0_shalf[u >> first_half]
-            + self.beta_g_r1_fhalf[u & mask_fhalf] * self.beta_g_r1_shalf[u >> first_half];
-          intermediates1[i] = tmp;
-        }
-        6 => {
-          //NOT gate
-          let tmp = self.beta_g_r0_fhalf[i & mask_fhalf] * self.beta_g_r0_shalf[i >> first_half]
-            + self.beta_g_r1_fhalf[i & mask_fhalf] * self.beta_g_r1_shalf[i >> first_half];
-          intermediates1[i] = tmp;
-        }
-        7 => {
-          //minus gate
-          let tmp = self.beta_g_r0_fhalf[i & mask_fhalf] * self.beta_g_r0_shalf[i >> first_half]
-            + self.beta_g_r1_fhalf[i & mask_fhalf] * self.beta_g_r1_shalf[i >> first_half];
-          intermediates0[i] = self.circuit_value[self.sumcheck_layer_id - 1][v] * tmp;
-          intermediates1[i] = tmp;
-        }
-        8 => {
-          //XOR gate
-          let tmp = self.beta_g_r0_fhalf[i & mask_fhalf] * self.beta_g_r0_shalf[i >> first_half]
-            + self.beta_g_r1_fhalf[i & mask_fhalf] * self.beta_g_r1_shalf[i >> first_half];
-          let tmp_v = tmp * self.circuit_value[self.sumcheck_layer_id - 1][v];
-          intermediates0[i] = tmp_v;
-          intermediates1[i] = tmp;
-        }
-        13 => {
-          //bit-test gate
-          let tmp = self.beta_g_r0_fhalf[i & mask_fhalf] * self.beta_g_r0_shalf[i >> first_half]
-            + self.beta_g_r1_fhalf[i & mask_fhalf] * self.beta_g_r1_shalf[i >> first_half];
-          let tmp_v = tmp * self.circuit_value[self.sumcheck_layer_id - 1][v];
-          intermediates0[i] = tmp_v;
-          intermediates1[i] = tmp;
-        }
-        9 => {
-          //NAAB gate
-          let tmp = self.beta_g_r0_fhalf[i & mask_fhalf] * self.beta_g_r0_shalf[i >> first_half]
-            + self.beta_g_r1_fhalf[i & mask_fhalf] * self.beta_g_r1_shalf[i >> first_half];
-          let tmp_v = tmp * self.circuit_value[self.sumcheck_layer_id - 1][v];
-          intermediates1[i] = tmp_v;
-        }
-        10 => {
-          //relay gate
-          let a = self.beta_g_r0_fhalf[i & mask_fhalf];
-          let b = self.beta_g_r0_shalf[i >> first_half];
-          let c = self.beta_g_r1_fhalf[i & mask_fhalf];
-          let d = self.beta_g_r1_shalf[i >> first_half];
-          let tmp = a * b + c * d;
-          intermediates0[i] = tmp;
-        }
-        14 => {
-          //custom comb
-          let tmp = self.beta_g_r0_fhalf[i & mask_fhalf] * self.beta_g_r0_shalf[i >> first_half]
-            + self.beta_g_r1_fhalf[i & mask_fhalf] * self.beta_g_r1_shalf[i >> first_half];
-          intermediates1[i] = tmp;
-        }
-        _ => {
-          eprintln!(
-            "Warning Unknown gate {}",
-            self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty
-          )
-        }
+        GateType::Dummy | GateType::Input => {}
+        GateType::DirectRelay => intermediates0[i] = beta_g(u),
+        GateType::Sum | GateType::ExpSum | GateType::CustomLinearComb => {
+          intermediates0[i] = beta_g(i)
+        }
+        _ => match rules[i & rule_mask] {
+          Some(rule) => {
+            let tmp = beta_g(i);
+            intermediates0[i] = tmp * (rule.u + rule.uv * previous[v]);
+            intermediates1[i] = tmp * (rule.constant + rule.v * previous[v]);
+          }
+          None => eprintln!("Warning Unknown gate {:?}", ty),
+        },
       }
     }
 
-    for i in 0..1 << self.length_g {
-      let u = self.a_c.circuit[self.sumcheck_layer_id].gates[i].u;
-      let v = self.a_c.circuit[self.sumcheck_layer_id].gates[i].v;
-
-      match self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty {
-        0 => {
-          //add gate
-          if !self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] {
-            self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] = true;
-          }
-          self.add_v_array[u].b = self.add_v_array[u].b + intermediates0[i];
-          self.add_mult_sum[u].b = self.add_mult_sum[u].b + intermediates1[i];
-        }
-        2 => {}
-        1 => {
-          //mult gate
-          if !self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] {
-            self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] = true;
-          }
-          self.add_mult_sum[u].b = self.add_mult_sum[u].b + intermediates0[i];
-        }
-        5 => {
-          //sum gate
-          if !self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] {
-            self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] = true;
-          }
+    for (i, gate) in layer.gates.iter().enumerate().take(1 << self.length_g) {
+      let (u, v) = (gate.u, gate.v);
+      match gate.ty {
+        GateType::Dummy | GateType::Input => {}
+        GateType::Sum => {
           for j in u..v {
-            self.add_mult_sum[j].b = self.add_mult_sum[j].b + intermediates1[i];
+            self.add_mult_sum[j].b = self.add_mult_sum[j].b + intermediates0[i];
           }
         }
-
-        12 =>
-        //exp sum gate
-        {
-          if !self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] {
-            self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] = true;
-          }
-          let mut tmp = intermediates1[i];
-          for j in u..v {
+        GateType::ExpSum => {
+          let mut tmp = intermediates0[i];
+          for j in u..=v {
             self.add_mult_sum[j].b = self.add_mult_sum[j].b + tmp;
             tmp = tmp + tmp;
           }
         }
-        14 => {
-          if !self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] {
-            self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] = true;
+        GateType::CustomLinearComb => {
+          for (&src, &weight) in gate
+            .src
+            .iter()
+            .zip(&gate.weight)
+            .take(gate.parameter_length)
+          {
+            self.add_mult_sum[src].b = self.add_mult_sum[src].b + weight * intermediates0[i];
           }
-          let tmp = intermediates1[i];
-          for j in 0..self.a_c.circuit[self.sumcheck_layer_id].gates[i].parameter_length {
-            let src = self.a_c.circuit[self.sumcheck_layer_id].gates[i].src[j];
-            let weight = self.a_c.circuit[self.sumcheck_layer_id].gates[i].weight[j];
-            self.add_mult_sum[src].b = self.add_mult_sum[src].b + weight * tmp;
-          }
-        }
-        4 =>
-        //direct relay gate
-        {
-          if !self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] {
-            self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] = true;
-          }
-          self.add_mult_sum[u].b = self.add_mult_sum[u].b + intermediates1[i];
-        }
-        6 =>
-        //NOT gate
-        {
-          if !self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] {
-            self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] = true;
-          }
-          self.add_mult_sum[u].b = self.add_mult_sum[u].b - intermediates1[i];
-          self.add_v_array[u].b = self.add_v_array[u].b + intermediates1[i];
-        }
-        7 =>
-        //minus gate
-        {
-          if !self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] {
-            self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] = true;
-          }
-          self.add_v_array[u].b = self.add_v_array[u].b - (intermediates0[i]);
-          self.add_mult_sum[u].b = self.add_mult_sum[u].b + intermediates1[i];
-        }
-        8 =>
-        //XOR gate
-        {
-          if !self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] {
-            self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] = true;
-          }
-          self.add_v_array[u].b = self.add_v_array[u].b + intermediates0[i];
-          self.add_mult_sum[u].b =
-            self.add_mult_sum[u].b + intermediates1[i] - intermediates0[i] - intermediates0[i];
-        }
-        13 =>
-        //bit-test gate
-        {
-          if !self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] {
-            self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] = true;
-          }
-          self.add_mult_sum[u].b = self.add_mult_sum[u].b - intermediates0[i] + intermediates1[i];
-        }
-        9 =>
-        //NAAB gate
-        {
-          if !self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] {
-            self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] = true;
-          }
-          self.add_v_array[u].b = self.add_v_array[u].b + intermediates1[i];
-          self.add_mult_sum[u].b = self.add_mult_sum[u].b - intermediates1[i];
         }
-        10 =>
-        //relay gate
-        {
-          if !self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] {
-            self.ctx.gate_meet[self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty] = true;
-          }
+        _ => {
           self.add_mult_sum[u].b = self.add_mult_sum[u].b + intermediates0[i];
+          self.add_v_array[u].b = self.add_v_array[u].b + intermediates1[i];
         }
-        _ => println!(
-          "Warning Unknown gate {}",
-          self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty
-        ),
       }
     }
     let time_span = t0.elapsed();
@@ -615,145 +460,66 @@ impl ZkProver {
     let mut intermediates0 = vec![FE_ZERO; total_g];
     let mut intermediates1 = vec![FE_ZERO; total_g];
 
+    let (rules, rule_mask) = self.layer_rules();
+    let layer = &self.a_c.circuit[self.sumcheck_layer_id];
+    let beta_u = |u: usize| self.beta_u_fhalf[u & mask_fhalf] * self.beta_u_shalf[u >> first_half];
+
+    // intermediates0 goes to the coefficient of V(v), intermediates1 to the
+    // constant term
     //todo
     //#pragma omp parallel for
     for i in 0..total_g {
-      let ty = self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty;
-      let u = self.a_c.circuit[self.sumcheck_layer_id].gates[i].u;
-
-      let tmp_u = self.beta_u_fhalf[u & mask_fhalf] * self.beta_u_shalf[u >> first_half];
+      let Gate { ty, u, .. } = layer.gates[i];
       let tmp_g = self.beta_g_r0_fhalf[i & mask_g_fhalf] * self.beta_g_r0_shalf[i >> first_g_half]
         + self.beta_g_r1_fhalf[i & mask_g_fhalf] * self.beta_g_r1_shalf[i >> first_g_half];
 
       match ty {
-        0 => {
-          // add gate
-          intermediates0[i] = tmp_g * tmp_u;
-          intermediates1[i] = intermediates0[i] * self.v_u;
-        }
-        1 => {
-          // mult gate
-          intermediates0[i] = tmp_g * tmp_u * self.v_u;
-        }
-        5 | 12 | 14 => {
-          // sum gate, exp sum gate, custom comb gate
+        GateType::Dummy | GateType::Input | GateType::DirectRelay => {}
+        GateType::Sum | GateType::ExpSum | GateType::CustomLinearComb => {
           intermediates0[i] = tmp_g * self.v_u;
         }
-        6 => {
-          // not gate
-          intermediates0[i] = tmp_g * tmp_u - tmp_g * tmp_u * self.v_u;
-        }
-        7 => {
-          // minus gate
-          intermediates0[i] = tmp_g * tmp_u;
-          intermediates1[i] = intermediates0[i] * self.v_u;
-        }
-        8 => {
-          // xor gate
-          let tmp_v_u = tmp_g * tmp_u * self.v_u;
-          intermediates0[i] = tmp_g * tmp_u - tmp_v_u - tmp_v_u;
-          intermediates1[i] = tmp_v_u;
-        }
-        9 => {
-          // NAAB gate
-          intermediates0[i] = tmp_g * tmp_u - self.v_u * tmp_u;
-        }
-        10 => {
-          // relay gate
-          intermediates0[i] = tmp_g * tmp_u * self.v_u;
-        }
-        2 | 4 => { /* No operation required */ }
-        _ => {
-          println!("Warning Unknown gate {}", ty);
-        }
+        _ => match rules[i & rule_mask] {
+          Some(rule) => {
+            let tmp = tmp_g * beta_u(u);
+            intermediates0[i] = tmp * (rule.v + rule.uv * self.v_u);
+            intermediates1[i] = tmp * (rule.constant + rule.u * self.v_u);
+          }
+          None => eprintln!("Warning Unknown gate {:?}", ty),
+        },
       }
     }
 
-    for i in 0..total_g {
-      let ty = self.a_c.circuit[self.sumcheck_layer_id].gates[i].ty;
-      let u = self.a_c.circuit[self.sumcheck_layer_id].gates[i].u;
-      let v = self.a_c.circuit[self.sumcheck_layer_id].gates[i].v;
-      match ty {
-        1 =>
-        //mult gate
-        {
-          self.add_mult_sum[v].b = self.add_mult_sum[v].b + intermediates0[i];
-        }
-        0 =>
-        //add gate
-        {
-          self.add_mult_sum[v].b = self.add_mult_sum[v].b + intermediates0[i];
-          self.add_v_array[v].b = intermediates1[i] + self.add_v_array[v].b;
-        }
-        2 => {}
-        4 => {}
-        5 =>
-        //sum gate
-        {
+    for (i, gate) in layer.gates.iter().enumerate() {
+      let (u, v) = (gate.u, gate.v);
+      match gate.ty {
+        GateType::Dummy | GateType::Input | GateType::DirectRelay => {}
+        GateType::Sum => {
           for j in u..v {
-            let tmp_u = self.beta_u_fhalf[j & mask_fhalf] * self.beta_u_shalf[j >> first_half];
-            self.add_v_array[0].b = self.add_v_array[0].b + intermediates0[i] * tmp_u;
+            self.add_v_array[0].b = self.add_v_array[0].b + intermediates0[i] * beta_u(j);
           }
         }
-        12 =>
-        //exp sum gate
-        {
+        GateType::ExpSum => {
           let mut tmp_g_vu = intermediates0[i];
-
-          for j in u..v {
-            let tmp_u = self.beta_u_fhalf[j & mask_fhalf] * self.beta_u_shalf[j >> first_half];
-            self.add_v_array[0].b = self.add_v_array[0].b + tmp_g_vu * tmp_u;
+          for j in u..=v {
+            self.add_v_array[0].b = self.add_v_array[0].b + tmp_g_vu * beta_u(j);
             tmp_g_vu = tmp_g_vu + tmp_g_vu;
           }
         }
-        14 =>
-        //custom comb gate
-        {
-          let tmp_g_vu = intermediates0[i];
-
-          for j in 0..self.a_c.circuit[self.sumcheck_layer_id].gates[i].parameter_length {
-            let src = self.a_c.circuit[self.sumcheck_layer_id].gates[i].src[j];
-            let tmp_u = self.beta_u_fhalf[src & mask_fhalf] * self.beta_u_shalf[src >> first_half];
-            let weight = self.a_c.circuit[self.sumcheck_layer_id].gates[i].weight[j];
-            self.add_v_array[0].b = self.add_v_array[0].b + tmp_g_vu * tmp_u * weight;
+        GateType::CustomLinearComb => {
+          for (&src, &weight) in gate
+            .src
+            .iter()
+            .zip(&gate.weight)
+            .take(gate.parameter_length)
+          {
+            self.add_v_array[0].b =
+              self.add_v_array[0].b + intermediates0[i] * beta_u(src) * weight;
           }
         }
-        6 =>
-        //not gate
-        {
-          self.add_v_array[v].b = self.add_v_array[v].b + intermediates0[i];
-        }
-        7 =>
-        //minus gate
-        {
-          self.add_mult_sum[v].b = self.add_mult_sum[v].b - intermediates0[i];
-          self.add_v_array[v].b = intermediates1[i] + self.add_v_array[v].b;
-        }
-        8 =>
-        //xor gate
-        {
+        _ => {
           self.add_mult_sum[v].b = self.add_mult_sum[v].b + intermediates0[i];
           self.add_v_array[v].b = self.add_v_array[v].b + intermediates1[i];
         }
-        13 =>
-        //bit-test gate
-        {
-          self.add_mult_sum[v].b = self.add_mult_sum[v].b - intermediates0[i];
-          self.add_v_array[v].b = self.add_v_array[v].b + intermediates0[i];
-        }
-        9 =>
-        //NAAB gate
-        {
-          self.add_mult_sum[v].b = self.add_mult_sum[v].b + intermediates0[i];
-        }
-        10 =>
-        //relay gate
-        {
-          self.add_v_array[v].b = self.add_v_array[v].b + intermediates0[i];
-        }
-        _ => {
-          println!("Warning Unknown gate {}", ty);
-        }
       }
     }
     let time_span = t0.elapsed();
@@ -857,4 +623,327 @@ impl ZkProver {
     self.v_v = self.v_mult_add[0].eval(previous_random);
     (self.v_u, self.v_v)
   }
+
+  /// Reduces the claim on the circuit outputs, which are absorbed into
+  /// `transcript`, to a claim on the input layer. With `masks`, one per layer
+  /// from the output layer down, the sumchecks are masked and the points at
+  /// which the masks are opened are returned.
+  fn prove_layers<H: MerkleHasher>(
+    &mut self,
+    transcript: &mut Transcript<H>,
+    masks: &[SumcheckMask],
+  ) -> (Vec<LayerProof>, InputClaim, Vec<Vec<FieldElement>>) {
+    let result = self.evaluate();
+    let mut alpha = FE_REAL_ONE;
+    let mut beta = FE_ZERO;
+
+    let capacity = self.a_c.circuit[self.a_c.total_depth - 1].bit_length;
+
+    transcript.absorb_field_elements(&result);
+    let mut r_0 = transcript.squeeze_field_elements(capacity);
+    let mut r_1 = transcript.squeeze_field_elements(capacity);
+
+    let mut one_minus_r_0 = r_0.iter().map(|x| FE_REAL_ONE - *x).collect::<Vec<_>>();
+    let mut one_minus_r_1 = r_1.iter().map(|x| FE_REAL_ONE - *x).collect::<Vec<_>>();
+
+    let mut alpha_beta_sum = self.v_res(&one_minus_r_0, &r_0, result);
+    let mut layers = Vec::with_capacity(self.a_c.total_depth - 1);
+    let mut mask_points = Vec::with_capacity(masks.len());
+
+    for (k, i) in (1..self.a_c.total_depth).rev().enumerate() {
+      let previous_bit_length = self.a_c.circuit[i - 1].bit_length;
+      // the masked polynomial is the layer polynomial plus rho times the mask
+      let mask = masks.get(k);
+      let rho = mask.map(|mask| {
+        transcript.absorb_field_element(mask.sum());
+        transcript.squeeze_field_element()
+      });
+      let masked = |poly: QuadraticPoly, challenges: &[FieldElement]| match (mask, rho) {
+        (Some(mask), Some(rho)) => poly + mask.round_poly(challenges) * rho,
+        _ => poly,
+      };
+      let mut challenges = Vec::with_capacity(2 * previous_bit_length);
+
+      self.sumcheck_init(SumcheckInitArgs {
+        sumcheck_layer_id: i,
+        length_g: self.a_c.circuit[i].bit_length,
+        length_v: previous_bit_length,
+        alpha,
+        beta,
+        r_0: r_0.clone(),
+        r_1: r_1.clone(),
+        one_minus_r_0: one_minus_r_0.clone(),
+        one_minus_r_1: one_minus_r_1.clone(),
+      });
+
+      self.sumcheck_phase1_init();
+
+      let mut sumcheck_phase1 = Vec::with_capacity(previous_bit_length);
+      let mut r_u = Vec::with_capacity(previous_bit_length);
+      let mut previous_random = FE_ZERO;
+      for j in 0..previous_bit_length {
+        let poly = masked(self.sumcheck_phase1_update(previous_random, j), &challenges);
+        absorb_quadratic_poly(transcript, &poly);
+        previous_random = transcript.squeeze_field_element();
+        r_u.push(previous_random);
+        challenges.push(previous_random);
+        sumcheck_phase1.push(poly);
+      }
+      let one_minus_r_u: Vec<FieldElement> = r_u.iter().map(|x| FE_REAL_ONE - *x).collect();
+
+      self.v_u = self.v_mult_add[0].eval(previous_random);
+      transcript.absorb_field_element(self.v_u);
+      self.sumcheck_phase2_init(&r_u, &one_minus_r_u);
+
+      let mut sumcheck_phase2 = Vec::with_capacity(previous_bit_length);
+      let mut r_v = Vec::with_capacity(previous_bit_length);
+      let mut previous_random = FE_ZERO;
+      for j in 0..previous_bit_length {
+        let poly = masked(self.sumcheck_phase2_update(previous_random, j), &challenges);
+        absorb_quadratic_poly(transcript, &poly);
+        previous_random = if i == 1 {
+          FE_ZERO
+        } else {
+          transcript.squeeze_field_element()
+        };
+        r_v.push(previous_random);
+        challenges.push(previous_random);
+        sumcheck_phase2.push(poly);
+      }
+      let one_minus_r_v: Vec<FieldElement> = r_v.iter().map(|x| FE_REAL_ONE - *x).collect();
+
+      let (v_u, v_v) = self.sumcheck_finalize(previous_random);
+      let mask = mask.map(|mask| LayerMask {
+        sum: mask.sum(),
+        eval: mask.eval(&challenges),
+      });
+      layers.push(LayerProof {
+        sumcheck_phase1,
+        sumcheck_phase2,
+        v_u,
+        v_v,
+        mask,
+      });
+      if let Some(mask) = mask {
+        transcript.absorb_field_element(mask.eval);
+        mask_points.push(challenges);
+      }
+
+      transcript.absorb_field_elements(&[v_u, v_v]);
+      alpha = transcript.squeeze_field_element();
+      beta = transcript.squeeze_field_element();
+
+      if i != 1 {
+        alpha_beta_sum = alpha * v_u + beta * v_v;
+      } else {
+        alpha_beta_sum = v_u;
+      }
+      r_0 = r_u;
+      r_1 = r_v;
+      one_minus_r_0 = one_minus_r_u;
+      one_minus_r_1 = one_minus_r_v;
+    }
+
+    (
+      layers,
+      InputClaim {
+        r_0,
+        one_minus_r_0,
+        value: alpha_beta_sum,
+      },
+      mask_points,
+    )
+  }
+
+  /// Proves the evaluation of the circuit on the witness given to
+  /// [`ZkProver::get_witness`], the witness is opened with the polynomial
+  /// commitment. In masked mode the circuit is proven with its input mask,
+  /// see [`crate::mask`].
+  pub fn prove<H: MerkleHasher>(&mut self, transcript: &mut Transcript<H>) -> GkrProof {
+    if !self.masked {
+      return self.prove_committed(transcript, None);
+    }
+
+    let mut rng = rand::thread_rng();
+    let plain = self.a_c.clone();
+    let values = self.circuit_value.clone();
+    let input_size = plain.circuit[0].gates.len();
+    let mut masked_witness = values[0].clone();
+    masked_witness.resize(input_size, FE_ZERO);
+    masked_witness.extend((0..input_size).map(|_| FieldElement::new_random_from_rng(&mut rng)));
+
+    let a_c = plain.with_input_mask();
+    self.init_array(a_c.max_bit_length(), a_c);
+    self.get_witness(masked_witness);
+    let num_vars: Vec<_> = (1..self.a_c.total_depth)
+      .rev()
+      .map(|i| 2 * self.a_c.circuit[i - 1].bit_length)
+      .collect();
+    let masks = MaskCommitment::commit::<H, _>(&num_vars, &mut rng);
+    let proof = self.prove_committed(transcript, Some(masks));
+
+    self.a_c = plain;
+    self.circuit_value = values;
+    proof
+  }
+
+  fn prove_committed<H: MerkleHasher>(
+    &mut self,
+    transcript: &mut Transcript<H>,
+    masks: Option<MaskCommitment>,
+  ) -> GkrProof {
+    if let Some(masks) = &masks {
+      transcript.absorb_digest(masks.root);
+    }
+    // the witness is committed before any challenge is drawn
+    let input_bit_length = self.a_c.circuit[0].bit_length;
+    let merkle_root_l = self
+      .poly_prover
+      .commit_private_array::<H>(&self.circuit_value[0], input_bit_length);
+    transcript.absorb_digest(merkle_root_l);
+    let (
+      layers,
+      InputClaim {
+        r_0,
+        one_minus_r_0,
+        value,
+      },
+      mask_points,
+    ) = self.prove_layers(transcript, masks.as_ref().map_or(&[], |m| &m.masks));
+    let mask_proof = masks.map(|masks| masks.open(&mask_points, transcript));
+
+    println!("GKR Prove Time: {}", self.total_time);
+    let mut all_sum = vec![FE_ZERO; SLICE_NUMBER];
+    println!("GKR witness size: {}", self.a_c.circuit[0].gates.len());
+
+    let mut q_eval_real = vec![FE_ZERO; self.a_c.circuit[0].gates.len()];
+    dfs_for_public_eval(
+      &mut q_eval_real,
+      0,
+      FE_REAL_ONE,
+      &r_0,
+      &one_minus_r_0,
+      input_bit_length,
+      0,
+    );
+
+    let merkle_root_h = self.poly_prover.commit_public_array::<H>(
+      &q_eval_real,
+      input_bit_length,
+      value,
+      &mut all_sum,
+    );
+
+    // the verifier needs the coefficients of the public array, they are
+    // proven with the inverse FFT circuit
+    let fft_t0 = time::Instant::now();
+    let log_coef_length = input_bit_length - LOG_SLICE_NUMBER;
+    let mut q_eval_verifier = vec![FE_ZERO; 1 << log_coef_length];
+    dfs_coef(
+      &mut q_eval_verifier,
+      0,
+      FE_REAL_ONE,
+      &r_0,
+      &one_minus_r_0,
+      0,
+      log_coef_length,
+    );
+    let fft_proof = prove_inverse_fft(&q_eval_verifier, transcript);
+    self.total_time += fft_t0.elapsed().as_secs_f64();
+
+    let input_proof = self.poly_prover.open(
+      input_bit_length,
+      merkle_root_l,
+      merkle_root_h,
+      all_sum,
+      transcript,
+    );
+    println!("Prove time {}", self.total_time);
+
+    GkrProof {
+      layers,
+      fft_proof,
+      input_proof,
+      mask_proof,
+    }
+  }
+
+  /// Same as [`ZkProver::prove`] for a circuit whose input is known to the
+  /// verifier, nothing is committed. The proof is never zero knowledge.
+  pub fn prove_public_input<H: MerkleHasher>(
+    &mut self,
+    transcript: &mut Transcript<H>,
+  ) -> Vec<LayerProof> {
+    self.prove_layers(transcript, &[]).0
+  }
+}
+
+/// Fills `dst` with the evaluations of the multilinear extension of the
+/// equality function at `r_0`
+pub fn dfs_for_public_eval(
+  dst: &mut [FieldElement],
+  dep: usize,
+  val: FieldElement,
+  r_0: &[FieldElement],
+  one_minus_r_0: &[FieldElement],
+  r_0_len: usize,
+  pos: usize,
+) {
+  if dep == r_0_len {
+    dst[pos] = val;
+  } else {
+    dfs_for_public_eval(
+      dst,
+      dep + 1,
+      val * one_minus_r_0[r_0_len - 1 - dep],
+      r_0,
+      one_minus_r_0,
+      r_0_len,
+      pos << 1,
+    );
+    dfs_for_public_eval(
+      dst,
+      dep + 1,
+      val * r_0[r_0_len - 1 - dep],
+      r_0,
+      one_minus_r_0,
+      r_0_len,
+      pos << 1 | 1,
+    );
+  }
+}
+
+/// Fills `dst` with the evaluations of the multilinear extension of the
+/// equality function at the first `r_len` elements of `r`
+pub fn dfs_coef(
+  dst: &mut [FieldElement],
+  dep: usize,
+  val: FieldElement,
+  r: &[FieldElement],
+  one_minus_r: &[FieldElement],
+  pos: usize,
+  r_len: usize,
+) {
+  if dep == r_len {
+    dst[pos] = val;
+  } else {
+    dfs_coef(
+      dst,
+      dep + 1,
+      val * one_minus_r[r_len - 1 - dep],
+      r,
+      one_minus_r,
+      pos << 1,
+      r_len,
+    );
+    dfs_coef(
+      dst,
+      dep + 1,
+      val * r[r_len - 1 - dep],
+      r,
+      one_minus_r,
+      pos << 1 | 1,
+      r_len,
+    );
+  }
 }
@@ -0,0 +1,240 @@
+//! Sumcheck for sums over the boolean hypercube of a [`SumOfProducts`], a
+//! linear combination of products of multilinear polynomials.
+//!
+//! Variables are bound from the lowest bit of the evaluation index up, as in
+//! the GKR prover. Each round message is the univariate restriction of the sum
+//! given by its values at `0, 1, ..., degree`. The protocol ends with a claim
+//! on the polynomial at the challenge point, which the caller settles, for
+//! instance with a polynomial commitment opening or another sumcheck.
+
+use global::constants::{FE_REAL_ONE, FE_ZERO};
+use infrastructure::{
+  my_hash::MerkleHasher,
+  proof_format::{ProofFormat, ProofFormatError, ProofKind},
+  transcript::Transcript,
+};
+use prime_field::FieldElement;
+use serde::{Deserialize, Serialize};
+
+/// `Σ_i c_i Π_{j ∈ S_i} f_j` over multilinear polynomials `f_j` given by their
+/// evaluations on the boolean hypercube
+#[derive(Default, Debug, Clone)]
+pub struct SumOfProducts {
+  num_vars: usize,
+  mles: Vec<Vec<FieldElement>>,
+  products: Vec<(FieldElement, Vec<usize>)>,
+}
+
+impl SumOfProducts {
+  pub fn new(num_vars: usize) -> Self {
+    Self {
+      num_vars,
+      ..Default::default()
+    }
+  }
+
+  pub fn num_vars(&self) -> usize { self.num_vars }
+
+  /// Adds the multilinear polynomial of `evals` and returns its index
+  pub fn add_mle(&mut self, evals: Vec<FieldElement>) -> usize {
+    assert_eq!(evals.len(), 1 << self.num_vars, "mle size mismatch");
+    self.mles.push(evals);
+    self.mles.len() - 1
+  }
+
+  /// Adds the term `coefficient` times the product of the polynomials `mles`
+  pub fn add_product(&mut self, coefficient: FieldElement, mles: &[usize]) {
+    assert!(mles.iter().all(|&j| j < self.mles.len()), "unknown mle");
+    self.products.push((coefficient, mles.to_vec()));
+  }
+
+  /// Degree of the polynomial in each variable, at least one, the round
+  /// messages have one more value
+  pub fn degree(&self) -> usize {
+    self
+      .products
+      .iter()
+      .map(|(_, mles)| mles.len())
+      .max()
+      .unwrap_or(0)
+      .max(1)
+  }
+
+  /// Sum over the boolean hypercube
+  pub fn sum(&self) -> FieldElement {
+    (0..1 << self.num_vars).fold(FE_ZERO, |acc, x| acc + self.combine(|j| self.mles[j][x]))
+  }
+
+  /// Evaluations of the polynomials at `point`, one per mle
+  pub fn mle_evals(&self, point: &[FieldElement]) -> Vec<FieldElement> {
+    assert_eq!(point.len(), self.num_vars);
+    self
+      .mles
+      .iter()
+      .map(|evals| {
+        point
+          .iter()
+          .fold(evals.clone(), |evals, &r| fold(&evals, r))[0]
+      })
+      .collect()
+  }
+
+  /// Value of the polynomial given the values of its mles
+  pub fn eval_from(&self, mle_evals: &[FieldElement]) -> FieldElement {
+    self.combine(|j| mle_evals[j])
+  }
+
+  pub fn eval(&self, point: &[FieldElement]) -> FieldElement {
+    self.eval_from(&self.mle_evals(point))
+  }
+
+  fn combine(&self, value: impl Fn(usize) -> FieldElement) -> FieldElement {
+    self.products.iter().fold(FE_ZERO, |acc, (c, mles)| {
+      acc + mles.iter().fold(*c, |p, &j| p * value(j))
+    })
+  }
+
+  /// Message of the round binding the lowest remaining variable
+  fn round_poly(&self) -> RoundPoly {
+    let degree = self.degree();
+    let mut evals = vec![FE_ZERO; degree + 1];
+    let mut values = vec![FE_ZERO; self.mles.len()];
+    let mut steps = vec![FE_ZERO; self.mles.len()];
+    for k in 0..1 << (self.num_vars - 1) {
+      for (j, mle) in self.mles.iter().enumerate() {
+        values[j] = mle[2 * k];
+        steps[j] = mle[2 * k + 1] - mle[2 * k];
+      }
+      for eval in evals.iter_mut() {
+        *eval = *eval + self.combine(|j| values[j]);
+        for (value, step) in values.iter_mut().zip(&steps) {
+          *value = *value + *step;
+        }
+      }
+    }
+    RoundPoly { evals }
+  }
+
+  fn bind(&mut self, r: FieldElement) {
+    for mle in self.mles.iter_mut() {
+      *mle = fold(mle, r);
+    }
+    self.num_vars -= 1;
+  }
+}
+
+/// Restriction of the multilinear polynomial of `evals` to `x_0 = r`
+fn fold(evals: &[FieldElement], r: FieldElement) -> Vec<FieldElement> {
+  evals
+    .chunks(2)
+    .map(|pair| pair[0] + r * (pair[1] - pair[0]))
+    .collect()
+}
+
+/// Univariate round message given by its values at `0, 1, ..., degree`
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundPoly {
+  pub evals: Vec<FieldElement>,
+}
+
+impl RoundPoly {
+  pub fn degree(&self) -> usize { self.evals.len().saturating_sub(1) }
+
+  /// Lagrange interpolation over the integer nodes
+  pub fn eval(&self, x: FieldElement) -> FieldElement {
+    let nodes: Vec<_> = (0..self.evals.len() as u64)
+      .map(FieldElement::from_real)
+      .collect();
+    if let Some(i) = nodes.iter().position(|&node| node == x) {
+      return self.evals[i];
+    }
+    nodes
+      .iter()
+      .zip(&self.evals)
+      .fold(FE_ZERO, |acc, (&node, &y)| {
+        let (num, den) = nodes
+          .iter()
+          .filter(|&&other| other != node)
+          .fold((FE_REAL_ONE, FE_REAL_ONE), |(num, den), &other| {
+            (num * (x - other), den * (node - other))
+          });
+        acc + y * num * den.inverse()
+      })
+  }
+}
+
+/// Round messages of a sumcheck, one per variable
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct SumcheckProof {
+  pub rounds: Vec<RoundPoly>,
+}
+
+impl ProofFormat for SumcheckProof {
+  const KIND: ProofKind = ProofKind::Sumcheck;
+
+  fn validate(&self) -> Result<(), ProofFormatError> {
+    if self.rounds.iter().any(|round| round.evals.len() < 2) {
+      return Err(ProofFormatError::Malformed(
+        "round message below degree one",
+      ));
+    }
+    Ok(())
+  }
+}
+
+/// Claim left by the sumcheck: the polynomial is `value` at `point`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SumcheckClaim {
+  pub point: Vec<FieldElement>,
+  pub value: FieldElement,
+}
+
+/// Proves the sum of `poly` over the boolean hypercube, the claimed sum is
+/// not absorbed. Returns the challenge point and the evaluations of the mles
+/// at it.
+pub fn prove<H: MerkleHasher>(
+  mut poly: SumOfProducts,
+  transcript: &mut Transcript<H>,
+) -> (SumcheckProof, Vec<FieldElement>, Vec<FieldElement>) {
+  let mut rounds = Vec::with_capacity(poly.num_vars);
+  let mut point = Vec::with_capacity(poly.num_vars);
+  while poly.num_vars > 0 {
+    let round = poly.round_poly();
+    transcript.absorb_field_elements(&round.evals);
+    let r = transcript.squeeze_field_element();
+    poly.bind(r);
+    rounds.push(round);
+    point.push(r);
+  }
+  let mle_evals = poly.mles.iter().map(|mle| mle[0]).collect();
+  (SumcheckProof { rounds }, point, mle_evals)
+}
+
+/// Checks `proof` for a claimed `sum` of a polynomial of `num_vars` variables
+/// and of [`SumOfProducts::degree`] `degree`, `None` if a round fails
+pub fn verify<H: MerkleHasher>(
+  num_vars: usize,
+  degree: usize,
+  sum: FieldElement,
+  proof: &SumcheckProof,
+  transcript: &mut Transcript<H>,
+) -> Option<SumcheckClaim> {
+  if proof.rounds.len() != num_vars {
+    eprintln!("Sumcheck fail, expected {} rounds", num_vars);
+    return None;
+  }
+
+  let mut value = sum;
+  let mut point = Vec::with_capacity(num_vars);
+  for (j, round) in proof.rounds.iter().enumerate() {
+    if round.evals.len() != degree.max(1) + 1 || round.evals[0] + round.evals[1] != value {
+      eprintln!("Sumcheck fail, round {}", j);
+      return None;
+    }
+    transcript.absorb_field_elements(&round.evals);
+    let r = transcript.squeeze_field_element();
+    value = round.eval(r);
+    point.push(r);
+  }
+  Some(SumcheckClaim { point, value })
+}
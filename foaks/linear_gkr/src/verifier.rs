@@ -1,14 +1,21 @@
 use std::time::Instant;
-use std::{io::Write, mem};
+use std::{io::Write, mem, path::Path};
 
-use global::constants::{FE_REAL_ONE, FE_ZERO, LOG_SLICE_NUMBER, SLICE_NUMBER};
-use infrastructure::my_hash::HashDigest;
-use infrastructure::rs_polynomial::{inverse_fast_fourier_transform, ScratchPad};
+use global::constants::{FE_REAL_ONE, FE_ZERO, LOG_SLICE_NUMBER};
+use infrastructure::{
+  my_hash::{HashDigest, MerkleHasher},
+  transcript::Transcript,
+};
 use poly_commitment::PolyCommitVerifier;
 use prime_field::FieldElement;
 
-use crate::prover::SumcheckInitArgs;
-use crate::{circuit_fast_track::LayeredCircuit, polynomial::QuadraticPoly, prover::ZkProver};
+use crate::circuit_file::{load_circuit, CircuitFileError};
+use crate::fft_gkr::verify_inverse_fft;
+use crate::gate::{GateRegistry, GateType};
+use crate::mask::verify_masks;
+use crate::proof::{absorb_quadratic_poly, FftProof, GkrProof, InputClaim, LayerProof};
+use crate::prover::{dfs_coef, dfs_for_public_eval};
+use crate::{circuit_fast_track::LayeredCircuit, polynomial::QuadraticPoly};
 
 #[derive(Default, Debug)]
 pub struct VerifierContext {
@@ -19,6 +26,8 @@ pub struct VerifierContext {
 
 #[derive(Default, Debug)]
 pub struct ZkVerifier {
+  /// Whether [`ZkVerifier::verify`] expects masked proofs, see [`crate::mask`]
+  pub masked: bool,
   pub proof_size: usize,
   pub v_time: f64,
   pub poly_verifier: PolyCommitVerifier,
@@ -48,6 +57,20 @@ pub struct ZkVerifier {
   pub ctx: VerifierContext,
 }
 
+/// Points and claimed values of the layer masks of a masked proof
+#[derive(Default)]
+struct MaskClaims {
+  points: Vec<Vec<FieldElement>>,
+  evals: Vec<FieldElement>,
+}
+
+#[derive(Default)]
+struct VerificationTimes {
+  verification: f64,
+  predicates_calc: f64,
+  verification_rdl: f64,
+}
+
 pub struct PredicateArgs<'a> {
   depth: usize,
   r_0: &'a Vec<FieldElement>,
@@ -56,6 +79,11 @@ pub struct PredicateArgs<'a> {
   r_v: &'a Vec<FieldElement>,
 }
 
+/// Sums of the wiring predicates of the gates of each type of a layer at the
+/// sumcheck point
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicates(Vec<FieldElement>);
+
 pub struct BetaInitArgs<'a> {
   depth: usize,
   alpha: FieldElement,
@@ -70,6 +98,39 @@ pub struct BetaInitArgs<'a> {
   one_minus_r_v: &'a Vec<FieldElement>,
 }
 
+impl Predicates {
+  fn new(registry: &GateRegistry) -> Self { Self(vec![FE_ZERO; registry.code_bound()]) }
+
+  fn add(&mut self, ty: GateType, value: FieldElement) {
+    let predicate = &mut self.0[ty.code()];
+    *predicate = *predicate + value;
+  }
+
+  pub fn get(&self, ty: GateType) -> FieldElement {
+    self.0.get(ty.code()).copied().unwrap_or(FE_ZERO)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (GateType, FieldElement)> + '_ {
+    self
+      .0
+      .iter()
+      .enumerate()
+      .filter_map(|(code, value)| GateType::from_code(code).map(|ty| (ty, *value)))
+  }
+
+  /// Value of the layer at the sumcheck point, without the direct relay gates
+  pub fn combine(
+    &self,
+    registry: &GateRegistry,
+    v_u: FieldElement,
+    v_v: FieldElement,
+  ) -> FieldElement {
+    self.iter().fold(FE_ZERO, |acc, (ty, predicate)| {
+      acc + registry.predicate_contribution(ty, predicate, v_u, v_v)
+    })
+  }
+}
+
 impl ZkVerifier {
   pub fn new() -> Self { Default::default() }
 
@@ -98,38 +159,34 @@ impl ZkVerifier {
     self.beta_u_block_second_half = vec![FE_ZERO; second_half_size];
   }
 
-  pub fn verify(
+  /// Reduces the claim on `output` to a claim on the input layer, `None` if
+  /// one of the sumchecks fails. With `masked`, every layer must be masked and the
+  /// claims left on the masks are pushed to `mask_claims`.
+  fn verify_layers<H: MerkleHasher>(
     &mut self,
-    bit_length: usize,
-    inputs: Vec<FieldElement>,
-    query_count: usize,
-    combined_codeword: Vec<FieldElement>,
-    q: Vec<usize>,
-  ) -> (bool, f64) {
-    // ZkProver initialization
-    let mut zk_prover = ZkProver::new();
-    zk_prover.init_array(bit_length, self.a_c.clone());
-    zk_prover.get_witness(inputs);
-
-    // Times
-    let (mut verification_time, mut predicates_calc_time, mut verification_rdl_time) =
-      (0.0, 0.0, 0.0);
-
-    //Below function is not implemented neither in virgo repo nor orion repo
-    //prime_field::init_random();
-
-    //Below function is not implemented neither in virgo repo nor orion repo
-    //self.prover.unwrap().proof_init();
-
-    let result = zk_prover.evaluate();
+    output: &[FieldElement],
+    layers: &[LayerProof],
+    transcript: &mut Transcript<H>,
+    times: &mut VerificationTimes,
+    mut mask_claims: Option<&mut MaskClaims>,
+  ) -> Option<InputClaim> {
     let mut alpha = FE_REAL_ONE;
     let mut beta = FE_ZERO;
 
-    //	random_oracle oracle; // Orion just declare the variable but dont use it
     let capacity = self.a_c.circuit[self.a_c.total_depth - 1].bit_length;
+    if output.len() != 1 << capacity || layers.len() != self.a_c.total_depth - 1 {
+      eprintln!("Verification fail, malformed proof");
+      return None;
+    }
+    // the wiring of a parallel layer is read from its first block only
+    if let Some(depth) = self.a_c.invalid_parallel_layer() {
+      eprintln!("Verification fail, layer {} is not parallel", depth);
+      return None;
+    }
 
-    let mut r_0 = generate_randomness(capacity);
-    let mut r_1 = generate_randomness(capacity);
+    transcript.absorb_field_elements(output);
+    let mut r_0 = transcript.squeeze_field_elements(capacity);
+    let mut r_1 = transcript.squeeze_field_elements(capacity);
 
     //todo: Refactor parallel
     let mut one_minus_r_0 = r_0.iter().map(|x| FE_REAL_ONE - *x).collect::<Vec<_>>();
@@ -138,101 +195,84 @@ impl ZkVerifier {
     let t_a = Instant::now();
 
     println!("Calc V_output(r)");
-    assert_eq!(result.len(), 1 << capacity);
-    let mut alpha_beta_sum = zk_prover.v_res(&one_minus_r_0, &r_0, result);
+    let mut alpha_beta_sum = v_res(&one_minus_r_0, &r_0, output);
 
     let time_span = t_a.elapsed();
     println!("    Time:: {}", time_span.as_secs_f64());
+    times.verification += time_span.as_secs_f64();
 
     let mut direct_relay_value: FieldElement;
 
-    for i in (1..=(self.a_c.total_depth - 1)).rev() {
+    for (layer, i) in layers.iter().zip((1..=(self.a_c.total_depth - 1)).rev()) {
       let previous_bit_length = self.a_c.circuit[i - 1].bit_length;
-
-      zk_prover.sumcheck_init(SumcheckInitArgs {
-        sumcheck_layer_id: i,
-        length_g: self.a_c.circuit[i].bit_length,
-        length_v: previous_bit_length,
-        alpha,
-        beta,
-        r_0: r_0.clone(),
-        r_1: r_1.clone(),
-        one_minus_r_0: one_minus_r_0.clone(),
-        one_minus_r_1: one_minus_r_1.clone(),
+      if layer.sumcheck_phase1.len() != previous_bit_length
+        || layer.sumcheck_phase2.len() != previous_bit_length
+      {
+        eprintln!("Verification fail, malformed sumcheck, circuit {}", i);
+        return None;
+      }
+      if layer.mask.is_some() != mask_claims.is_some() {
+        eprintln!("Verification fail, unexpected mask, circuit {}", i);
+        return None;
+      }
+      let rho = layer.mask.map(|mask| {
+        transcript.absorb_field_element(mask.sum);
+        let rho = transcript.squeeze_field_element();
+        alpha_beta_sum = alpha_beta_sum + rho * mask.sum;
+        rho
       });
 
-      zk_prover.sumcheck_phase1_init();
+      //next level random, one challenge per sumcheck round
+      let mut r_u = Vec::with_capacity(previous_bit_length);
+      let mut r_v = Vec::with_capacity(previous_bit_length);
 
-      let mut previous_random = FE_ZERO;
+      for (j, poly) in layer.sumcheck_phase1.iter().enumerate() {
+        self.proof_size += mem::size_of::<QuadraticPoly>();
+        absorb_quadratic_poly(transcript, poly);
+        let elem = transcript.squeeze_field_element();
+        r_u.push(elem);
+
+        if poly.eval(&FE_ZERO) + poly.eval(&FE_REAL_ONE) != alpha_beta_sum {
+          eprintln!(
+            "Verification fail, phase1, circuit {}, current bit {}",
+            i, j
+          );
+          return None;
+        }
 
-      //next level random
-      let r_u = generate_randomness(previous_bit_length);
-      let mut r_v = generate_randomness(previous_bit_length);
+        alpha_beta_sum = poly.eval(&elem);
+      }
+      let one_minus_r_u: Vec<FieldElement> = r_u.iter().map(|x| FE_REAL_ONE - *x).collect();
 
       direct_relay_value =
         alpha * self.direct_relay(i, &r_0, &r_u) + beta * self.direct_relay(i, &r_1, &r_u);
 
-      if i == 1 {
-        r_v.fill(FE_ZERO);
-      }
-
-      //V should test the maskR for two points, V does random linear combination of these points first
-      //let random_combine = generate_randomness(1)[0];       // never used
-
-      //Every time all one test to V, V needs to do a linear combination for security.
-      //let linear_combine = generate_randomness(1)[0]; // mem leak // never used
-
-      let one_minus_r_u: Vec<FieldElement> = r_u.iter().map(|x| FE_REAL_ONE - *x).collect();
-      let one_minus_r_v: Vec<FieldElement> = r_v.iter().map(|x| FE_REAL_ONE - *x).collect();
-
-      for (j, elem) in r_u.iter().enumerate() {
-        let poly = zk_prover.sumcheck_phase1_update(previous_random, j);
+      let v_u = layer.v_u;
+      let v_v = layer.v_v;
+      transcript.absorb_field_element(v_u);
+      for (j, poly) in layer.sumcheck_phase2.iter().enumerate() {
         self.proof_size += mem::size_of::<QuadraticPoly>();
-        previous_random = *elem;
-        let eval_zero = poly.eval(&FE_ZERO);
-        let eval_one = poly.eval(&FE_REAL_ONE);
-
-        assert_eq!(
-          eval_zero + eval_one,
-          alpha_beta_sum,
-          "Verification fail, phase1, circuit {}, current bit {}",
-          i,
-          j
-        );
-
-        alpha_beta_sum = poly.eval(elem);
-      }
-      zk_prover.v_u = zk_prover.v_mult_add[0].eval(previous_random);
-      zk_prover.sumcheck_phase2_init(&r_u, &one_minus_r_u);
-      let mut previous_random = FE_ZERO;
-      for (j, elem) in r_v.iter_mut().enumerate() {
-        if i == 1 {
-          *elem = FE_ZERO;
+        absorb_quadratic_poly(transcript, poly);
+        let elem = if i == 1 {
+          FE_ZERO
+        } else {
+          transcript.squeeze_field_element()
+        };
+        r_v.push(elem);
+
+        if poly.eval(&FE_ZERO) + poly.eval(&FE_REAL_ONE) + direct_relay_value * v_u
+          != alpha_beta_sum
+        {
+          eprintln!(
+            "Verification fail, phase2, circuit {}, current bit {}",
+            i, j
+          );
+          return None;
         }
-        let poly = zk_prover.sumcheck_phase2_update(previous_random, j);
-        self.proof_size += mem::size_of::<QuadraticPoly>();
-        //poly.c = poly.c; ???
-
-        previous_random = *elem;
-
-        assert_eq!(
-          poly.eval(&FE_ZERO) + poly.eval(&FE_REAL_ONE) + direct_relay_value * zk_prover.v_u,
-          alpha_beta_sum,
-          "Verification fail, phase2, circuit {}, current bit {}",
-          i,
-          j
-        );
 
-        alpha_beta_sum = poly.eval(elem) + direct_relay_value * zk_prover.v_u;
+        alpha_beta_sum = poly.eval(&elem) + direct_relay_value * v_u;
       }
-      //Add one more round for maskR
-      //quadratic_poly poly p->sumcheck_finalroundR(previous_random, C.current[i -
-      // 1].bit_length);
-
-      let final_claims = zk_prover.sumcheck_finalize(previous_random);
-
-      let v_u = final_claims.0;
-      let v_v = final_claims.1;
+      let one_minus_r_v: Vec<FieldElement> = r_v.iter().map(|x| FE_REAL_ONE - *x).collect();
 
       let predicates_calc = Instant::now();
       self.beta_init(BetaInitArgs {
@@ -259,47 +299,28 @@ impl ZkVerifier {
 
       let predicates_calc_span = predicates_calc.elapsed();
       if !self.a_c.circuit[i].is_parallel {
-        verification_rdl_time += predicates_calc_span.as_secs_f64();
+        times.verification_rdl += predicates_calc_span.as_secs_f64();
       }
-      verification_time += predicates_calc_span.as_secs_f64();
-      predicates_calc_time += predicates_calc_span.as_secs_f64();
-
-      let mult_value = predicates_value[1];
-      let add_value = predicates_value[0];
-      let not_value = predicates_value[6];
-      let minus_value = predicates_value[7];
-      let xor_value = predicates_value[8];
-      let naab_value = predicates_value[9];
-      let sum_value = predicates_value[5];
-      let relay_value = predicates_value[10];
-      let exp_sum_value = predicates_value[12];
-      let bit_test_value = predicates_value[13];
-      let custom_comb_value = predicates_value[14];
-
-      let mut r = r_u.clone();
-      r.reserve(r_v.len());
-      r.extend(r_v.clone());
-
-      if alpha_beta_sum
-        != (add_value * (v_u + v_v)
-          + mult_value * v_u * v_v
-          + not_value * (FE_REAL_ONE - v_u)
-          + minus_value * (v_u - v_v)
-          + xor_value * (v_u + v_v - FieldElement::from_real(2) * v_u * v_v)
-          + naab_value * (v_v - v_u * v_v)
-          + sum_value * v_u
-          + custom_comb_value * v_u
-          + relay_value * v_u
-          + exp_sum_value * v_u
-          + bit_test_value * (FE_REAL_ONE - v_v) * v_u)
-          + direct_relay_value * v_u
-      {
+      times.verification += predicates_calc_span.as_secs_f64();
+      times.predicates_calc += predicates_calc_span.as_secs_f64();
+
+      let mut layer_value = predicates_value.combine(&self.a_c.registry, v_u, v_v);
+      if let (Some(mask), Some(rho), Some(claims)) = (layer.mask, rho, mask_claims.as_deref_mut()) {
+        layer_value = layer_value + rho * mask.eval;
+        transcript.absorb_field_element(mask.eval);
+        claims
+          .points
+          .push([r_u.as_slice(), r_v.as_slice()].concat());
+        claims.evals.push(mask.eval);
+      }
+      if alpha_beta_sum != layer_value + direct_relay_value * v_u {
         eprintln!("Verification fail, semi final, circuit level {}", i,);
-        return (false, 0.0);
+        return None;
       }
 
-      alpha = FieldElement::new_random();
-      beta = FieldElement::new_random();
+      transcript.absorb_field_elements(&[v_u, v_v]);
+      alpha = transcript.squeeze_field_element();
+      beta = transcript.squeeze_field_element();
 
       if i != 1 {
         alpha_beta_sum = alpha * v_u + beta * v_v;
@@ -312,98 +333,203 @@ impl ZkVerifier {
       one_minus_r_1 = one_minus_r_v;
     }
 
-    println!("GKR Prove Time: {}", zk_prover.total_time);
-    let mut all_sum = vec![FE_ZERO; SLICE_NUMBER];
-    println!("GKR witness size: {}", self.a_c.circuit[0].gates.len());
-    let merkle_root_l = zk_prover
-      .poly_prover
-      .commit_private_array(&zk_prover.circuit_value[0], self.a_c.circuit[0].bit_length);
+    Some(InputClaim {
+      r_0,
+      one_minus_r_0,
+      value: alpha_beta_sum,
+    })
+  }
+
+  /// Checks `proof` against the claimed circuit `output`. `a_c` must hold the
+  /// circuit and [`ZkVerifier::init_array`] must have been called. In masked
+  /// mode the proof is checked against the circuit with its input mask, see
+  /// [`crate::mask`].
+  pub fn verify<H: MerkleHasher>(
+    &mut self,
+    output: &[FieldElement],
+    proof: &GkrProof,
+    transcript: &mut Transcript<H>,
+  ) -> bool {
+    if !self.masked {
+      return self.verify_committed(output, proof, transcript);
+    }
+
+    let masked = self.a_c.with_input_mask();
+    self.init_array(masked.max_bit_length());
+    let plain = mem::replace(&mut self.a_c, masked);
+    let result = self.verify_committed(output, proof, transcript);
+    self.a_c = plain;
+    result
+  }
+
+  fn verify_committed<H: MerkleHasher>(
+    &mut self,
+    output: &[FieldElement],
+    proof: &GkrProof,
+    transcript: &mut Transcript<H>,
+  ) -> bool {
+    self.proof_size = 0;
+    let mut times = VerificationTimes::default();
+
+    let mut mask_claims = match (self.masked, &proof.mask_proof) {
+      (true, Some(mask_proof)) => {
+        transcript.absorb_digest(mask_proof.merkle_root_l);
+        Some(MaskClaims::default())
+      }
+      (false, None) => None,
+      _ => {
+        eprintln!("Verification fail, mask proof");
+        return false;
+      }
+    };
+    // the witness is committed before any challenge is drawn
+    transcript.absorb_digest(proof.input_proof.merkle_root_l);
+
+    let InputClaim {
+      r_0,
+      one_minus_r_0,
+      value,
+    } = match self.verify_layers(
+      output,
+      &proof.layers,
+      transcript,
+      &mut times,
+      mask_claims.as_mut(),
+    ) {
+      Some(claim) => claim,
+      None => return false,
+    };
+
+    if let (Some(claims), Some(mask_proof)) = (&mask_claims, &proof.mask_proof) {
+      if !verify_masks(mask_proof, &claims.points, &claims.evals, transcript) {
+        eprintln!("Verification fail, mask vpd");
+        return false;
+      }
+    }
+
+    let input_proof = &proof.input_proof;
+    let input_bit_length = self.a_c.circuit[0].bit_length;
+    // the slice sums of the input opening add up to the claim left on the input layer
+    let input_sum = input_proof.all_sum.iter().fold(FE_ZERO, |acc, x| acc + *x);
+    if input_sum != value {
+      eprintln!("Verification fail, input sum");
+      return false;
+    }
+
     self.ctx.q_eval_real = vec![FE_ZERO; self.a_c.circuit[0].gates.len()];
-    self.dfs_for_public_eval(
-      0usize,
+    dfs_for_public_eval(
+      &mut self.ctx.q_eval_real,
+      0,
       FE_REAL_ONE,
       &r_0,
       &one_minus_r_0,
-      self.a_c.circuit[0].bit_length,
+      input_bit_length,
       0,
     );
 
-    let merkle_root_h = zk_prover.poly_prover.commit_public_array(
-      &self.ctx.q_eval_real,
-      self.a_c.circuit[0].bit_length,
-      alpha_beta_sum,
-      &mut all_sum,
-    );
-
     self.proof_size += 2 * mem::size_of::<HashDigest>();
     self.vpd_randomness = r_0.clone();
     self.one_minus_vpd_randomness = one_minus_r_0.clone();
-    self.poly_verifier.pc_prover = zk_prover.poly_prover.clone();
 
-    let public_array = self.public_array_prepare(
+    let fft_t0 = Instant::now();
+    let public_array = match self.public_array_prepare(
       &r_0,
-      one_minus_r_0.clone(),
-      self.a_c.circuit[0].bit_length,
-      &mut zk_prover.poly_prover.scratch_pad,
-    );
+      &one_minus_r_0,
+      input_bit_length,
+      &proof.fft_proof,
+      transcript,
+    ) {
+      Some(public_array) => public_array,
+      None => {
+        eprintln!("Verification fail, public array fft");
+        return false;
+      }
+    };
+    times.verification += fft_t0.elapsed().as_secs_f64();
 
-    let (v_time, proof_size, p_time, input_0_verify) = self.poly_verifier.verify_poly_commitment(
-      &all_sum,
-      self.a_c.circuit[0].bit_length,
+    let (v_time, proof_size, input_0_verify) = self.poly_verifier.verify_poly_commitment(
+      input_bit_length,
       &public_array,
-      merkle_root_l,
-      merkle_root_h,
+      input_proof,
+      transcript,
     );
 
-    verification_time += v_time;
+    times.verification += v_time;
     self.proof_size += proof_size;
-    zk_prover.total_time += p_time;
-
-    zk_prover.poly_prover.total_time_pc_p += self.poly_verifier.pc_prover.total_time_pc_p;
 
     if !input_0_verify {
       eprintln!("Verification fail, input vpd");
-      return (false, 0.0);
+      return false;
     }
 
     println!("Verification pass");
-    println!("Prove time {}", zk_prover.total_time);
-    println!("Verification rdl time {}", verification_rdl_time);
+    println!("Verification rdl time {}", times.verification_rdl);
     println!(
       "Verification time {}",
-      verification_time - verification_rdl_time
+      times.verification - times.verification_rdl
     );
-    self.v_time = verification_time - verification_rdl_time;
+    self.v_time = times.verification - times.verification_rdl;
     println!("Proof size (bytes) {}", self.proof_size);
 
     let output_path = "log.txt";
 
     ZkVerifier::write_file(
       &String::from(output_path),
-      zk_prover.total_time,
-      verification_time,
-      predicates_calc_time,
-      verification_rdl_time,
+      times.verification,
+      times.predicates_calc,
+      times.verification_rdl,
       self.proof_size,
     )
     .expect("Error while writing file");
 
-    // Code added from tensor_product()
-    let sample_t0 = Instant::now();
-    for i in 0..query_count {
-      assert_eq!(
-        zk_prover.circuit_value[zk_prover.a_c.total_depth - 1][i],
-        combined_codeword[q[i]],
-      );
+    true
+  }
+
+  /// Checks `layers` for a circuit whose `input` is known, the claim left on
+  /// the input layer is evaluated directly. The layers must not be masked.
+  pub fn verify_public_input<H: MerkleHasher>(
+    &mut self,
+    input: &[FieldElement],
+    output: &[FieldElement],
+    layers: &[LayerProof],
+    transcript: &mut Transcript<H>,
+  ) -> bool {
+    self.proof_size = 0;
+    let mut times = VerificationTimes::default();
+    if input.len() != self.a_c.circuit[0].gates.len() {
+      return false;
     }
-    let time_span = sample_t0.elapsed().as_secs_f64();
 
-    (true, time_span)
+    let InputClaim {
+      r_0,
+      one_minus_r_0,
+      value,
+    } = match self.verify_layers(output, layers, transcript, &mut times, None) {
+      Some(claim) => claim,
+      None => return false,
+    };
+
+    let mut eq = vec![FE_ZERO; input.len()];
+    dfs_for_public_eval(
+      &mut eq,
+      0,
+      FE_REAL_ONE,
+      &r_0,
+      &one_minus_r_0,
+      self.a_c.circuit[0].bit_length,
+      0,
+    );
+    let input_value = eq
+      .iter()
+      .zip(input.iter())
+      .fold(FE_ZERO, |acc, (x, y)| acc + *x * *y);
+
+    self.v_time = times.verification - times.verification_rdl;
+    input_value == value
   }
 
   pub fn write_file(
     output_path: &str,
-    total_time: f64,
     verification_time: f64,
     predicates_calc_time: f64,
     verification_rdl_time: f64,
@@ -418,19 +544,23 @@ impl ZkVerifier {
     let mut result_file = std::fs::File::create(full_path)?;
     writeln!(
       result_file,
-      "{} {} {} {} {}",
-      total_time, verification_time, predicates_calc_time, verification_rdl_time, proof_size
+      "{} {} {} {}",
+      verification_time, predicates_calc_time, verification_rdl_time, proof_size
     )?;
     Ok(())
   }
 
-  pub fn public_array_prepare(
+  /// Computes the coefficients of the public array from the evaluations of
+  /// the equality function at `r`, the inverse FFT is taken from the prover
+  /// and checked with `fft_proof`.
+  pub fn public_array_prepare<H: MerkleHasher>(
     &mut self,
-    r: &Vec<FieldElement>,
-    one_minus_r: Vec<FieldElement>,
+    r: &[FieldElement],
+    one_minus_r: &[FieldElement],
     log_length: usize,
-    scratch_pad: &mut ScratchPad,
-  ) -> Vec<FieldElement> {
+    fft_proof: &FftProof,
+    transcript: &mut Transcript<H>,
+  ) -> Option<Vec<FieldElement>> {
     self.ctx.q_eval_verifier = vec![FE_ZERO; 1 << (log_length - LOG_SLICE_NUMBER)];
     self.ctx.q_ratio = vec![FE_ZERO; 1 << LOG_SLICE_NUMBER];
 
@@ -442,8 +572,8 @@ impl ZkVerifier {
       one_minus_r[log_length - LOG_SLICE_NUMBER..].to_vec(),
       0,
     );
-    Self::dfs_coef(
-      self,
+    dfs_coef(
+      &mut self.ctx.q_eval_verifier,
       0,
       FE_REAL_ONE,
       r,
@@ -451,17 +581,14 @@ impl ZkVerifier {
       0,
       log_length - LOG_SLICE_NUMBER,
     );
-    let mut q_coef_verifier = vec![FE_ZERO; 1 << (log_length - LOG_SLICE_NUMBER)];
-
-    inverse_fast_fourier_transform(
-      scratch_pad,
-      &self.ctx.q_eval_verifier,
-      1 << (log_length - LOG_SLICE_NUMBER),
-      1 << (log_length - LOG_SLICE_NUMBER),
-      FieldElement::get_root_of_unity(log_length - LOG_SLICE_NUMBER)
-        .expect("Failed to retrieve root of unity"),
-      &mut q_coef_verifier,
-    );
+
+    let (fft_verified, fft_proof_size) =
+      verify_inverse_fft(&self.ctx.q_eval_verifier, fft_proof, transcript);
+    self.proof_size += fft_proof_size;
+    if !fft_verified {
+      return None;
+    }
+    let q_coef_verifier = &fft_proof.coef;
 
     let mut q_coef_arr = vec![FE_ZERO; 1 << log_length];
     let coef_slice_size = 1 << (log_length - LOG_SLICE_NUMBER);
@@ -474,40 +601,7 @@ impl ZkVerifier {
         );
       }
     }
-    q_coef_arr
-  }
-
-  pub fn dfs_coef(
-    &mut self,
-    dep: usize,
-    val: FieldElement,
-    r: &Vec<FieldElement>,
-    one_minus_r: Vec<FieldElement>,
-    pos: usize,
-    r_len: usize,
-  ) {
-    if dep == r_len {
-      self.ctx.q_eval_verifier[pos] = val;
-    } else {
-      Self::dfs_coef(
-        self,
-        dep + 1,
-        val * one_minus_r[r_len - 1 - dep],
-        r,
-        one_minus_r.clone(),
-        pos << 1,
-        r_len,
-      );
-      Self::dfs_coef(
-        self,
-        dep + 1,
-        val * r[r_len - 1 - dep],
-        r,
-        one_minus_r,
-        pos << 1 | 1,
-        r_len,
-      );
-    }
+    Some(q_coef_arr)
   }
 
   pub fn dfs_ratio(
@@ -540,39 +634,6 @@ impl ZkVerifier {
     }
   }
 
-  pub fn dfs_for_public_eval(
-    &mut self,
-    dep: usize,
-    val: FieldElement,
-    r_0: &Vec<FieldElement>,
-    one_minus_r_0: &Vec<FieldElement>,
-    r_0_len: usize,
-    pos: usize,
-  ) {
-    if dep == r_0_len {
-      self.ctx.q_eval_real[pos] = val;
-    } else {
-      Self::dfs_for_public_eval(
-        self,
-        dep + 1,
-        val * (*one_minus_r_0)[r_0_len - 1 - dep],
-        r_0,
-        one_minus_r_0,
-        r_0_len,
-        pos << 1,
-      );
-      Self::dfs_for_public_eval(
-        self,
-        dep + 1,
-        val * r_0[r_0_len - 1 - dep],
-        r_0,
-        one_minus_r_0,
-        r_0_len,
-        pos << 1 | 1,
-      );
-    }
-  }
-
   pub fn direct_relay(
     &mut self,
     depth: usize,
@@ -695,89 +756,36 @@ impl ZkVerifier {
       }
     }
 
-    if self.a_c.circuit[depth].is_parallel {
-      self.beta_g_r0_block_first_half[0] = alpha;
-      self.beta_g_r1_block_first_half[0] = beta;
-      self.beta_g_r0_block_second_half[0] = FE_REAL_ONE;
-      self.beta_g_r1_block_second_half[0] = FE_REAL_ONE;
-
-      let first_half_len = self.a_c.circuit[depth - 1].log_block_size / 2;
-      let second_half_len = self.a_c.circuit[depth - 1].log_block_size - first_half_len;
-
-      for i in 0..first_half_len {
-        let r0 = r_0[i + first_half_len];
-        let r1 = r_1[i + first_half_len];
-        let or0 = one_minus_r_0[i + first_half_len];
-        let or1 = one_minus_r_1[i + first_half_len];
-
-        for j in 0..(1 << i) {
-          self.beta_g_r0_block_first_half[j | (1 << i)] = self.beta_g_r0_block_first_half[j] * r0;
-          self.beta_g_r1_block_first_half[j | (1 << i)] = self.beta_g_r1_block_first_half[j] * r1;
-        }
-
-        for j in 0..(1 << i) {
-          self.beta_g_r0_block_first_half[j] = self.beta_g_r0_block_first_half[j] * or0;
-          self.beta_g_r1_block_first_half[j] = self.beta_g_r1_block_first_half[j] * or1;
-        }
-      }
-
-      for i in 0..second_half_len {
-        let r0 = r_0[i + first_half_len];
-        let r1 = r_1[i + first_half_len];
-        let or0 = one_minus_r_0[i + first_half_len];
-        let or1 = one_minus_r_1[i + first_half_len];
-
-        for j in 0..(1 << i) {
-          self.beta_g_r0_block_second_half[j | (1 << i)] = self.beta_g_r0_block_second_half[j] * r0;
-          self.beta_g_r1_block_second_half[j | (1 << i)] = self.beta_g_r1_block_second_half[j] * r1;
-        }
-
-        for j in 0..(1 << 1) {
-          self.beta_g_r0_block_second_half[j] = self.beta_g_r0_block_second_half[j] * or0;
-          self.beta_g_r1_block_second_half[j] = self.beta_g_r1_block_second_half[j] * or1;
-        }
-      }
-
-      self.beta_u_block_first_half[0] = FE_REAL_ONE;
-      self.beta_v_block_first_half[0] = FE_REAL_ONE;
-      self.beta_u_block_second_half[0] = FE_REAL_ONE;
-      self.beta_v_block_second_half[0] = FE_REAL_ONE;
-      let first_half_len = self.a_c.circuit[depth - 1].bit_length / 2;
-      let second_half_len = self.a_c.circuit[depth - 1].bit_length - first_half_len;
-
-      for i in 0..first_half_len {
-        let ru = r_u[i];
-        let rv = r_v[i];
-        let oru = one_minus_r_u[i];
-        let orv = one_minus_r_v[i];
-
-        for j in 0..(1 << i) {
-          self.beta_u_block_first_half[j | (1 << i)] = self.beta_u_block_first_half[j] * ru;
-          self.beta_v_block_first_half[j | (1 << i)] = self.beta_v_block_first_half[j] * rv;
-        }
-
-        for j in 0..(1 << i) {
-          self.beta_u_block_first_half[j] = self.beta_u_block_first_half[j] * oru;
-          self.beta_v_block_first_half[j] = self.beta_v_block_first_half[j] * orv;
-        }
-      }
-
-      for i in 0..second_half_len {
-        let ru = r_u[i + first_half_len];
-        let rv = r_v[i + first_half_len];
-        let oru = one_minus_r_u[i + first_half_len];
-        let orv = one_minus_r_v[i + first_half_len];
-
-        for j in 0..(1 << i) {
-          self.beta_u_block_second_half[j | (1 << i)] = self.beta_u_block_second_half[j] * ru;
-          self.beta_v_block_second_half[j | (1 << i)] = self.beta_v_block_second_half[j] * rv;
-        }
-
-        for j in 0..(1 << i) {
-          self.beta_u_block_second_half[j] = self.beta_u_block_second_half[j] * oru;
-          self.beta_v_block_second_half[j] = self.beta_v_block_second_half[j] * orv;
-        }
-      }
+    let layer = &self.a_c.circuit[depth];
+    if layer.is_parallel {
+      // the equality functions on the gates of the first blocks, the block
+      // indices are the high bits
+      let log_previous_block_size = self.a_c.circuit[depth - 1].bit_length - layer.log_repeat_num;
+      let (g_len, uv_len) = (layer.log_block_size, log_previous_block_size);
+      fill_beta_halves(
+        &mut self.beta_g_r0_block_first_half,
+        &mut self.beta_g_r0_block_second_half,
+        alpha,
+        &r_0[..g_len],
+      );
+      fill_beta_halves(
+        &mut self.beta_g_r1_block_first_half,
+        &mut self.beta_g_r1_block_second_half,
+        beta,
+        &r_1[..g_len],
+      );
+      fill_beta_halves(
+        &mut self.beta_u_block_first_half,
+        &mut self.beta_u_block_second_half,
+        FE_REAL_ONE,
+        &r_u[..uv_len],
+      );
+      fill_beta_halves(
+        &mut self.beta_v_block_first_half,
+        &mut self.beta_v_block_second_half,
+        FE_REAL_ONE,
+        &r_v[..uv_len],
+      );
     }
   }
 
@@ -790,446 +798,203 @@ impl ZkVerifier {
       r_u,
       r_v,
     }: PredicateArgs,
-  ) -> Vec<FieldElement> {
-    let gate_type_count = 15;
-
-    let mut ret_para = vec![FE_ZERO; gate_type_count];
-    let mut ret = vec![FE_ZERO; gate_type_count];
+  ) -> Predicates {
+    let registry = &self.a_c.registry;
+    let mut ret = Predicates::new(registry);
 
     if depth == 1 {
       return ret;
     }
 
-    let debug_mode = false;
-    if self.a_c.circuit[depth].is_parallel {
-      let first_half_g = self.a_c.circuit[depth].log_block_size / 2;
-      let first_half_uv = self.a_c.circuit[depth - 1].log_block_size / 2;
-
-      let mut one_block_alpha = vec![FE_ZERO; gate_type_count];
-      let mut one_block_beta = vec![FE_ZERO; gate_type_count];
-
-      assert_eq!(
-        (1 << self.a_c.circuit[depth].log_block_size),
-        self.a_c.circuit[depth].block_size
-      );
+    let layer = &self.a_c.circuit[depth];
+    let previous = &self.a_c.circuit[depth - 1];
+    if layer.is_parallel {
+      let log_previous_block_size = previous.bit_length - layer.log_repeat_num;
+      let first_half_g = layer.log_block_size / 2;
+      let first_half_uv = log_previous_block_size / 2;
+      let beta_g_alpha = |g: usize| {
+        self.beta_g_r0_block_first_half[g & ((1 << first_half_g) - 1)]
+          * self.beta_g_r0_block_second_half[g >> first_half_g]
+      };
+      let beta_g_beta = |g: usize| {
+        self.beta_g_r1_block_first_half[g & ((1 << first_half_g) - 1)]
+          * self.beta_g_r1_block_second_half[g >> first_half_g]
+      };
+      let beta_u = |u: usize| {
+        self.beta_u_block_first_half[u & ((1 << first_half_uv) - 1)]
+          * self.beta_u_block_second_half[u >> first_half_uv]
+      };
+      let beta_v = |v: usize| {
+        self.beta_v_block_first_half[v & ((1 << first_half_uv) - 1)]
+          * self.beta_v_block_second_half[v >> first_half_uv]
+      };
+
+      // sums over the blocks of the equality functions on the block indices,
+      // with u and v in the same block as g or with v = 0
+      let (mut same_alpha, mut same_beta) = (FE_REAL_ONE, FE_REAL_ONE);
+      let (mut v0_alpha, mut v0_beta) = (FE_REAL_ONE, FE_REAL_ONE);
+      for j in 0..layer.log_repeat_num {
+        let (r0, r1) = (r_0[layer.log_block_size + j], r_1[layer.log_block_size + j]);
+        let (ru, rv) = (
+          r_u[log_previous_block_size + j],
+          r_v[log_previous_block_size + j],
+        );
+        let (or0, or1, oru, orv) = (
+          FE_REAL_ONE - r0,
+          FE_REAL_ONE - r1,
+          FE_REAL_ONE - ru,
+          FE_REAL_ONE - rv,
+        );
+        same_alpha = same_alpha * (r0 * ru * rv + or0 * oru * orv);
+        same_beta = same_beta * (r1 * ru * rv + or1 * oru * orv);
+        v0_alpha = v0_alpha * orv * (r0 * ru + or0 * oru);
+        v0_beta = v0_beta * orv * (r1 * ru + or1 * oru);
+      }
 
-      for i in 0..self.a_c.circuit[depth].log_block_size {
-        let mut g = i;
-        let mut u = self.a_c.circuit[depth].gates[i].u;
-        let mut v = self.a_c.circuit[depth].gates[i].v;
-        g &= (1 << self.a_c.circuit[depth].log_block_size) - 1;
-        u &= (1 << self.a_c.circuit[depth - 1].log_block_size) - 1;
-        v &= (1 << self.a_c.circuit[depth - 1].log_block_size) - 1;
-
-        match self.a_c.circuit[depth].gates[i].ty {
-          0 => {
-            let g_first_half = g & ((1 << first_half_g) - 1);
-            let g_second_half = g >> first_half_g;
-            let u_first_half = u & ((1 << first_half_uv) - 1);
-            let u_second_half = u >> first_half_uv;
-            let v_first_half = v & ((1 << first_half_uv) - 1);
-            let v_second_half = v >> first_half_uv;
-            let uv_value = (self.beta_u_block_first_half[u_first_half]
-              * self.beta_u_block_second_half[u_second_half])
-              * (self.beta_v_block_first_half[v_first_half]
-                * self.beta_v_block_second_half[v_second_half]);
-            one_block_alpha[0] = one_block_alpha[0]
-              + (self.beta_g_r0_block_first_half[g_first_half]
-                * self.beta_g_r0_block_second_half[g_second_half])
-                * uv_value;
-            one_block_beta[0] = one_block_beta[0]
-              + (self.beta_g_r1_block_first_half[g_first_half]
-                * self.beta_g_r1_block_second_half[g_second_half])
-                * uv_value;
-          }
-          1 => {
-            let g_first_half = g & ((1 << first_half_g) - 1);
-            let g_second_half = g >> first_half_g;
-            let u_first_half = u & ((1 << first_half_uv) - 1);
-            let u_second_half = u >> first_half_uv;
-            let v_first_half = v & ((1 << first_half_uv) - 1);
-            let v_second_half = v >> first_half_uv;
-            let uv_value = (self.beta_u_block_first_half[u_first_half]
-              * self.beta_u_block_second_half[u_second_half])
-              * (self.beta_v_block_first_half[v_first_half]
-                * self.beta_v_block_second_half[v_second_half]);
-            one_block_alpha[1] = one_block_alpha[1]
-              + (self.beta_g_r0_block_first_half[g_first_half]
-                * self.beta_g_r0_block_second_half[g_second_half])
-                * uv_value;
-            one_block_beta[1] = one_block_beta[1]
-              + (self.beta_g_r1_block_first_half[g_first_half]
-                * self.beta_g_r1_block_second_half[g_second_half])
-                * uv_value;
-          }
-          2 => {}
-          3 => {}
-          4 => {}
-          5 => {
-            let g_first_half = g & ((1 << first_half_g) - 1);
-            let g_second_half = g >> first_half_g;
-
-            let beta_g_val_alpha = self.beta_g_r0_block_first_half[g_first_half]
-              * self.beta_g_r0_block_second_half[g_second_half];
-            let beta_g_val_beta = self.beta_g_r1_block_first_half[g_first_half]
-              * self.beta_g_r1_block_second_half[g_second_half];
-            let beta_v_0 = self.beta_v_block_first_half[0] * self.beta_v_block_second_half[0];
+      for (g, gate) in layer.gates[..layer.block_size].iter().enumerate() {
+        let (ty, u, v) = (gate.ty, gate.u, gate.v);
+        let beta_g_v_0 = (beta_g_alpha(g) * v0_alpha + beta_g_beta(g) * v0_beta) * beta_v(0);
+        match ty {
+          GateType::Sum => {
             for j in u..v {
-              let u_first_half = j & ((1 << first_half_uv) - 1);
-              let u_second_half = j >> first_half_uv;
-              one_block_alpha[5] = one_block_alpha[5]
-                + beta_g_val_alpha
-                  * beta_v_0
-                  * (self.beta_u_block_first_half[u_first_half]
-                    * self.beta_u_block_second_half[u_second_half]);
-              one_block_beta[5] = one_block_beta[5]
-                + beta_g_val_beta
-                  * beta_v_0
-                  * (self.beta_u_block_first_half[u_first_half]
-                    * self.beta_u_block_second_half[u_second_half]);
+              ret.add(ty, beta_g_v_0 * beta_u(j));
             }
           }
-          12 => {
-            let g_first_half = g & ((1 << first_half_g) - 1);
-            let g_second_half = g >> first_half_g;
-
-            let beta_g_val_alpha = self.beta_g_r0_block_first_half[g_first_half]
-              * self.beta_g_r0_block_second_half[g_second_half];
-            let beta_g_val_beta = self.beta_g_r1_block_first_half[g_first_half]
-              * self.beta_g_r1_block_second_half[g_second_half];
-            let mut beta_v_0 = self.beta_v_block_first_half[0] * self.beta_v_block_second_half[0];
+          GateType::ExpSum => {
+            let mut beta_g_v_0 = beta_g_v_0;
             for j in u..=v {
-              let u_first_half = j & ((1 << first_half_uv) - 1);
-              let u_second_half = j >> first_half_uv;
-              one_block_alpha[12] = one_block_alpha[12]
-                + beta_g_val_alpha
-                  * beta_v_0
-                  * (self.beta_u_block_first_half[u_first_half]
-                    * self.beta_u_block_second_half[u_second_half]);
-              one_block_beta[12] = one_block_beta[12]
-                + beta_g_val_beta
-                  * beta_v_0
-                  * (self.beta_u_block_first_half[u_first_half]
-                    * self.beta_u_block_second_half[u_second_half]);
-
-              beta_v_0 = beta_v_0 + beta_v_0;
+              ret.add(ty, beta_g_v_0 * beta_u(j));
+              beta_g_v_0 = beta_g_v_0 + beta_g_v_0;
             }
           }
-          6 => {
-            let g_first_half = g & ((1 << first_half_g) - 1);
-            let g_second_half = g >> first_half_g;
-            let u_first_half = u & ((1 << first_half_uv) - 1);
-            let u_second_half = u >> first_half_uv;
-            let v_first_half = v & ((1 << first_half_uv) - 1);
-            let v_second_half = v >> first_half_uv;
-            let uv_value = (self.beta_u_block_first_half[u_first_half]
-              * self.beta_u_block_second_half[u_second_half])
-              * (self.beta_v_block_first_half[v_first_half]
-                * self.beta_v_block_second_half[v_second_half]);
-            one_block_alpha[6] = one_block_alpha[6]
-              + (self.beta_g_r0_block_first_half[g_first_half]
-                * self.beta_g_r0_block_second_half[g_second_half])
-                * uv_value;
-            one_block_beta[6] = one_block_beta[6]
-              + (self.beta_g_r1_block_first_half[g_first_half]
-                * self.beta_g_r1_block_second_half[g_second_half])
-                * uv_value;
-          }
-          7 => {
-            let g_first_half = g & ((1 << first_half_g) - 1);
-            let g_second_half = g >> first_half_g;
-            let u_first_half = u & ((1 << first_half_uv) - 1);
-            let u_second_half = u >> first_half_uv;
-            let v_first_half = v & ((1 << first_half_uv) - 1);
-            let v_second_half = v >> first_half_uv;
-            let uv_value = (self.beta_u_block_first_half[u_first_half]
-              * self.beta_u_block_second_half[u_second_half])
-              * (self.beta_v_block_first_half[v_first_half]
-                * self.beta_v_block_second_half[v_second_half]);
-            one_block_alpha[7] = one_block_alpha[7]
-              + (self.beta_g_r0_block_first_half[g_first_half]
-                * self.beta_g_r0_block_second_half[g_second_half])
-                * uv_value;
-            one_block_beta[7] = one_block_beta[7]
-              + (self.beta_g_r1_block_first_half[g_first_half]
-                * self.beta_g_r1_block_second_half[g_second_half])
-                * uv_value;
-          }
-          8 => {
-            let g_first_half = g & ((1 << first_half_g) - 1);
-            let g_second_half = g >> first_half_g;
-            let u_first_half = u & ((1 << first_half_uv) - 1);
-            let u_second_half = u >> first_half_uv;
-            let v_first_half = v & ((1 << first_half_uv) - 1);
-            let v_second_half = v >> first_half_uv;
-            let uv_value = (self.beta_u_block_first_half[u_first_half]
-              * self.beta_u_block_second_half[u_second_half])
-              * (self.beta_v_block_first_half[v_first_half]
-                * self.beta_v_block_second_half[v_second_half]);
-            one_block_alpha[8] = one_block_alpha[8]
-              + (self.beta_g_r0_block_first_half[g_first_half]
-                * self.beta_g_r0_block_second_half[g_second_half])
-                * uv_value;
-            one_block_beta[8] = one_block_beta[8]
-              + (self.beta_g_r1_block_first_half[g_first_half]
-                * self.beta_g_r1_block_second_half[g_second_half])
-                * uv_value;
-          }
-          9 => {
-            let g_first_half = g & ((1 << first_half_g) - 1);
-            let g_second_half = g >> first_half_g;
-            let u_first_half = u & ((1 << first_half_uv) - 1);
-            let u_second_half = u >> first_half_uv;
-            let v_first_half = v & ((1 << first_half_uv) - 1);
-            let v_second_half = v >> first_half_uv;
-            let uv_value = (self.beta_u_block_first_half[u_first_half]
-              * self.beta_u_block_second_half[u_second_half])
-              * (self.beta_v_block_first_half[v_first_half]
-                * self.beta_v_block_second_half[v_second_half]);
-            one_block_alpha[9] = one_block_alpha[9]
-              + (self.beta_g_r0_block_first_half[g_first_half]
-                * self.beta_g_r0_block_second_half[g_second_half])
-                * uv_value;
-            one_block_beta[9] = one_block_beta[9]
-              + (self.beta_g_r1_block_first_half[g_first_half]
-                * self.beta_g_r1_block_second_half[g_second_half])
-                * uv_value;
-          }
-          10 => {
-            let g_first_half = g & ((1 << first_half_g) - 1);
-            let g_second_half = g >> first_half_g;
-            let u_first_half = u & ((1 << first_half_uv) - 1);
-            let u_second_half = u >> first_half_uv;
-            let v_first_half = v & ((1 << first_half_uv) - 1);
-            let v_second_half = v >> first_half_uv;
-            let uv_value = (self.beta_u_block_first_half[u_first_half]
-              * self.beta_u_block_second_half[u_second_half])
-              * (self.beta_v_block_first_half[v_first_half]
-                * self.beta_v_block_second_half[v_second_half]);
-            one_block_alpha[10] = one_block_alpha[10]
-              + (self.beta_g_r0_block_first_half[g_first_half]
-                * self.beta_g_r0_block_second_half[g_second_half])
-                * uv_value;
-            one_block_beta[10] = one_block_beta[10]
-              + (self.beta_g_r1_block_first_half[g_first_half]
-                * self.beta_g_r1_block_second_half[g_second_half])
-                * uv_value;
-          }
-          13 => {
-            let g_first_half = g & ((1 << first_half_g) - 1);
-            let g_second_half = g >> first_half_g;
-            let u_first_half = u & ((1 << first_half_uv) - 1);
-            let u_second_half = u >> first_half_uv;
-            let v_first_half = v & ((1 << first_half_uv) - 1);
-            let v_second_half = v >> first_half_uv;
-            let uv_value = (self.beta_u_block_first_half[u_first_half]
-              * self.beta_u_block_second_half[u_second_half])
-              * (self.beta_v_block_first_half[v_first_half]
-                * self.beta_v_block_second_half[v_second_half]);
-            one_block_alpha[13] = one_block_alpha[13]
-              + (self.beta_g_r0_block_first_half[g_first_half]
-                * self.beta_g_r0_block_second_half[g_second_half])
-                * uv_value;
-            one_block_beta[13] = one_block_beta[13]
-              + (self.beta_g_r1_block_first_half[g_first_half]
-                * self.beta_g_r1_block_second_half[g_second_half])
-                * uv_value;
-          }
-          _ => {}
-        }
-      }
-      for i in 0..self.a_c.circuit[depth].repeat_num {
-        let mut prefix_alpha = FE_REAL_ONE;
-        let mut prefix_beta = FE_REAL_ONE;
-        let mut prefix_alpha_v0 = FE_REAL_ONE;
-        let mut prefix_beta_v0 = FE_REAL_ONE;
-
-        for j in 0..self.a_c.circuit[depth].log_repeat_num {
-          if (i >> j) > 0 {
-            let uv_value = r_u[j + self.a_c.circuit[depth - 1].log_block_size]
-              * r_v[j + self.a_c.circuit[depth - 1].log_block_size];
-            prefix_alpha =
-              prefix_alpha * r_0[j + self.a_c.circuit[depth].log_block_size] * uv_value;
-            prefix_beta = prefix_beta * r_1[j + self.a_c.circuit[depth].log_block_size] * uv_value;
-
-            let uv_value_v0 = r_u[j + self.a_c.circuit[depth - 1].log_block_size]
-              * (FE_REAL_ONE - r_v[j + self.a_c.circuit[depth - 1].log_block_size]);
-
-            prefix_alpha_v0 =
-              prefix_alpha_v0 * r_0[j + self.a_c.circuit[depth].log_block_size] * uv_value_v0;
-            prefix_beta_v0 =
-              prefix_beta_v0 * r_1[j + self.a_c.circuit[depth].log_block_size] * uv_value_v0;
-          } else {
-            let uv_value = (FE_REAL_ONE - r_u[j + self.a_c.circuit[depth - 1].log_block_size])
-              * (FE_REAL_ONE - r_v[j + self.a_c.circuit[depth - 1].log_block_size]);
-            prefix_alpha = prefix_alpha
-              * (FE_REAL_ONE - r_0[j + self.a_c.circuit[depth].log_block_size])
-              * uv_value;
-            prefix_beta = prefix_beta
-              * (FE_REAL_ONE - r_1[j + self.a_c.circuit[depth].log_block_size])
-              * uv_value;
+          GateType::CustomLinearComb => {
+            for (&src, &weight) in gate
+              .src
+              .iter()
+              .zip(&gate.weight)
+              .take(gate.parameter_length)
+            {
+              ret.add(ty, beta_g_v_0 * beta_u(src) * weight);
+            }
           }
+          _ => match registry.rule(ty) {
+            // gates reading a single input are wired with v = 0 in every block
+            Some(rule) if rule.is_unary() => ret.add(ty, beta_g_v_0 * beta_u(u)),
+            Some(_) => {
+              let beta_g = beta_g_alpha(g) * same_alpha + beta_g_beta(g) * same_beta;
+              ret.add(ty, beta_g * beta_u(u) * beta_v(v))
+            }
+            None => {}
+          },
         }
-
-        (0..gate_type_count).for_each(|j| {
-          let update_alpha = if j == 6 || j == 10 || j == 5 || j == 12 {
-            prefix_alpha_v0
-          } else {
-            prefix_alpha
-          };
-
-          let update_beta = if j == 6 || j == 10 || j == 5 || j == 12 {
-            prefix_beta_v0
-          } else {
-            prefix_beta
-          };
-
-          ret_para[j] =
-            ret_para[j] + update_alpha * one_block_alpha[j] + update_beta * one_block_beta[j];
-        });
       }
-      if !debug_mode {
-        ret = ret_para.clone();
-      }
-    }
-    if !self.a_c.circuit[depth].is_parallel || debug_mode {
-      let first_half_g = self.a_c.circuit[depth].bit_length / 2;
-      let first_half_uv = self.a_c.circuit[depth - 1].bit_length / 2;
-
-      let mut tmp_u_val = vec![FE_ZERO; self.a_c.circuit[depth - 1].gates.len()];
-      let zero_v = self.beta_v_first_half[0] * self.beta_v_second_half[0];
-      let mut relay_set = false;
-      for i in 0..(self.a_c.circuit[depth].gates.len()) {
-        let g = i;
-        let u = self.a_c.circuit[depth].gates[i].u;
-        let v = self.a_c.circuit[depth].gates[i].v;
-
-        let g_first_half = g & ((1 << first_half_g) - 1);
-        let g_second_half = g >> first_half_g;
-        let u_first_half = u & ((1 << first_half_uv) - 1);
-        let u_second_half = u >> first_half_uv;
-        let v_first_half = v & ((1 << first_half_uv) - 1);
-        let v_second_half = v >> first_half_uv;
-
-        let ty = self.a_c.circuit[depth].gates[i].ty;
-
+    } else {
+      let first_half_g = layer.bit_length / 2;
+      let first_half_uv = previous.bit_length / 2;
+      let beta_g = |g: usize| {
+        let (first, second) = (g & ((1 << first_half_g) - 1), g >> first_half_g);
+        self.beta_g_r0_first_half[first] * self.beta_g_r0_second_half[second]
+          + self.beta_g_r1_first_half[first] * self.beta_g_r1_second_half[second]
+      };
+      let beta_u = |u: usize| {
+        self.beta_u_first_half[u & ((1 << first_half_uv) - 1)]
+          * self.beta_u_second_half[u >> first_half_uv]
+      };
+      let beta_v = |v: usize| {
+        self.beta_v_first_half[v & ((1 << first_half_uv) - 1)]
+          * self.beta_v_second_half[v >> first_half_uv]
+      };
+
+      for (g, gate) in layer.gates.iter().enumerate() {
+        let (ty, u, v) = (gate.ty, gate.u, gate.v);
         match ty {
-          0 | 1 | 6 | 7 | 8 | 9 | 13 => {
-            ret[ty] = ret[ty]
-              + (self.beta_g_r0_first_half[g_first_half]
-                * self.beta_g_r0_second_half[g_second_half]
-                + self.beta_g_r1_first_half[g_first_half]
-                  * self.beta_g_r1_second_half[g_second_half])
-                * (self.beta_u_first_half[u_first_half] * self.beta_u_second_half[u_second_half])
-                * (self.beta_v_first_half[v_first_half] * self.beta_v_second_half[v_second_half]);
-          }
-          2 => {}
-          3 => {}
-          4 => {}
-          5 => {
-            let beta_g_val = self.beta_g_r0_first_half[g_first_half]
-              * self.beta_g_r0_second_half[g_second_half]
-              + self.beta_g_r1_first_half[g_first_half] * self.beta_g_r1_second_half[g_second_half];
-            let beta_v_0 = self.beta_v_first_half[0] * self.beta_v_second_half[0];
+          GateType::Sum => {
+            let beta_g_v_0 = beta_g(g) * beta_v(0);
             for j in u..v {
-              let u_first_half = j & ((1 << first_half_uv) - 1);
-              let u_second_half = j >> first_half_uv;
-              ret[5] = ret[5]
-                + beta_g_val
-                  * beta_v_0
-                  * (self.beta_u_first_half[u_first_half] * self.beta_u_second_half[u_second_half]);
+              ret.add(ty, beta_g_v_0 * beta_u(j));
             }
           }
-          12 => {
-            let g_first_half = g & ((1 << first_half_g) - 1);
-            let g_second_half = g >> first_half_g;
-
-            let beta_g_val = self.beta_g_r0_first_half[g_first_half]
-              * self.beta_g_r0_second_half[g_second_half]
-              + self.beta_g_r1_first_half[g_first_half] * self.beta_g_r1_second_half[g_second_half];
-            let mut beta_v_0 = self.beta_v_first_half[0] * self.beta_v_second_half[0];
+          GateType::ExpSum => {
+            let mut beta_g_v_0 = beta_g(g) * beta_v(0);
             for j in u..=v {
-              let u_first_half = j & ((1 << first_half_uv) - 1);
-              let u_second_half = j >> first_half_uv;
-              ret[12] = ret[12]
-                + beta_g_val
-                  * beta_v_0
-                  * (self.beta_u_first_half[u_first_half] * self.beta_u_second_half[u_second_half]);
-              beta_v_0 = beta_v_0 + beta_v_0;
+              ret.add(ty, beta_g_v_0 * beta_u(j));
+              beta_g_v_0 = beta_g_v_0 + beta_g_v_0;
             }
           }
-          14 => {
-            let g_first_half = g & ((1 << first_half_g) - 1);
-            let g_second_half = g >> first_half_g;
-
-            let beta_g_val = self.beta_g_r0_first_half[g_first_half]
-              * self.beta_g_r0_second_half[g_second_half]
-              + self.beta_g_r1_first_half[g_first_half] * self.beta_g_r1_second_half[g_second_half];
-            let beta_v_0 = self.beta_v_first_half[0] * self.beta_v_second_half[0];
-            for j in 0..self.a_c.circuit[depth].gates[i].parameter_length {
-              let src = self.a_c.circuit[depth].gates[i].src[j];
-              let u_first_half = src & ((1 << first_half_uv) - 1);
-              let u_second_half = src >> first_half_uv;
-              let weight = self.a_c.circuit[depth].gates[i].weight[j];
-              ret[14] = ret[14]
-                + beta_g_val
-                  * beta_v_0
-                  * (self.beta_u_first_half[u_first_half] * self.beta_u_second_half[u_second_half])
-                  * weight;
+          GateType::CustomLinearComb => {
+            let beta_g_v_0 = beta_g(g) * beta_v(0);
+            for (&src, &weight) in gate
+              .src
+              .iter()
+              .zip(&gate.weight)
+              .take(gate.parameter_length)
+            {
+              ret.add(ty, beta_g_v_0 * beta_u(src) * weight);
             }
           }
-          10 => {
-            if !relay_set {
-              tmp_u_val = vec![FE_ZERO; self.a_c.circuit[depth - 1].gates.len()];
-
-              for (i, tmp_item) in tmp_u_val
-                .iter_mut()
-                .enumerate()
-                .take(self.a_c.circuit[depth - 1].gates.len())
-              {
-                let u_first_half = i & ((1 << first_half_uv) - 1);
-                let u_second_half = i >> first_half_uv;
-                *tmp_item =
-                  self.beta_u_first_half[u_first_half] * self.beta_u_second_half[u_second_half];
-              }
-
-              relay_set = true;
-            }
-            let g_first_half = g & ((1 << first_half_g) - 1);
-            let g_second_half = g >> first_half_g;
-            ret[10] = ret[10]
-              + (self.beta_g_r0_first_half[g_first_half]
-                * self.beta_g_r0_second_half[g_second_half]
-                + self.beta_g_r1_first_half[g_first_half]
-                  * self.beta_g_r1_second_half[g_second_half])
-                * tmp_u_val[u];
-          }
+          _ if registry.rule(ty).is_some() => ret.add(ty, beta_g(g) * beta_u(u) * beta_v(v)),
           _ => {}
         }
       }
-      ret[10] = ret[10] * zero_v;
     }
-    ret
-      .iter()
-      .zip(ret_para.iter())
-      .for_each(|(ret_val, ret_para_val)| {
-        if self.a_c.circuit[depth].is_parallel {
-          assert_eq!(ret_val, ret_para_val);
-        }
-      });
 
     ret
   }
 
-  pub fn v_in() {}
-
-  //Never used
-  pub fn read_r1cs() {}
+  /// Loads the circuit to verify from the files of the Virgo and Orion
+  /// reference implementations, see [`crate::circuit_file`]
+  pub fn read_circuit(
+    &mut self,
+    circuit_path: &Path,
+    meta_path: Option<&Path>,
+  ) -> Result<(), CircuitFileError> {
+    self.a_c = load_circuit(circuit_path, meta_path, GateRegistry::new())?;
+    self.init_array(self.a_c.max_bit_length());
+    Ok(())
+  }
 
   //Never used, original code is all commented in Orion, empty in Virgo
   pub fn self_inner_product_test() {} //Never used, implemented only in Virgo, empty in Orion
 }
 
+/// Fills the two halves of the table of `scale` times the equality function at
+/// `r`, the first half on the low bits
+fn fill_beta_halves(
+  first_half: &mut [FieldElement],
+  second_half: &mut [FieldElement],
+  scale: FieldElement,
+  r: &[FieldElement],
+) {
+  let (low, high) = r.split_at(r.len() / 2);
+  first_half[0] = scale;
+  second_half[0] = FE_REAL_ONE;
+  for (table, r) in [(first_half, low), (second_half, high)] {
+    for (i, &r) in r.iter().enumerate() {
+      for j in 0..(1 << i) {
+        table[j | (1 << i)] = table[j] * r;
+        table[j] = table[j] * (FE_REAL_ONE - r);
+      }
+    }
+  }
+}
+
+/// Evaluates the multilinear extension of the circuit output at `r_0`
+fn v_res(
+  one_minus_r_0: &[FieldElement],
+  r_0: &[FieldElement],
+  output: &[FieldElement],
+) -> FieldElement {
+  let mut output = output.to_vec();
+  let mut output_size = output.len();
+  for (i, elem) in r_0.iter().enumerate() {
+    for j in 0..(output_size >> 1) {
+      output[j] = output[j << 1] * one_minus_r_0[i] + output[j << 1 | 1] * *elem;
+    }
+    output_size >>= 1;
+  }
+  output[0]
+}
+
 pub fn generate_randomness(size: usize) -> Vec<FieldElement> {
   (0..size).map(|_| FieldElement::new_random()).collect()
 }
@@ -0,0 +1,82 @@
+use infrastructure::{my_hash::Sha256Hasher, transcript::Transcript};
+use linear_gkr::{
+  circuit_builder::CircuitBuilder,
+  gate::{BinaryRule, GateType},
+  prover::ZkProver,
+  verifier::ZkVerifier,
+};
+use prime_field::FieldElement;
+
+fn fe(x: u64) -> FieldElement { FieldElement::from_real(x) }
+
+#[test]
+fn lays_out_and_proves_a_dag() {
+  let mut builder = CircuitBuilder::new();
+  let triple = builder.register(
+    "triple",
+    BinaryRule {
+      constant: FieldElement::zero(),
+      u: fe(3),
+      v: FieldElement::zero(),
+      uv: FieldElement::zero(),
+    },
+  );
+  let [a, b, c]: [_; 3] = builder.inputs(3).try_into().unwrap();
+  let ab = builder.mul(a, b);
+  let sum = builder.add(ab, c);
+  let comb = builder.linear_combination(&[(sum, fe(2)), (a, fe(3))]);
+  let tripled = builder.gate(triple, ab, c);
+  // never used by an output
+  builder.sub(a, c);
+  builder.output(comb);
+  builder.output(a);
+  builder.output(tripled);
+  let a_c = builder.build();
+
+  assert_eq!(a_c.total_depth, 5);
+  for (layer, size) in [4, 4, 4, 4, 4].into_iter().enumerate() {
+    assert_eq!(a_c.circuit[layer].gates.len(), size);
+    assert_eq!(1 << a_c.circuit[layer].bit_length, size);
+  }
+  // c is relayed to the layer of a * b, the triple gate only reads u
+  let layer_2: Vec<_> = a_c.circuit[2]
+    .gates
+    .iter()
+    .map(|g| (g.ty, g.u, g.v))
+    .collect();
+  assert!(layer_2.contains(&(GateType::Mult, 0, 1)));
+  assert!(layer_2.contains(&(GateType::Relay, 2, 0)));
+  assert!(!layer_2.contains(&(GateType::Minus, 0, 2)));
+  assert!(a_c.circuit[3]
+    .gates
+    .iter()
+    .any(|g| g.ty == triple && g.v == 0));
+
+  let input = [fe(3), fe(5), fe(7)];
+  let mut witness = input.to_vec();
+  witness.resize(a_c.circuit[0].gates.len(), FieldElement::zero());
+  let mut prover = ZkProver::new();
+  prover.init_array(a_c.max_bit_length(), a_c.clone());
+  prover.get_witness(witness.clone());
+  let output = prover.evaluate();
+  assert_eq!(
+    output,
+    [
+      fe(2) * (fe(15) + fe(7)) + fe(9),
+      fe(3),
+      fe(45),
+      FieldElement::zero(),
+    ]
+  );
+  let layers = prover.prove_public_input(&mut Transcript::<Sha256Hasher>::new());
+
+  let mut verifier = ZkVerifier::new();
+  verifier.init_array(a_c.max_bit_length());
+  verifier.a_c = a_c;
+  assert!(verifier.verify_public_input(
+    &witness,
+    &output,
+    &layers,
+    &mut Transcript::<Sha256Hasher>::new()
+  ));
+}
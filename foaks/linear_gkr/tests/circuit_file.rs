@@ -0,0 +1,181 @@
+use infrastructure::{my_hash::Sha256Hasher, transcript::Transcript};
+use linear_gkr::{
+  circuit_file::{read_circuit, write_circuit, write_meta, CircuitFileError},
+  fft_gkr::inverse_fft_circuit,
+  gate::{BinaryRule, GateRegistry, GateType},
+  prover::ZkProver,
+  verifier::ZkVerifier,
+};
+use prime_field::FieldElement;
+
+// the example of the readme, the output layer is padded with a dummy gate
+const EXAMPLE: &str = "3
+4 3 0 1 1 3 1 1 1 3 2 1 1 3 3 1 1
+2 0 0 0 1 1 1 2 3
+1 1 0 0 1
+";
+const PADDED_EXAMPLE: &str = "3
+4 3 0 1 1 3 1 1 1 3 2 1 1 3 3 1 1
+2 0 0 0 1 1 1 2 3
+2 1 0 0 1 2 1 0 0
+";
+
+fn read(circuit: &str) -> Result<(), CircuitFileError> {
+  read_circuit(circuit, None, GateRegistry::new()).map(|_| ())
+}
+
+#[test]
+fn proves_a_loaded_circuit() {
+  let a_c = read_circuit(EXAMPLE, None, GateRegistry::new()).unwrap();
+  assert_eq!(a_c.total_depth, 4);
+  assert_eq!(a_c.circuit[1].gates[3].ty, GateType::DirectRelay);
+  assert_eq!(write_circuit(&a_c).unwrap(), PADDED_EXAMPLE);
+
+  let mut prover = ZkProver::new();
+  prover.init_array(a_c.max_bit_length(), a_c.clone());
+  prover.get_witness(a_c.inputs.clone());
+  let output = prover.evaluate();
+  assert_eq!(output, [FieldElement::from_real(2), FieldElement::zero()]);
+  let layers = prover.prove_public_input(&mut Transcript::<Sha256Hasher>::new());
+
+  let mut verifier = ZkVerifier::new();
+  verifier.init_array(a_c.max_bit_length());
+  let inputs = a_c.inputs.clone();
+  verifier.a_c = a_c;
+  assert!(verifier.verify_public_input(
+    &inputs,
+    &output,
+    &layers,
+    &mut Transcript::<Sha256Hasher>::new()
+  ));
+}
+
+#[test]
+fn round_trips_meta_and_registered_gates() {
+  let circuit = "3
+2 3 0 5 0 3 1 6 0
+4 6 0 0 0 15 1 0 0 6 2 1 0 15 3 1 1
+4 0 0 0 1 10 1 2 0 16 2 3 0 2 3 0 0
+";
+  let meta = "0 0 0 0 0
+1 2 2 1 1
+0 4 1 2 0
+";
+  let mut registry = GateRegistry::new();
+  let rule = |u: u64, uv: u64| BinaryRule {
+    constant: FieldElement::zero(),
+    u: FieldElement::from_real(u),
+    v: FieldElement::zero(),
+    uv: FieldElement::from_real(uv),
+  };
+  registry.register("mult_add", rule(1, 1));
+  registry.register("triple", rule(3, 0));
+
+  let a_c = read_circuit(circuit, Some(meta), registry.clone()).unwrap();
+  assert_eq!(a_c.circuit[2].gates[1].ty, GateType::Registered(0));
+  assert!(a_c.circuit[2].is_parallel);
+  assert_eq!(write_circuit(&a_c).unwrap(), circuit);
+  assert_eq!(write_meta(&a_c), meta);
+
+  assert!(matches!(
+    read_circuit(circuit, None, GateRegistry::new()),
+    Err(CircuitFileError::UnknownGateType {
+      layer: 1,
+      gate: 1,
+      ty: 15
+    })
+  ));
+  assert!(matches!(
+    read_circuit(
+      circuit,
+      Some("0 0 0 0 0\n1 2 2 1 1\n1 4 2 2 1\n"),
+      registry.clone()
+    ),
+    Err(CircuitFileError::InvalidMeta { layer: 2 })
+  ));
+  // the blocks of a parallel layer must have the same gates
+  let other_blocks = circuit.replace("6 2 1 0", "9 2 1 0");
+  assert!(matches!(
+    read_circuit(&other_blocks, Some(meta), registry),
+    Err(CircuitFileError::InvalidMeta { layer: 1 })
+  ));
+}
+
+#[test]
+fn reports_malformed_circuits() {
+  assert!(matches!(
+    read("3\n4 3 0 1 1 3 1 1 1 3 2 1 1 3 3 1 1\n2 0 0 0 1 1 1 2 4\n1 1 0 0 1"),
+    Err(CircuitFileError::WireOutOfRange {
+      layer: 1,
+      gate: 1,
+      wire: 4,
+      bound: 4
+    })
+  ));
+  assert!(matches!(
+    read("2\n2 3 0 1 0 3 1 1 0\n2 0 1 0 1 1 0 0 1"),
+    Err(CircuitFileError::GateOutOfOrder {
+      layer: 1,
+      expected: 0,
+      found: 1
+    })
+  ));
+  assert!(matches!(
+    read("2\n2 3 0 1 0 3 1 1 0\n2 0 0 0 1 11 1 0 1"),
+    Err(CircuitFileError::UnknownGateType {
+      layer: 1,
+      gate: 1,
+      ty: 11
+    })
+  ));
+  assert!(matches!(
+    read("2\n2 3 0 1 0 0 1 1 0\n1 0 0 0 1"),
+    Err(CircuitFileError::MisplacedGate {
+      layer: 0,
+      gate: 1,
+      ty: GateType::Add
+    })
+  ));
+  assert!(matches!(
+    read("2\n2 3 0 1 0 3 1 1 0\n1 5 0 1 1"),
+    Err(CircuitFileError::WireOutOfRange {
+      layer: 1,
+      gate: 0,
+      wire: 1,
+      ..
+    })
+  ));
+  assert!(matches!(
+    read("2\n2 3 0 1 0 3 1 1 0\n1 13 0 0 1"),
+    Err(CircuitFileError::BitTestWires { layer: 1, gate: 0 })
+  ));
+  assert!(matches!(read("2\n2 3 0 1 0 3 1 1 0\n1 0 0 0 1\n"), Ok(())));
+  assert!(matches!(
+    read("2\n2 3 0 1 0 3 1 1 0\n1 0 0 0 1 7"),
+    Err(CircuitFileError::TrailingData)
+  ));
+  assert!(matches!(
+    read("2\n2 3 0 1 0 3 1 1 0\n1 0 0 0"),
+    Err(CircuitFileError::UnexpectedEnd)
+  ));
+  assert!(matches!(
+    read("2\n2 3 0 1 0 3 1 1 0\n1 0 0 -1 1"),
+    Err(CircuitFileError::InvalidNumber(_))
+  ));
+
+  // linear combinations have no encoding
+  let mut fft = inverse_fft_circuit(2);
+  assert!(matches!(
+    write_circuit(&fft),
+    Err(CircuitFileError::UnsupportedGate {
+      layer: 1,
+      gate: 0,
+      ty: GateType::CustomLinearComb
+    })
+  ));
+  fft.circuit.remove(1);
+  assert!(matches!(
+    write_circuit(&fft),
+    Err(CircuitFileError::UnsupportedLayout)
+  ));
+}
@@ -0,0 +1,77 @@
+use infrastructure::{
+  my_hash::Sha256Hasher,
+  rs_polynomial::{inverse_fast_fourier_transform, ScratchPad},
+  transcript::Transcript,
+};
+use linear_gkr::{
+  fft_gkr::{inverse_fft_circuit, prove_inverse_fft, verify_inverse_fft},
+  proof::FftProof,
+  prover::ZkProver,
+};
+use prime_field::FieldElement;
+
+const LOG_ORDER: usize = 5;
+const ORDER: usize = 1 << LOG_ORDER;
+
+fn evals() -> Vec<FieldElement> {
+  (0..ORDER as u64)
+    .map(|i| FieldElement::new(i * i + 3, 2 * i + 1))
+    .collect()
+}
+
+fn verify(evals: &[FieldElement], proof: &FftProof) -> bool {
+  verify_inverse_fft(evals, proof, &mut Transcript::<Sha256Hasher>::new()).0
+}
+
+#[test]
+fn circuit_computes_the_inverse_fft() {
+  for log_order in 1..=LOG_ORDER {
+    let order = 1 << log_order;
+    let evals = &evals()[..order];
+    let mut expected = vec![FieldElement::zero(); order];
+    let rou = FieldElement::get_root_of_unity(log_order).unwrap();
+    let mut scratch_pad = ScratchPad::from_order(order);
+    inverse_fast_fourier_transform(&mut scratch_pad, evals, order, order, rou, &mut expected);
+
+    let mut prover = ZkProver::new();
+    prover.init_array(log_order, inverse_fft_circuit(log_order));
+    prover.get_witness(evals.to_vec());
+    assert_eq!(prover.evaluate(), expected, "order {}", order);
+  }
+}
+
+#[test]
+fn proves_the_coefficients() {
+  let evals = evals();
+  let proof = prove_inverse_fft(&evals, &mut Transcript::<Sha256Hasher>::new());
+  assert_eq!(proof.coef.len(), ORDER);
+  assert!(verify(&evals, &proof));
+
+  let mut other = evals;
+  other[3] = other[3] + FieldElement::real_one();
+  assert!(!verify(&other, &proof));
+}
+
+#[test]
+fn tampered_proofs_are_rejected() {
+  let evals = evals();
+  let proof = prove_inverse_fft(&evals, &mut Transcript::<Sha256Hasher>::new());
+
+  let mut tampered = proof.clone();
+  tampered.coef[1] = tampered.coef[1] + FieldElement::real_one();
+  assert!(!verify(&evals, &tampered));
+
+  let mut tampered = proof.clone();
+  tampered.coef.pop();
+  assert!(!verify(&evals, &tampered));
+
+  let mut tampered = proof.clone();
+  let round = &mut tampered.layers[1].sumcheck_phase1[0];
+  round.a = round.a + FieldElement::real_one();
+  assert!(!verify(&evals, &tampered));
+
+  let mut tampered = proof;
+  let claim = &mut tampered.layers[0].v_u;
+  *claim = *claim + FieldElement::real_one();
+  assert!(!verify(&evals, &tampered));
+}
@@ -0,0 +1,159 @@
+use infrastructure::{my_hash::Sha256Hasher, transcript::Transcript};
+use linear_gkr::{
+  circuit_fast_track::{Gate, Layer, LayeredCircuit},
+  gate::{BinaryRule, GateType},
+  prover::ZkProver,
+  verifier::ZkVerifier,
+};
+use prime_field::FieldElement;
+
+fn fe(x: u64) -> FieldElement { FieldElement::from_real(x) }
+
+fn layer(bit_length: usize, gates: Vec<Gate>) -> Layer {
+  assert_eq!(gates.len(), 1 << bit_length);
+  Layer {
+    bit_length,
+    gates,
+    ..Default::default()
+  }
+}
+
+/// Every built-in gate type and two registered ones, one binary and one unary
+fn circuit(square_rule: BinaryRule) -> LayeredCircuit {
+  let mut a_c = LayeredCircuit::default();
+  let affine = a_c.registry.register(
+    "affine",
+    BinaryRule {
+      constant: fe(3),
+      u: fe(2),
+      v: FieldElement::zero() - fe(1),
+      uv: fe(5),
+    },
+  );
+  let square = a_c.registry.register("square", square_rule);
+
+  a_c.circuit = vec![
+    layer(2, vec![Gate::from_params(GateType::Input, 0, 0); 4]),
+    layer(
+      2,
+      (0..4)
+        .map(|i| Gate::from_params(GateType::DirectRelay, i, 0))
+        .collect(),
+    ),
+    layer(
+      3,
+      vec![
+        Gate::from_params(GateType::Add, 0, 1),
+        Gate::from_params(GateType::Mult, 1, 2),
+        Gate::from_params(GateType::Not, 0, 0),
+        Gate::from_params(GateType::Minus, 2, 3),
+        Gate::from_params(GateType::Xor, 0, 1),
+        Gate::from_params(GateType::Naab, 1, 3),
+        Gate::from_params(GateType::Relay, 3, 0),
+        Gate::from_params(GateType::BitTest, 2, 2),
+      ],
+    ),
+    layer(
+      3,
+      vec![
+        Gate::from_params(GateType::Sum, 0, 4),
+        Gate::from_params(GateType::ExpSum, 1, 3),
+        Gate {
+          ty: GateType::CustomLinearComb,
+          src: vec![0, 5, 7],
+          weight: vec![fe(2), fe(9), fe(4)],
+          parameter_length: 3,
+          ..Default::default()
+        },
+        Gate::from_params(affine, 6, 7),
+        Gate::from_params(square, 4, 0),
+        Gate::from_params(GateType::Mult, 4, 5),
+        Gate::new(),
+        Gate::from_params(GateType::Relay, 7, 0),
+      ],
+    ),
+  ];
+  a_c.total_depth = a_c.circuit.len();
+  a_c
+}
+
+fn double() -> BinaryRule {
+  BinaryRule {
+    constant: FieldElement::zero(),
+    u: fe(2),
+    v: FieldElement::zero(),
+    uv: FieldElement::zero(),
+  }
+}
+
+fn prove(a_c: LayeredCircuit, input: &[FieldElement]) -> (Vec<FieldElement>, ZkProver) {
+  let mut prover = ZkProver::new();
+  prover.init_array(3, a_c);
+  prover.get_witness(input.to_vec());
+  let output = prover.evaluate();
+  (output, prover)
+}
+
+#[test]
+fn evaluates_every_gate_type() {
+  let input = [fe(3), fe(5), fe(7), fe(11)];
+  let (output, prover) = prove(circuit(double()), &input);
+
+  let [a, b, c, d] = input;
+  let layer_2 = [
+    a + b,
+    b * c,
+    fe(1) - a,
+    c - d,
+    a + b - fe(2) * a * b,
+    d - b * d,
+    d,
+    c - c * c,
+  ];
+  assert_eq!(prover.circuit_value[2], layer_2);
+  let x = layer_2;
+  assert_eq!(
+    output,
+    [
+      x[0] + x[1] + x[2] + x[3],
+      x[1] + fe(2) * x[2] + fe(4) * x[3],
+      fe(2) * x[0] + fe(9) * x[5] + fe(4) * x[7],
+      fe(3) + fe(2) * x[6] - x[7] + fe(5) * x[6] * x[7],
+      fe(2) * x[4],
+      x[4] * x[5],
+      FieldElement::zero(),
+      x[7],
+    ]
+  );
+}
+
+#[test]
+fn proves_every_gate_type() {
+  let input = [fe(3), fe(5), fe(7), fe(11)];
+  let (output, mut prover) = prove(circuit(double()), &input);
+  let layers = prover.prove_public_input(&mut Transcript::<Sha256Hasher>::new());
+
+  let mut verifier = ZkVerifier::new();
+  verifier.a_c = circuit(double());
+  verifier.init_array(3);
+  assert!(verifier.verify_public_input(
+    &input,
+    &output,
+    &layers,
+    &mut Transcript::<Sha256Hasher>::new()
+  ));
+
+  // the rule of a registered gate is part of the circuit
+  let mut verifier = ZkVerifier::new();
+  verifier.a_c = circuit(BinaryRule {
+    u: fe(3),
+    ..double()
+  });
+  verifier.init_array(3);
+  assert!(!verifier.verify_public_input(
+    &input,
+    &output,
+    &layers,
+    &mut Transcript::<Sha256Hasher>::new()
+  ));
+}
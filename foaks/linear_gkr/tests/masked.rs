@@ -0,0 +1,126 @@
+use infrastructure::{my_hash::Sha256Hasher, transcript::Transcript};
+use linear_gkr::{
+  circuit_builder::CircuitBuilder, circuit_fast_track::LayeredCircuit, proof::GkrProof,
+  prover::ZkProver, verifier::ZkVerifier,
+};
+use prime_field::FieldElement;
+
+const INPUTS: usize = 128;
+
+/// Products of adjacent inputs and their sum, the input is large enough to be
+/// committed
+fn circuit() -> LayeredCircuit {
+  let mut builder = CircuitBuilder::new();
+  let inputs = builder.inputs(INPUTS);
+  let products: Vec<_> = inputs
+    .chunks(2)
+    .map(|pair| builder.mul(pair[0], pair[1]))
+    .collect();
+  let fourth = products[3];
+  let mut sums = products;
+  while sums.len() > 1 {
+    sums = sums
+      .chunks(2)
+      .map(|pair| builder.add(pair[0], pair[1]))
+      .collect();
+  }
+  builder.output(sums[0]);
+  builder.output(fourth);
+  builder.build()
+}
+
+fn witness(offset: u64) -> Vec<FieldElement> {
+  (0..INPUTS as u64)
+    .map(|x| FieldElement::from_real(x * x + offset))
+    .collect()
+}
+
+fn prove(a_c: &LayeredCircuit, masked: bool) -> (Vec<FieldElement>, GkrProof) {
+  prove_witness(a_c, masked, witness(1))
+}
+
+fn prove_witness(
+  a_c: &LayeredCircuit,
+  masked: bool,
+  witness: Vec<FieldElement>,
+) -> (Vec<FieldElement>, GkrProof) {
+  let mut prover = ZkProver::new();
+  prover.masked = masked;
+  prover.init_array(a_c.max_bit_length(), a_c.clone());
+  prover.get_witness(witness);
+  let output = prover.evaluate();
+  let proof = prover.prove(&mut Transcript::<Sha256Hasher>::new());
+  (output, proof)
+}
+
+fn verify(a_c: &LayeredCircuit, masked: bool, output: &[FieldElement], proof: &GkrProof) -> bool {
+  let mut verifier = ZkVerifier::new();
+  verifier.masked = masked;
+  verifier.a_c = a_c.clone();
+  verifier.init_array(a_c.max_bit_length());
+  verifier.verify(output, proof, &mut Transcript::<Sha256Hasher>::new())
+}
+
+#[test]
+fn masked_proofs_verify() {
+  let a_c = circuit();
+  let (output, proof) = prove(&a_c, true);
+  assert!(proof.mask_proof.is_some());
+  assert!(proof.layers.iter().all(|layer| layer.mask.is_some()));
+  assert!(verify(&a_c, true, &output, &proof));
+
+  let (plain_output, plain_proof) = prove(&a_c, false);
+  assert_eq!(plain_output, output);
+  assert!(verify(&a_c, false, &plain_output, &plain_proof));
+}
+
+#[test]
+fn masked_mode_must_match() {
+  let a_c = circuit();
+  let (output, proof) = prove(&a_c, true);
+  assert!(!verify(&a_c, false, &output, &proof));
+  let (output, proof) = prove(&a_c, false);
+  assert!(!verify(&a_c, true, &output, &proof));
+}
+
+#[test]
+fn swapped_input_commitments_are_rejected() {
+  let a_c = circuit();
+  for masked in [false, true] {
+    let (output, mut proof) = prove(&a_c, masked);
+    let (_, other) = prove_witness(&a_c, masked, witness(2));
+    assert_ne!(
+      proof.input_proof.merkle_root_l,
+      other.input_proof.merkle_root_l
+    );
+    proof.input_proof.merkle_root_l = other.input_proof.merkle_root_l;
+    assert!(!verify(&a_c, masked, &output, &proof));
+  }
+}
+
+#[test]
+fn tampered_masks_are_rejected() {
+  let a_c = circuit();
+  let (output, proof) = prove(&a_c, true);
+
+  let mut tampered = proof.clone();
+  let mask = tampered.layers[1].mask.as_mut().unwrap();
+  mask.eval = mask.eval + FieldElement::real_one();
+  assert!(!verify(&a_c, true, &output, &tampered));
+
+  let mut tampered = proof;
+  let mask = tampered.layers[0].mask.as_mut().unwrap();
+  mask.sum = mask.sum + FieldElement::real_one();
+  assert!(!verify(&a_c, true, &output, &tampered));
+}
+
+#[test]
+fn masked_proofs_are_randomized() {
+  let a_c = circuit();
+  let (_, first) = prove(&a_c, true);
+  let (_, second) = prove(&a_c, true);
+  for (a, b) in first.layers.iter().zip(&second.layers) {
+    assert_ne!(a.sumcheck_phase1, b.sumcheck_phase1);
+    assert_ne!(a.sumcheck_phase2, b.sumcheck_phase2);
+  }
+}
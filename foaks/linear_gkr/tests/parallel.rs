@@ -0,0 +1,149 @@
+use infrastructure::{my_hash::Sha256Hasher, transcript::Transcript};
+use linear_gkr::{
+  circuit_builder::CircuitBuilder,
+  circuit_fast_track::{Gate, Layer, LayeredCircuit},
+  gate::{BinaryRule, GateType},
+  proof::LayerProof,
+  prover::ZkProver,
+  verifier::ZkVerifier,
+};
+use prime_field::FieldElement;
+
+fn fe(x: u64) -> FieldElement { FieldElement::from_real(x) }
+
+/// One instance mixing binary, unary, registered and linear combination gates
+fn instance() -> LayeredCircuit {
+  let mut builder = CircuitBuilder::new();
+  let double = builder.register(
+    "double",
+    BinaryRule {
+      constant: FieldElement::zero(),
+      u: fe(2),
+      v: FieldElement::zero(),
+      uv: FieldElement::zero(),
+    },
+  );
+  let [a, b, c]: [_; 3] = builder.inputs(3).try_into().unwrap();
+  let ab = builder.mul(a, b);
+  let not_c = builder.gate(GateType::Not, c, c);
+  let doubled = builder.gate(double, ab, ab);
+  let sum = builder.sub(ab, not_c);
+  let comb = builder.linear_combination(&[(doubled, fe(3)), (sum, fe(5)), (a, fe(7))]);
+  let out = builder.mul(comb, sum);
+  builder.output(out);
+  builder.output(c);
+  builder.build()
+}
+
+/// Sum, exponential sum and bit gates, which the builder does not place
+fn sums() -> LayeredCircuit {
+  let mut a_c = LayeredCircuit::default();
+  let relay = Layer::padded(
+    (0..4)
+      .map(|g| Gate::from_params(GateType::DirectRelay, g, 0))
+      .collect(),
+  );
+  a_c.circuit = vec![
+    Layer::padded(vec![Gate::from_params(GateType::Input, 0, 0); 4]),
+    relay,
+    Layer::padded(vec![
+      Gate::from_params(GateType::Sum, 0, 4),
+      Gate::from_params(GateType::ExpSum, 1, 3),
+      Gate::from_params(GateType::Xor, 2, 3),
+      Gate::from_params(GateType::BitTest, 1, 1),
+    ]),
+  ];
+  a_c.total_depth = a_c.circuit.len();
+  a_c
+}
+
+fn prove(a_c: &LayeredCircuit, input: &[FieldElement]) -> (Vec<FieldElement>, Vec<LayerProof>) {
+  let mut prover = ZkProver::new();
+  prover.init_array(a_c.max_bit_length(), a_c.clone());
+  prover.get_witness(input.to_vec());
+  let output = prover.evaluate();
+  let layers = prover.prove_public_input(&mut Transcript::<Sha256Hasher>::new());
+  (output, layers)
+}
+
+fn verify(
+  a_c: &LayeredCircuit,
+  input: &[FieldElement],
+  output: &[FieldElement],
+  layers: &[LayerProof],
+) -> bool {
+  let mut verifier = ZkVerifier::new();
+  verifier.init_array(a_c.max_bit_length());
+  verifier.a_c = a_c.clone();
+  verifier.verify_public_input(
+    input,
+    output,
+    layers,
+    &mut Transcript::<Sha256Hasher>::new(),
+  )
+}
+
+fn check_repeated(single: LayeredCircuit, log_repeat_num: usize) {
+  let a_c = single.repeated(log_repeat_num);
+  assert_eq!(a_c.invalid_parallel_layer(), None);
+  for (layer, one) in a_c.circuit.iter().zip(&single.circuit).skip(1) {
+    assert!(layer.is_parallel);
+    assert_eq!(layer.block_size, one.gates.len());
+    assert_eq!(layer.repeat_num, 1 << log_repeat_num);
+  }
+
+  let block_size = single.circuit[0].gates.len();
+  let input: Vec<_> = (0..a_c.circuit[0].gates.len() as u64)
+    .map(|i| fe(i % 2 + i / 3))
+    .collect();
+  let (output, layers) = prove(&a_c, &input);
+  for (block, output) in input
+    .chunks(block_size)
+    .zip(output.chunks(output.len() >> log_repeat_num))
+  {
+    assert_eq!(prove(&single, block).0, output);
+  }
+  assert!(verify(&a_c, &input, &output, &layers));
+
+  // the same predicates as the gate by gate evaluation
+  let mut flat = a_c.clone();
+  flat
+    .circuit
+    .iter_mut()
+    .for_each(|layer| layer.is_parallel = false);
+  assert!(verify(&flat, &input, &output, &layers));
+
+  let mut wrong = output;
+  wrong[0] = wrong[0] + fe(1);
+  assert!(!verify(&a_c, &input, &wrong, &layers));
+}
+
+#[test]
+fn proves_repeated_instances() {
+  check_repeated(instance(), 3);
+  check_repeated(instance(), 0);
+  check_repeated(sums(), 2);
+}
+
+#[test]
+fn checks_the_parallel_layout() {
+  let mut a_c = instance().repeated(2);
+  let depth = a_c.total_depth;
+  let block_size = a_c.circuit[depth - 1].block_size;
+  a_c.circuit[depth - 1].gates[block_size].u += 1;
+  assert_eq!(a_c.invalid_parallel_layer(), Some(depth - 1));
+
+  // the verifier only reads the first block of a parallel layer
+  let honest = instance().repeated(2);
+  let input = vec![fe(3); a_c.circuit[0].gates.len()];
+  let (output, layers) = prove(&a_c, &input);
+  assert!(!verify(&honest, &input, &output, &layers));
+
+  let mut a_c = instance().repeated(2);
+  a_c.circuit[2].log_block_size += 1;
+  assert_eq!(a_c.invalid_parallel_layer(), Some(2));
+  // nor a layout the verifier is given directly
+  let (output, layers) = prove(&honest, &input);
+  assert!(verify(&honest, &input, &output, &layers));
+  assert!(!verify(&a_c, &input, &output, &layers));
+}
@@ -0,0 +1,92 @@
+use infrastructure::{
+  my_hash::Sha256Hasher,
+  proof_format::{ProofFormat, ProofFormatError, HEADER_SIZE},
+  transcript::Transcript,
+};
+use linear_gkr::{
+  circuit_builder::CircuitBuilder,
+  proof::{GkrProof, LayerProof},
+  prover::ZkProver,
+};
+use poly_commitment::{LdtCommitment, PolyCommitProof};
+use prime_field::FieldElement;
+
+/// Sum of the products of adjacent inputs, the input is large enough to be
+/// committed
+fn prove() -> GkrProof {
+  let mut builder = CircuitBuilder::new();
+  let inputs = builder.inputs(128);
+  let mut sums: Vec<_> = inputs
+    .chunks(2)
+    .map(|pair| builder.mul(pair[0], pair[1]))
+    .collect();
+  while sums.len() > 1 {
+    sums = sums
+      .chunks(2)
+      .map(|pair| builder.add(pair[0], pair[1]))
+      .collect();
+  }
+  builder.output(sums[0]);
+  let a_c = builder.build();
+
+  let mut prover = ZkProver::new();
+  prover.init_array(a_c.max_bit_length(), a_c);
+  prover.get_witness((0..128).map(FieldElement::from_real).collect());
+  prover.evaluate();
+  prover.prove(&mut Transcript::<Sha256Hasher>::new())
+}
+
+fn round_trip<T: ProofFormat>(proof: &T) -> T {
+  let bytes = proof.to_bytes().unwrap();
+  let decoded = T::from_bytes(&bytes).unwrap();
+  assert_eq!(decoded.to_bytes().unwrap(), bytes);
+  decoded
+}
+
+#[test]
+fn gkr_proof_round_trip() {
+  let proof = prove();
+  round_trip(&proof);
+  round_trip(&proof.input_proof);
+  round_trip(&proof.input_proof.commitment);
+  for layer in proof.layers.iter() {
+    round_trip(layer);
+  }
+}
+
+#[test]
+fn rejects_malformed_proofs() {
+  let proof = prove();
+
+  let mut layer = proof.layers[1].clone();
+  layer.sumcheck_phase2.pop();
+  assert!(matches!(
+    LayerProof::from_bytes(&layer.to_bytes().unwrap()),
+    Err(ProofFormatError::Malformed(_))
+  ));
+
+  let mut input_proof = proof.input_proof.clone();
+  input_proof.queries[0].steps.pop();
+  assert!(matches!(
+    PolyCommitProof::from_bytes(&input_proof.to_bytes().unwrap()),
+    Err(ProofFormatError::Malformed(_))
+  ));
+
+  let mut commitment = proof.input_proof.commitment.clone();
+  commitment.randomness.pop();
+  assert!(matches!(
+    LdtCommitment::from_bytes(&commitment.to_bytes().unwrap()),
+    Err(ProofFormatError::Malformed(_))
+  ));
+
+  // a layer proof is not a gkr proof
+  assert!(matches!(
+    GkrProof::from_bytes(&proof.layers[0].to_bytes().unwrap()),
+    Err(ProofFormatError::UnexpectedKind { .. })
+  ));
+
+  let bytes = proof.to_bytes().unwrap();
+  for len in [0, HEADER_SIZE, bytes.len() / 2, bytes.len() - 1] {
+    assert!(GkrProof::from_bytes(&bytes[..len]).is_err());
+  }
+}
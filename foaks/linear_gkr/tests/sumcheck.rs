@@ -0,0 +1,90 @@
+use infrastructure::{my_hash::Sha256Hasher, proof_format::ProofFormat, transcript::Transcript};
+use linear_gkr::sumcheck::{self, RoundPoly, SumOfProducts, SumcheckProof};
+use prime_field::FieldElement;
+use rand::{rngs::StdRng, SeedableRng};
+
+const NUM_VARS: usize = 5;
+
+fn random_mle(rng: &mut StdRng) -> Vec<FieldElement> {
+  (0..1 << NUM_VARS)
+    .map(|_| FieldElement::new_random_from_rng(rng))
+    .collect()
+}
+
+/// `3 f g h - f^2 + 5 g`
+fn poly() -> SumOfProducts {
+  let mut rng = StdRng::seed_from_u64(1);
+  let mut poly = SumOfProducts::new(NUM_VARS);
+  let f = poly.add_mle(random_mle(&mut rng));
+  let g = poly.add_mle(random_mle(&mut rng));
+  let h = poly.add_mle(random_mle(&mut rng));
+  poly.add_product(FieldElement::from_real(3), &[f, g, h]);
+  poly.add_product(-FieldElement::real_one(), &[f, f]);
+  poly.add_product(FieldElement::from_real(5), &[g]);
+  poly
+}
+
+fn verify(poly: &SumOfProducts, sum: FieldElement, proof: &SumcheckProof) -> bool {
+  let mut transcript = Transcript::<Sha256Hasher>::new();
+  match sumcheck::verify(NUM_VARS, poly.degree(), sum, proof, &mut transcript) {
+    Some(claim) => poly.eval(&claim.point) == claim.value,
+    None => false,
+  }
+}
+
+#[test]
+fn sumcheck_reduces_to_an_evaluation() {
+  let poly = poly();
+  let sum = poly.sum();
+  let (proof, point, mle_evals) =
+    sumcheck::prove(poly.clone(), &mut Transcript::<Sha256Hasher>::new());
+  assert_eq!(proof.rounds.len(), NUM_VARS);
+  assert!(proof.rounds.iter().all(|round| round.degree() == 3));
+  assert_eq!(poly.mle_evals(&point), mle_evals);
+
+  let claim = sumcheck::verify(
+    NUM_VARS,
+    poly.degree(),
+    sum,
+    &proof,
+    &mut Transcript::<Sha256Hasher>::new(),
+  )
+  .unwrap();
+  assert_eq!(claim.point, point);
+  assert_eq!(claim.value, poly.eval_from(&mle_evals));
+  assert!(verify(&poly, sum, &proof));
+}
+
+#[test]
+fn wrong_sums_and_messages_are_rejected() {
+  let poly = poly();
+  let sum = poly.sum();
+  let (proof, ..) = sumcheck::prove(poly.clone(), &mut Transcript::<Sha256Hasher>::new());
+  assert!(!verify(&poly, sum + FieldElement::real_one(), &proof));
+
+  let mut tampered = proof.clone();
+  let last = tampered.rounds.last_mut().unwrap();
+  last.evals[2] = last.evals[2] + FieldElement::real_one();
+  assert!(!verify(&poly, sum, &tampered));
+
+  let mut truncated = proof;
+  truncated.rounds[0].evals.pop();
+  assert!(!verify(&poly, sum, &truncated));
+}
+
+#[test]
+fn round_polys_interpolate() {
+  // x^3 - 2x + 1 at 0, 1, 2, 3
+  let round = RoundPoly {
+    evals: [1, 0, 5, 22].map(FieldElement::from_real).to_vec(),
+  };
+  let x = FieldElement::from_real(10);
+  assert_eq!(round.eval(x), FieldElement::from_real(981));
+}
+
+#[test]
+fn proofs_round_trip() {
+  let (proof, ..) = sumcheck::prove(poly(), &mut Transcript::<Sha256Hasher>::new());
+  let decoded = SumcheckProof::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+  assert_eq!(decoded.rounds, proof.rounds);
+}
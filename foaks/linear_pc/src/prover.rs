@@ -0,0 +1,181 @@
+use std::marker::PhantomData;
+
+use global::constants::FE_ZERO;
+use infrastructure::{
+  merkle_tree::{self, create_tree, MerkleProof},
+  my_hash::{HashDigest, MerkleHasher, Sha256Hasher},
+  transcript::Transcript,
+};
+use linear_code::{
+  linear_code_encode::{EncodeScratch, LinearCodeEncodeContext},
+  parameter::{CodeParams, ExpanderSeed, DEFAULT_EXPANDER_SEED},
+};
+use prime_field::FieldElement;
+use rayon::prelude::*;
+
+use crate::{tensor_from_multi_point, tensor_from_point, OpeningProof};
+
+#[derive(Default)]
+pub struct LinearPcProver<H: MerkleHasher = Sha256Hasher> {
+  encoded_codeword: Vec<Vec<FieldElement>>,
+  coef: Vec<Vec<FieldElement>>,
+  codeword_size: Vec<usize>,
+  mt: Vec<HashDigest>,
+  lce_ctx: LinearCodeEncodeContext,
+  hasher: PhantomData<H>,
+}
+
+impl<H: MerkleHasher> LinearPcProver<H> {
+  pub fn init(n: usize, params: CodeParams) -> Self {
+    Self::init_with_seed(n, params, &DEFAULT_EXPANDER_SEED)
+  }
+
+  /// The code is fully determined by `n`, `params` and `seed`
+  pub fn init_with_seed(n: usize, params: CodeParams, seed: &ExpanderSeed) -> Self {
+    params.validate().expect("invalid code parameters");
+    let column_size = params.column_size;
+    let mut lce_ctx = LinearCodeEncodeContext::init(params);
+    lce_ctx.expander_init(n / column_size, seed);
+    Self {
+      lce_ctx,
+      codeword_size: Vec::with_capacity(column_size),
+      encoded_codeword: Vec::with_capacity(column_size),
+      coef: Vec::with_capacity(column_size),
+      ..Default::default()
+    }
+  }
+
+  /// The expander graphs of the code, the verifier has to be built from the
+  /// same ones or from the same seed
+  pub fn encode_context(&self) -> &LinearCodeEncodeContext { &self.lce_ctx }
+
+  /// Returns the merkle root of the encoded columns
+  pub fn commit(&mut self, src: &[FieldElement]) -> HashDigest {
+    // Todo: Refactor, delete self.codeword_size field
+    let n: usize = src.len();
+    let column_size = self.lce_ctx.params.column_size;
+
+    assert_eq!(n % column_size, 0);
+
+    let segment = n / column_size;
+    let lce_ctx = &self.lce_ctx;
+    // every worker encodes its rows with its own scratch buffers
+    let encoded: Vec<Vec<FieldElement>> = src
+      .par_chunks(segment)
+      .map_init(EncodeScratch::default, |scratch, row| {
+        lce_ctx.encode_with(scratch, row)
+      })
+      .collect();
+    for (row, mut dst) in src.chunks(segment).zip(encoded) {
+      self.coef.push(row.to_vec());
+      self.codeword_size.push(dst.len());
+      dst.resize(2 * segment, FE_ZERO);
+      self.encoded_codeword.push(dst);
+    }
+
+    let stash: Vec<HashDigest> = (0..(segment * 2))
+      .into_par_iter()
+      .map(|i| {
+        (0..(column_size / 2)).fold(HashDigest::default(), |acc, j| {
+          merkle_tree::hash_double_field_element_merkle_damgard::<H>(
+            self.encoded_codeword[2 * j][i],
+            self.encoded_codeword[2 * j + 1][i],
+            acc,
+          )
+        })
+      })
+      .collect();
+
+    create_tree::<H>(&mut self.mt, &stash, true);
+
+    self.mt[1]
+  }
+
+  pub fn tensor_product_protocol(
+    &mut self,
+    r0: &[FieldElement],
+    r1: &[FieldElement],
+    n: usize,
+  ) -> (FieldElement, OpeningProof) {
+    let size_r0 = r0.len();
+    let size_r1 = r1.len();
+    assert_eq!(size_r0 * size_r1, n);
+
+    let column_size = self.lce_ctx.params.column_size;
+    let segment = n / column_size;
+
+    let mut transcript = Transcript::<H>::new();
+    transcript.absorb_digest(self.mt[1]);
+    transcript.absorb_field_elements(r0);
+    transcript.absorb_field_elements(r1);
+
+    let query_count = self.lce_ctx.params.query_count();
+    println!("Query count: {}", query_count);
+    println!("Column size: {}", column_size);
+    println!("Number of merkle pathes: {}", query_count);
+    println!("Number of field elements: {}", query_count * column_size);
+
+    //prover construct the combined codeword
+    let codeword_size_0 = self.codeword_size[0];
+    let mut combined_codeword = vec![FE_ZERO; codeword_size_0];
+    for (i, elem_r0) in r0.iter().enumerate().take(column_size) {
+      for (j, elem_c_c) in combined_codeword.iter_mut().enumerate() {
+        *elem_c_c = *elem_c_c + *elem_r0 * self.encoded_codeword[i][j];
+      }
+    }
+    transcript.absorb_field_elements(&combined_codeword);
+
+    //prover construct the combined original message
+    let mut combined_message = vec![FE_ZERO; n];
+
+    for (i, coef_i) in self.coef.iter().enumerate().take(column_size) {
+      for (j, &coef_ij) in coef_i.iter().enumerate().take(codeword_size_0) {
+        combined_message[j] = combined_message[j] + r0[i] * coef_ij;
+      }
+    }
+
+    //check for encode
+    {
+      let sliced_message = &combined_message[..segment];
+      let test_codeword = self.lce_ctx.encode(sliced_message);
+      let test_codeword_size = test_codeword.len();
+      assert_eq!(test_codeword_size, codeword_size_0);
+      assert_eq!(test_codeword, combined_codeword);
+    }
+
+    //open the columns the verifier asks for
+    let column_q: Vec<usize> = (0..query_count)
+      .map(|_| transcript.squeeze_index(codeword_size_0))
+      .collect();
+    let columns = column_q
+      .iter()
+      .map(|&q| self.encoded_codeword.iter().map(|row| row[q]).collect())
+      .collect();
+    let column_proof = MerkleProof::open_batch(&self.mt, &column_q, segment * 2);
+
+    // the code is systematic, the combined message is the prefix of the codeword
+    let mut answer = FE_ZERO;
+    for i in 0..(segment) {
+      answer = answer + r1[i] * combined_message[i];
+    }
+
+    (
+      answer,
+      OpeningProof {
+        combined_codeword,
+        columns,
+        column_proof,
+      },
+    )
+  }
+
+  pub fn open(&mut self, x: FieldElement, n: usize) -> (FieldElement, OpeningProof) {
+    let (r0, r1) = tensor_from_point(x, n, self.lce_ctx.params.column_size);
+    self.tensor_product_protocol(&r0, &r1, n)
+  }
+
+  pub fn open_multi(&mut self, r: &[FieldElement], n: usize) -> (FieldElement, OpeningProof) {
+    let (r0, r1) = tensor_from_multi_point(r, n, self.lce_ctx.params.column_size);
+    self.tensor_product_protocol(&r0, &r1, n)
+  }
+}
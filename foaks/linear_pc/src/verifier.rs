@@ -0,0 +1,166 @@
+use std::{marker::PhantomData, time::Instant};
+
+use global::constants::FE_ZERO;
+use infrastructure::{
+  merkle_tree,
+  my_hash::{HashDigest, MerkleHasher, Sha256Hasher},
+  transcript::Transcript,
+};
+use linear_code::{
+  linear_code_encode::LinearCodeEncodeContext,
+  parameter::{CodeParams, ExpanderSeed},
+};
+use prime_field::FieldElement;
+
+use crate::{tensor_from_multi_point, tensor_from_point, OpeningProof};
+
+#[derive(Default)]
+pub struct LinearPcVerifier<H: MerkleHasher = Sha256Hasher> {
+  lce_ctx: LinearCodeEncodeContext,
+  hasher: PhantomData<H>,
+}
+
+impl<H: MerkleHasher> LinearPcVerifier<H> {
+  /// `lce_ctx` must hold the expander graphs the commitment was encoded with
+  pub fn new(lce_ctx: LinearCodeEncodeContext) -> Self {
+    Self {
+      lce_ctx,
+      ..Default::default()
+    }
+  }
+
+  /// Rebuilds the code of `LinearPcProver::init_with_seed(n, params, seed)`
+  pub fn from_seed(n: usize, params: CodeParams, seed: &ExpanderSeed) -> Self {
+    params.validate().expect("invalid code parameters");
+    let mut lce_ctx = LinearCodeEncodeContext::init(params);
+    lce_ctx.expander_init(n / params.column_size, seed);
+    Self::new(lce_ctx)
+  }
+
+  fn codeword_size(&self, segment: usize) -> usize {
+    if segment <= self.lce_ctx.params.distance_threshold() {
+      segment
+    } else {
+      segment + self.lce_ctx.d[0].l + self.lce_ctx.d[0].r
+    }
+  }
+
+  pub fn tensor_product_protocol(
+    &mut self,
+    r0: &[FieldElement],
+    r1: &[FieldElement],
+    n: usize,
+    root: HashDigest,
+    value: FieldElement,
+    proof: &OpeningProof,
+  ) -> bool {
+    let size_r0 = r0.len();
+    let size_r1 = r1.len();
+    assert_eq!(size_r0 * size_r1, n);
+
+    let column_size = self.lce_ctx.params.column_size;
+    let segment = n / column_size;
+
+    let mut proof_size = 0;
+    let mut transcript = Transcript::<H>::new();
+    transcript.absorb_digest(root);
+    transcript.absorb_field_elements(r0);
+    transcript.absorb_field_elements(r1);
+
+    let query_count = self.lce_ctx.params.query_count();
+    let codeword_size_0 = self.codeword_size(segment);
+    let combined_codeword = &proof.combined_codeword;
+    if combined_codeword.len() != codeword_size_0 || proof.columns.len() != query_count {
+      eprintln!("Malformed opening proof");
+      return false;
+    }
+    transcript.absorb_field_elements(combined_codeword);
+    proof_size += codeword_size_0 * std::mem::size_of::<FieldElement>();
+
+    // the spot checks only bind the queried columns, the rest of the combined
+    // codeword must be the encoding of its prefix
+    if self.lce_ctx.encode(&combined_codeword[..segment]) != *combined_codeword {
+      eprintln!("Combined codeword is not a codeword");
+      return false;
+    }
+
+    //verifier random check columns
+    let v_t0 = Instant::now();
+
+    let mut leaves = Vec::with_capacity(query_count);
+    for column in proof.columns.iter() {
+      let q = transcript.squeeze_index(codeword_size_0);
+      if column.len() != column_size {
+        eprintln!("Malformed column opening");
+        return false;
+      }
+      let mut sum = FE_ZERO;
+      for (j, elem) in r0.iter().enumerate().take(column_size) {
+        sum = sum + *elem * column[j];
+      }
+      proof_size += std::mem::size_of::<FieldElement>() * column_size;
+      if sum != combined_codeword[q] {
+        eprintln!("Combined codeword check failed");
+        return false;
+      }
+
+      //calc hash
+      let mut column_hash = HashDigest::default();
+
+      for j in 0..column_size / 2 {
+        column_hash = merkle_tree::hash_double_field_element_merkle_damgard::<H>(
+          column[2 * j],
+          column[2 * j + 1],
+          column_hash,
+        );
+      }
+      leaves.push((q, column_hash));
+    }
+
+    if !proof.column_proof.verify::<H>(root, &leaves, segment * 2) {
+      eprintln!("Column merkle path check failed");
+      return false;
+    }
+    proof_size += proof.column_proof.size();
+
+    // the code is systematic, the combined message is the prefix of the codeword
+    let mut answer = FE_ZERO;
+    for i in 0..(segment) {
+      answer = answer + r1[i] * combined_codeword[i];
+    }
+    if answer != value {
+      eprintln!("Claimed value check failed");
+      return false;
+    }
+    let verification_time = v_t0.elapsed().as_secs_f64();
+
+    println!("Proof size for tensor IOP {} bytes", proof_size);
+    println!("Verification time {}", verification_time);
+
+    true
+  }
+
+  pub fn verify(
+    &mut self,
+    root: HashDigest,
+    x: FieldElement,
+    n: usize,
+    value: FieldElement,
+    proof: &OpeningProof,
+  ) -> bool {
+    let (r0, r1) = tensor_from_point(x, n, self.lce_ctx.params.column_size);
+    self.tensor_product_protocol(&r0, &r1, n, root, value, proof)
+  }
+
+  pub fn verify_multi(
+    &mut self,
+    root: HashDigest,
+    r: &[FieldElement],
+    n: usize,
+    value: FieldElement,
+    proof: &OpeningProof,
+  ) -> bool {
+    let (r0, r1) = tensor_from_multi_point(r, n, self.lce_ctx.params.column_size);
+    self.tensor_product_protocol(&r0, &r1, n, root, value, proof)
+  }
+}
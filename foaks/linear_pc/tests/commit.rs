@@ -0,0 +1,57 @@
+use global::constants::FE_ZERO;
+use infrastructure::{
+  merkle_tree::{create_tree, hash_double_field_element_merkle_damgard},
+  my_hash::{HashDigest, Sha256Hasher},
+};
+use linear_code::parameter::CodeParams;
+use linear_pc::prover::LinearPcProver;
+use prime_field::FieldElement;
+
+const N: usize = 1 << 14;
+
+/// Row by row encoding and column hashing on one thread
+fn serial_root(prover: &LinearPcProver, src: &[FieldElement]) -> HashDigest {
+  let mut lce_ctx = prover.encode_context().clone();
+  let column_size = lce_ctx.params.column_size;
+  let segment = src.len() / column_size;
+  let rows: Vec<Vec<FieldElement>> = src
+    .chunks(segment)
+    .map(|row| {
+      let mut dst = lce_ctx.encode(row);
+      dst.resize(2 * segment, FE_ZERO);
+      dst
+    })
+    .collect();
+
+  let leaves: Vec<HashDigest> = (0..2 * segment)
+    .map(|i| {
+      (0..column_size / 2).fold(HashDigest::default(), |acc, j| {
+        hash_double_field_element_merkle_damgard::<Sha256Hasher>(
+          rows[2 * j][i],
+          rows[2 * j + 1][i],
+          acc,
+        )
+      })
+    })
+    .collect();
+  let mut tree = vec![];
+  create_tree::<Sha256Hasher>(&mut tree, &leaves, true);
+  tree[1]
+}
+
+#[test]
+fn parallel_commit_matches_serial() {
+  let src: Vec<FieldElement> = (0..N)
+    .map(|i| FieldElement::new(i as u64 * 3 + 1, i as u64))
+    .collect();
+
+  for threads in [1, 4] {
+    let pool = rayon::ThreadPoolBuilder::new()
+      .num_threads(threads)
+      .build()
+      .unwrap();
+    let mut prover: LinearPcProver = LinearPcProver::init(N, CodeParams::default());
+    let root = pool.install(|| prover.commit(&src));
+    assert_eq!(root, serial_root(&prover, &src));
+  }
+}
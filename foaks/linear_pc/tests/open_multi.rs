@@ -0,0 +1,55 @@
+use global::constants::FE_REAL_ONE;
+use linear_code::parameter::CodeParams;
+use linear_pc::{prover::LinearPcProver, verifier::LinearPcVerifier};
+use prime_field::FieldElement;
+
+const LOG_N: usize = 14;
+const N: usize = 1 << LOG_N;
+
+/// Multilinear extension of `evals` at `r`, `r[0]` binding the most
+/// significant bit of the index
+fn mle(evals: &[FieldElement], r: &[FieldElement]) -> FieldElement {
+  r.iter().fold(evals.to_vec(), |evals, &x| {
+    let (low, high) = evals.split_at(evals.len() / 2);
+    low
+      .iter()
+      .zip(high)
+      .map(|(&a, &b)| a * (FE_REAL_ONE - x) + b * x)
+      .collect()
+  })[0]
+}
+
+#[test]
+fn multilinear_openings_evaluate_the_extension() {
+  let coefs: Vec<FieldElement> = (0..N)
+    .map(|i| FieldElement::new(i as u64 * 7 + 2, i as u64 % 5))
+    .collect();
+  let r: Vec<FieldElement> = (0..LOG_N)
+    .map(|i| FieldElement::new(i as u64 + 3, 11))
+    .collect();
+
+  let mut prover: LinearPcProver = LinearPcProver::init(N, CodeParams::default());
+  let mut verifier: LinearPcVerifier = LinearPcVerifier::new(prover.encode_context().clone());
+  let root = prover.commit(&coefs);
+  let (value, proof) = prover.open_multi(&r, N);
+  assert_eq!(value, mle(&coefs, &r));
+  assert!(verifier.verify_multi(root, &r, N, value, &proof));
+}
+
+#[test]
+fn forged_combined_codewords_are_rejected() {
+  let coefs: Vec<FieldElement> = (0..N).map(|i| FieldElement::new(i as u64, 1)).collect();
+  let x = FieldElement::new(3, 5);
+
+  let mut prover: LinearPcProver = LinearPcProver::init(N, CodeParams::default());
+  let mut verifier: LinearPcVerifier = LinearPcVerifier::new(prover.encode_context().clone());
+  let root = prover.commit(&coefs);
+  let (value, mut proof) = prover.open(x, N);
+  assert!(verifier.verify(root, x, N, value, &proof));
+
+  // the first weight of a point opening is one, shifting the first entry
+  // shifts the claimed value by as much
+  let delta = FieldElement::new(1, 0);
+  proof.combined_codeword[0] = proof.combined_codeword[0] + delta;
+  assert!(!verifier.verify(root, x, N, value + delta, &proof));
+}
@@ -0,0 +1,86 @@
+use infrastructure::{
+  my_hash::HashDigest,
+  proof_format::{ProofFormat, ProofFormatError, HEADER_SIZE},
+};
+use linear_code::parameter::CodeParams;
+use linear_pc::{prover::LinearPcProver, verifier::LinearPcVerifier, OpeningProof};
+use poly_commitment::CommitPhaseData;
+use prime_field::FieldElement;
+
+const N: usize = 1 << 14;
+
+fn open() -> (
+  LinearPcVerifier,
+  HashDigest,
+  FieldElement,
+  FieldElement,
+  OpeningProof,
+) {
+  let mut prover: LinearPcProver = LinearPcProver::init(N, CodeParams::default());
+  let verifier = LinearPcVerifier::new(prover.encode_context().clone());
+  let coefs: Vec<FieldElement> = (0..N).map(|i| FieldElement::new(i as u64, 1)).collect();
+  let root = prover.commit(&coefs);
+  let x = FieldElement::new(3, 5);
+  let (value, proof) = prover.open(x, N);
+  (verifier, root, x, value, proof)
+}
+
+fn round_trip<T: ProofFormat>(proof: &T) -> T {
+  let bytes = proof.to_bytes().unwrap();
+  let decoded = T::from_bytes(&bytes).unwrap();
+  assert_eq!(decoded.to_bytes().unwrap(), bytes);
+  decoded
+}
+
+#[test]
+fn opening_proof_round_trip() {
+  let (mut verifier, root, x, value, proof) = open();
+
+  let decoded = round_trip(&proof);
+  assert!(verifier.verify(root, x, N, value, &decoded));
+}
+
+#[test]
+fn rejects_malformed_proofs() {
+  let (_, _, _, _, proof) = open();
+
+  let mut opening = proof.clone();
+  opening.columns[1].pop();
+  assert!(matches!(
+    OpeningProof::from_bytes(&opening.to_bytes().unwrap()),
+    Err(ProofFormatError::Malformed(_))
+  ));
+
+  // a commitment is not an opening proof
+  let bytes = proof.to_bytes().unwrap();
+  let commitment = CommitPhaseData::default().to_bytes().unwrap();
+  assert!(matches!(
+    OpeningProof::from_bytes(&commitment),
+    Err(ProofFormatError::UnexpectedKind { .. })
+  ));
+
+  for len in [0, HEADER_SIZE, bytes.len() / 2, bytes.len() - 1] {
+    assert!(OpeningProof::from_bytes(&bytes[..len]).is_err());
+  }
+}
+
+#[test]
+fn commit_phase_data_round_trip() {
+  let mut cpd = CommitPhaseData::default();
+  cpd.rs_codeword[0] = (0..8).map(|i| FieldElement::new(i, i + 1)).collect();
+  cpd.rs_codeword_mapping[0] = (0..8).rev().collect();
+  cpd.merkle[0] = vec![HashDigest::new_from_c(1, 2, 3, 4); 16];
+  cpd.merkle_size[0] = 4;
+
+  let decoded = round_trip(&cpd);
+  assert_eq!(decoded.rs_codeword[0], cpd.rs_codeword[0]);
+  assert_eq!(decoded.rs_codeword_mapping[0], cpd.rs_codeword_mapping[0]);
+  assert_eq!(decoded.merkle[0], cpd.merkle[0]);
+  assert_eq!(decoded.merkle_size, cpd.merkle_size);
+
+  cpd.rs_codeword_mapping[0][3] = 8;
+  assert!(matches!(
+    CommitPhaseData::from_bytes(&cpd.to_bytes().unwrap()),
+    Err(ProofFormatError::Malformed(_))
+  ));
+}
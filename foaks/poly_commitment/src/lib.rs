@@ -1,25 +1,28 @@
-use std::{
-  env, ffi::OsStr, fs::File, io::Read, os::unix::prelude::OsStrExt, process::Command, time,
-};
+use std::{mem, time};
 
 use global::constants::*;
 use infrastructure::{
-  my_hash::HashDigest,
+  my_hash::{HashDigest, MerkleHasher},
+  proof_format::{ProofFormat, ProofFormatError, ProofKind},
   rs_polynomial::{fast_fourier_transform, inverse_fast_fourier_transform, ScratchPad},
+  transcript::Transcript,
   utility::my_log,
 };
 use prime_field::FieldElement;
+use serde::{Deserialize, Serialize};
 
 use crate::vpd::{
-  fri::{
-    request_init_commit, request_init_value_with_merkle, request_step_commit, FRIContext, TripleVec,
-  },
+  fri::{request_init_commit, request_init_value_with_merkle, request_step_commit, FRIContext},
   verifier::verify_merkle,
 };
 
 mod vpd;
+pub use vpd::fri::{CommitPhaseData, TripleVec};
+
+/// Number of FRI queries, each one opens every slice at a random position
+const QUERY_COUNT: usize = 33;
 
-#[derive(Default)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct LdtCommitment {
   pub commitment_hash: Vec<HashDigest>,
   pub randomness: Vec<FieldElement>,
@@ -27,6 +30,62 @@ pub struct LdtCommitment {
   pub mx_depth: usize,
 }
 
+/// Openings answering one FRI query: both witness oracles at the first level
+/// and the folded codeword of every commit phase step
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct LdtQuery {
+  pub witness_l: TripleVec,
+  pub witness_h: TripleVec,
+  pub steps: Vec<TripleVec>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct PolyCommitProof {
+  pub merkle_root_l: HashDigest,
+  pub merkle_root_h: HashDigest,
+  pub all_sum: Vec<FieldElement>,
+  pub commitment: LdtCommitment,
+  pub queries: Vec<LdtQuery>,
+}
+
+impl ProofFormat for LdtCommitment {
+  const KIND: ProofKind = ProofKind::LdtCommitment;
+
+  fn validate(&self) -> Result<(), ProofFormatError> {
+    if self.randomness.len() != self.commitment_hash.len()
+      || self.mx_depth != self.commitment_hash.len()
+    {
+      return Err(ProofFormatError::Malformed("ldt commitment depth mismatch"));
+    }
+    if self.final_rs_code.len() != (1 << RS_CODE_RATE) * SLICE_NUMBER {
+      return Err(ProofFormatError::Malformed("final rs code length"));
+    }
+    Ok(())
+  }
+}
+
+impl ProofFormat for PolyCommitProof {
+  const KIND: ProofKind = ProofKind::PolyCommit;
+
+  fn validate(&self) -> Result<(), ProofFormatError> {
+    self.commitment.validate()?;
+    let depth = self.commitment.commitment_hash.len();
+    let well_formed = |t: &TripleVec| t.0.len() == SLICE_NUMBER && !t.1.is_empty();
+    if self.all_sum.len() != SLICE_NUMBER
+      || self.queries.len() != QUERY_COUNT
+      || !self.queries.iter().all(|query| {
+        well_formed(&query.witness_l)
+          && well_formed(&query.witness_h)
+          && query.steps.len() == depth
+          && query.steps.iter().all(well_formed)
+      })
+    {
+      return Err(ProofFormatError::Malformed("poly commitment proof shape"));
+    }
+    Ok(())
+  }
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct PolyCommitContext {
   pub twiddle_factor: Vec<FieldElement>,
@@ -67,7 +126,7 @@ pub struct PolyCommitProver {
 }
 
 impl PolyCommitProver {
-  pub fn commit_private_array(
+  pub fn commit_private_array<H: MerkleHasher>(
     &mut self,
     private_array: &[FieldElement],
     log_array_length: usize,
@@ -144,7 +203,7 @@ impl PolyCommitProver {
       self.fri_ctx = Some(FRIContext::new());
     }
 
-    let ret = vpd::prover::vpd_prover_init(
+    let ret = vpd::prover::vpd_prover_init::<H>(
       self.fri_ctx.as_mut().expect("Failed to retrieve fri_ctx"),
       &self.ctx,
       log_array_length,
@@ -157,7 +216,7 @@ impl PolyCommitProver {
     ret
   }
 
-  pub fn commit_public_array(
+  pub fn commit_public_array<H: MerkleHasher>(
     &mut self,
     public_array: &[FieldElement],
     r_0_len: usize,
@@ -353,7 +412,7 @@ impl PolyCommitProver {
     println!("PostGKR prepare time 0:{}", time_span);
 
     t0 = time::Instant::now();
-    let ret = request_init_commit(fri_ctx, &self.ctx, r_0_len, 1);
+    let ret = request_init_commit::<H>(fri_ctx, &self.ctx, r_0_len, 1);
 
     time_span = t0.elapsed().as_secs_f64();
     self.total_time_pc_p += time_span;
@@ -361,77 +420,126 @@ impl PolyCommitProver {
 
     ret
   }
+
+  /// Runs the FRI commit phase and answers the queries drawn from `transcript`.
+  /// Must be called after both arrays have been committed.
+  pub fn open<H: MerkleHasher>(
+    &mut self,
+    log_length: usize,
+    merkle_root_l: HashDigest,
+    merkle_root_h: HashDigest,
+    all_sum: Vec<FieldElement>,
+    transcript: &mut Transcript<H>,
+  ) -> PolyCommitProof {
+    transcript.absorb_digest(merkle_root_l);
+    transcript.absorb_digest(merkle_root_h);
+    transcript.absorb_field_elements(&all_sum);
+
+    let fri_ctx = self.fri_ctx.as_mut().expect("Failed to retrieve fri_ctx");
+    let commitment = fri_ctx.commit_phase(log_length, self.ctx.slice_count, transcript);
+
+    let queries = (0..QUERY_COUNT)
+      .map(|_| {
+        let mut pow = sample_query(transcript, log_length);
+        let s0_pow = pow / 2;
+        let s1_pow = (pow + (1 << (log_length + RS_CODE_RATE - LOG_SLICE_NUMBER))) / 2;
+
+        let (witness_l, _) = request_init_value_with_merkle::<H>(s0_pow, s1_pow, 0, fri_ctx);
+        let (witness_h, _) = request_init_value_with_merkle::<H>(s0_pow, s1_pow, 1, fri_ctx);
+
+        let steps = (0..(log_length - LOG_SLICE_NUMBER))
+          .map(|i| {
+            pow %= 1 << (log_length + RS_CODE_RATE - LOG_SLICE_NUMBER - i);
+            request_step_commit(i, pow / 2, fri_ctx).0
+          })
+          .collect();
+
+        LdtQuery {
+          witness_l,
+          witness_h,
+          steps,
+        }
+      })
+      .collect();
+
+    PolyCommitProof {
+      merkle_root_l,
+      merkle_root_h,
+      all_sum,
+      commitment,
+      queries,
+    }
+  }
 }
 
-#[derive(Default, Debug)]
-pub struct PolyCommitVerifier {
-  pub pc_prover: PolyCommitProver,
+/// Draws the position of a FRI query, an even index in the upper half of the
+/// first level codeword
+fn sample_query<H: MerkleHasher>(transcript: &mut Transcript<H>, log_length: usize) -> usize {
+  let max = 1 << (log_length + RS_CODE_RATE - LOG_SLICE_NUMBER);
+  let mut pow = transcript.squeeze_index(max);
+  while pow < (1 << (log_length - LOG_SLICE_NUMBER)) || pow % 2 == 1 {
+    pow = transcript.squeeze_index(max);
+  }
+  pow
 }
 
+#[derive(Default, Debug)]
+pub struct PolyCommitVerifier {}
+
 impl PolyCommitVerifier {
-  pub fn verify_poly_commitment(
-    &mut self,
-    all_sum: &[FieldElement],
+  pub fn verify_poly_commitment<H: MerkleHasher>(
+    &self,
     log_length: usize,
     public_array: &[FieldElement],
-    merkle_tree_l: HashDigest,
-    merkle_tree_h: HashDigest,
-  ) -> (f64, usize, f64, bool) {
-    let command = format!("./fft_gkr {} log_fftgkr.txt", log_length - LOG_SLICE_NUMBER);
-    // Use output, should error the error
-    Command::new("sh")
-      .arg("-c")
-      .arg(OsStr::from_bytes(command.as_bytes()))
-      .output()
-      .expect("Failed to execute command");
-
-    let mut file = match File::open(
-      env::current_dir()
-        .expect("Failed to retrieve current directory")
-        //.join("src")
-        .join("log_fftgkr.txt"),
-    ) {
-      Err(err) => panic!("Couldn't open {}: {}", "log_fftgkr.txt", err),
-      Ok(file) => file,
-    };
-
-    let mut contents = String::new();
-    file
-      .read_to_string(&mut contents)
-      .expect("something went wrong reading the file");
-
-    let mut iter = contents.split_whitespace();
-    let v_time_fft: f64 = iter
-      .next()
-      .expect("Error getting v_time_fft")
-      .parse()
-      .expect("Error converting v_time_fft to f64");
-    let proof_size_fft: usize = iter
-      .next()
-      .expect("Error getting proof_size_fft")
-      .parse()
-      .expect("Error converting proof_size_fft to usize");
-    let p_time_fft: f64 = iter
-      .next()
-      .expect("Error getting p_time_fft")
-      .parse()
-      .expect("Error converting p_time_fft to f64");
-
-    let mut v_time = v_time_fft;
-    let p_time = p_time_fft;
-    let mut proof_size = proof_size_fft;
-
-    let com = self
-      .pc_prover
-      .fri_ctx
-      .as_mut()
-      .expect("Failed to retrieve fri_ctx")
-      .commit_phase(log_length, self.pc_prover.ctx.slice_count);
+    proof: &PolyCommitProof,
+    transcript: &mut Transcript<H>,
+  ) -> (f64, usize, bool) {
+    let mut v_time = 0.0;
+    let mut proof_size = 0;
+
+    let PolyCommitProof {
+      merkle_root_l,
+      merkle_root_h,
+      all_sum,
+      commitment: com,
+      queries,
+    } = proof;
+
+    let slice_count = 1 << LOG_SLICE_NUMBER;
+    let depth = log_length - LOG_SLICE_NUMBER;
+    let well_formed = |t: &TripleVec| t.0.len() == slice_count && !t.1.is_empty();
+    if all_sum.len() != slice_count
+      || com.commitment_hash.len() != depth
+      || com.final_rs_code.len() != (1 << RS_CODE_RATE) * SLICE_NUMBER
+      || queries.len() != QUERY_COUNT
+      || !queries.iter().all(|query| {
+        well_formed(&query.witness_l)
+          && well_formed(&query.witness_h)
+          && query.steps.len() == depth
+          && query.steps.iter().all(well_formed)
+      })
+    {
+      eprintln!("Malformed poly commitment proof");
+      return (v_time, proof_size, false);
+    }
+
+    transcript.absorb_digest(*merkle_root_l);
+    transcript.absorb_digest(*merkle_root_h);
+    transcript.absorb_field_elements(all_sum);
+
+    // replay the commit phase to recover the folding randomness
+    let mut randomness = Vec::with_capacity(depth);
+    for hash in com.commitment_hash.iter() {
+      randomness.push(transcript.squeeze_field_element());
+      transcript.absorb_digest(*hash);
+    }
+    transcript.absorb_field_elements(&com.final_rs_code);
+    proof_size += com.commitment_hash.len() * mem::size_of::<HashDigest>()
+      + com.final_rs_code.len() * mem::size_of::<FieldElement>();
 
     let coef_slice_size = 1 << (log_length - LOG_SLICE_NUMBER);
 
-    for _ in 0..33 {
-      let slice_count = 1 << LOG_SLICE_NUMBER;
+    for query in queries.iter() {
       let slice_size = 1 << (log_length + RS_CODE_RATE - LOG_SLICE_NUMBER);
 
       let mut t0: time::Instant;
@@ -439,10 +547,7 @@ impl PolyCommitVerifier {
 
       let inv_2 = FieldElement::from_real(2).inverse();
 
-      let mut alpha_l: TripleVec;
-      let mut alpha_h: TripleVec;
       let mut alpha: TripleVec = (vec![], vec![]);
-      let mut beta: TripleVec = (vec![], vec![]);
 
       let mut s0;
       let mut s1;
@@ -451,23 +556,16 @@ impl PolyCommitVerifier {
       let mut y = FE_ZERO;
       // let mut equ_beta: bool; not used in C++
       assert!(log_length - LOG_SLICE_NUMBER > 0);
-      let mut pow = 0_u128;
-      let mut max: u128;
+      let mut pow = sample_query(transcript, log_length);
 
-      for i in 0..(log_length - LOG_SLICE_NUMBER) {
+      for (i, beta) in query.steps.iter().enumerate() {
         t0 = time::Instant::now();
 
         if i == 0 {
-          max = 1 << (log_length + RS_CODE_RATE - LOG_SLICE_NUMBER - i);
-          pow = rand::random::<u128>() % max;
-
-          while pow < (1 << (log_length - LOG_SLICE_NUMBER - i)) || pow % 2 == 1 {
-            pow = rand::random::<u128>() % max;
-          }
           root_of_unity =
             FieldElement::get_root_of_unity(log_length + RS_CODE_RATE - LOG_SLICE_NUMBER - i)
               .expect("Failed to retrieve root of unity");
-          y = FieldElement::fast_pow(root_of_unity, pow);
+          y = FieldElement::fast_pow(root_of_unity, pow as u128);
         } else {
           root_of_unity = root_of_unity * root_of_unity;
           pow %= 1 << (log_length + RS_CODE_RATE - LOG_SLICE_NUMBER - i);
@@ -481,8 +579,8 @@ impl PolyCommitVerifier {
         let s0_pow = pow / 2;
         let s1_pow = (pow + (1 << (log_length + RS_CODE_RATE - LOG_SLICE_NUMBER - i))) / 2;
 
-        s0 = root_of_unity.fast_pow(s0_pow);
-        s1 = root_of_unity.fast_pow(s1_pow);
+        s0 = root_of_unity.fast_pow(s0_pow as u128);
+        s1 = root_of_unity.fast_pow(s1_pow as u128);
 
         // let mut indicator; in C++ "indicator" is used but in the if else the sentence are the same. Check vpd_verifier.cpp line 263
 
@@ -493,89 +591,56 @@ impl PolyCommitVerifier {
         assert_eq!(s0 * s0, y);
         assert_eq!(s1 * s1, y);
 
-        let mut new_size;
         let gen_val = |alpha: &TripleVec, inv_mu: FieldElement, i: usize, j: usize| {
           (alpha.0[j].0 + alpha.0[j].1) * inv_2
-            + (alpha.0[j].0 - alpha.0[j].1) * inv_2 * com.randomness[i] * inv_mu
+            + (alpha.0[j].0 - alpha.0[j].1) * inv_2 * randomness[i] * inv_mu
         };
 
-        let fri_ctx = self
-          .pc_prover
-          .fri_ctx
-          .as_mut()
-          .expect("Failed to retrieve fri_ctx");
+        proof_size += triple_vec_size(beta);
 
         if i == 0 {
           time_span = t0.elapsed().as_secs_f64();
           v_time += time_span;
-          (alpha_l, new_size) = request_init_value_with_merkle(
-            // Todo: This new_size is not read in C++, but at the end we actually need it
-            s0_pow.try_into().expect("Failed to convert s0_pow to u32"),
-            s1_pow.try_into().expect("Failed to convert s1_pow to u32"),
-            0,
-            fri_ctx,
-          );
-          (alpha_h, new_size) = request_init_value_with_merkle(
-            s0_pow.try_into().expect("Failed to convert s0_pow to u32"),
-            s1_pow.try_into().expect("Failed to convert s1_pow to u32"),
-            1,
-            fri_ctx,
-          );
-
-          proof_size += new_size;
+          let alpha_l = &query.witness_l;
+          let alpha_h = &query.witness_h;
+          proof_size += triple_vec_size(alpha_l) + triple_vec_size(alpha_h);
 
           t0 = time::Instant::now();
 
-          let min_pow = |s0: u128, s1: u128| {
-            if s0 < s1 {
-              s0
-            } else {
-              s1
-            }
-          };
-          if !verify_merkle(
-            merkle_tree_l,
+          let min_pow = s0_pow.min(s1_pow) as u128;
+          if !verify_merkle::<H>(
+            *merkle_root_l,
             &alpha_l.1,
             alpha_l.1.len(),
-            min_pow(s0_pow, s1_pow),
+            min_pow,
             &alpha_l.0,
           ) {
-            return (v_time, proof_size, p_time, false);
+            return (v_time, proof_size, false);
           }
-          if !verify_merkle(
-            merkle_tree_h,
+          if !verify_merkle::<H>(
+            *merkle_root_h,
             &alpha_h.1,
             alpha_h.1.len(),
-            min_pow(s0_pow, s1_pow),
+            min_pow,
             &alpha_h.0,
           ) {
-            return (v_time, proof_size, p_time, false);
+            return (v_time, proof_size, false);
           }
 
           v_time += t0.elapsed().as_secs_f64();
-          (beta, new_size) = request_step_commit(
-            0,
-            (pow / 2)
-              .try_into()
-              .expect("Failed to convert pow/2 to u32"),
-            fri_ctx,
-          );
-
-          proof_size += new_size;
           // Todo Check Orion whether we add t0 to v_time
-          t0 = time::Instant::now();
 
-          if !verify_merkle(
+          if !verify_merkle::<H>(
             com.commitment_hash[0],
             &beta.1,
             beta.1.len(),
-            pow / 2,
+            (pow / 2) as u128,
             &beta.0,
           ) {
-            return (v_time, proof_size, p_time, false);
+            return (v_time, proof_size, false);
           }
 
-          let inv_mu = root_of_unity.fast_pow(pow / 2).inverse();
+          let inv_mu = root_of_unity.fast_pow((pow / 2) as u128).inverse();
           alpha.0.clear();
           alpha.1.clear();
 
@@ -592,8 +657,8 @@ impl PolyCommitVerifier {
           )
           .expect("Failed to retrieve root of unity");
 
-          x[0] = x[0].fast_pow(s0_pow);
-          x[1] = x[1].fast_pow(s1_pow);
+          x[0] = x[0].fast_pow(s0_pow as u128);
+          x[1] = x[1].fast_pow(s1_pow as u128);
 
           rou[0] = x[0].fast_pow((slice_size >> RS_CODE_RATE) as u128);
           rou[1] = x[1].fast_pow((slice_size >> RS_CODE_RATE) as u128);
@@ -647,49 +712,35 @@ impl PolyCommitVerifier {
                 "a: {}, b:{}, Fri check consistency first round fail {}",
                 a, b, j
               );
-              return (v_time, proof_size, p_time, false);
+              return (v_time, proof_size, false);
             }
           }
 
-          // time_span = t0.elapsed().as_secs_f64();
-          // From original code
-          // This will not added into v time since the fft gkr already give the result, we didn't have time to integrate the fft gkr into the main body, so we have the evaluation code here
-          // v_time += time_span.count();
+          // Not added to v_time, the public array is checked against the inverse fft gkr proof
         } else {
           time_span = t0.elapsed().as_secs_f64();
           v_time += time_span;
 
-          alpha = beta.clone();
-          (beta, new_size) = request_step_commit(
-            i,
-            (pow / 2)
-              .try_into()
-              .expect("Failed to convert pow/2 to u32"),
-            fri_ctx,
-          );
-
-          proof_size += new_size;
-
           t0 = time::Instant::now();
 
-          if !verify_merkle(
+          if !verify_merkle::<H>(
             com.commitment_hash[i],
             &beta.1,
             beta.1.len(),
-            pow / 2,
+            (pow / 2) as u128,
             &beta.0,
           ) {
-            return (v_time, proof_size, p_time, false);
+            return (v_time, proof_size, false);
           }
 
-          let inv_mu = root_of_unity.fast_pow(pow / 2).inverse();
+          let inv_mu = root_of_unity.fast_pow((pow / 2) as u128).inverse();
           time_span = t0.elapsed().as_secs_f64();
           v_time += time_span;
 
           for j in 0..slice_count {
             let p_val_0 = gen_val(&alpha, inv_mu, i, j);
             let p_val_1 = (alpha.0[j].0 + alpha.0[j].1) * inv_2
-              + (alpha.0[j].1 - alpha.0[j].0) * inv_2 * com.randomness[i] * inv_mu;
+              + (alpha.0[j].1 - alpha.0[j].0) * inv_2 * randomness[i] * inv_mu;
 
             if p_val_0 != beta.0[j].0
               && p_val_0 != beta.0[j].1
@@ -697,31 +748,28 @@ impl PolyCommitVerifier {
               && p_val_1 != beta.0[j].1
             {
               eprintln!("Fri check consistency {} round fail", i);
-              return (v_time, proof_size, p_time, false);
+              return (v_time, proof_size, false);
             }
           }
         }
+        alpha = beta.clone();
       }
-      let fri_ctx = self
-        .pc_prover
-        .fri_ctx
-        .as_ref()
-        .expect("Failed to retrieve fri_ctx");
 
       let check_failed = (0..slice_count).any(|i| {
-        let template =
-          fri_ctx.cpd.rs_codeword[com.mx_depth - 1][(0 << (LOG_SLICE_NUMBER + 1)) | (i << 1)];
-        !(0..(1 << (RS_CODE_RATE - 1))).all(|j| {
-          fri_ctx.cpd.rs_codeword[com.mx_depth - 1][(j << (LOG_SLICE_NUMBER + 1)) | (i << 1)]
-            == template
-        })
+        let template = com.final_rs_code[(0 << (LOG_SLICE_NUMBER + 1)) | (i << 1)];
+        !(0..(1 << (RS_CODE_RATE - 1)))
+          .all(|j| com.final_rs_code[(j << (LOG_SLICE_NUMBER + 1)) | (i << 1)] == template)
       });
 
       if check_failed {
         eprintln!("Fri rs code check failed");
-        return (v_time, proof_size, p_time, false);
+        return (v_time, proof_size, false);
       }
     }
-    (v_time, proof_size, p_time, true)
+    (v_time, proof_size, true)
   }
 }
+
+fn triple_vec_size((values, path): &TripleVec) -> usize {
+  values.len() * 2 * mem::size_of::<FieldElement>() + path.len() * mem::size_of::<HashDigest>()
+}
@@ -3,16 +3,18 @@ extern crate rayon;
 use global::constants::*;
 use infrastructure::{
   merkle_tree,
-  my_hash::{my_hash, HashDigest},
+  my_hash::{HashDigest, MerkleHasher},
+  proof_format::{ProofFormat, ProofFormatError, ProofKind},
 };
 use prime_field::FieldElement;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::PolyCommitContext;
 
-pub type TripleVec<'a> = (Vec<(FieldElement, FieldElement)>, Vec<HashDigest>);
+pub type TripleVec = (Vec<(FieldElement, FieldElement)>, Vec<HashDigest>);
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct CommitPhaseData {
   pub merkle: [Vec<HashDigest>; MAX_FRI_DEPTH],
   pub merkle_size: [usize; MAX_FRI_DEPTH],
@@ -21,6 +23,28 @@ pub struct CommitPhaseData {
   pub rs_codeword_mapping: [Vec<usize>; MAX_FRI_DEPTH],
 }
 
+impl ProofFormat for CommitPhaseData {
+  const KIND: ProofKind = ProofKind::CommitPhaseData;
+
+  fn validate(&self) -> Result<(), ProofFormatError> {
+    for i in 0..MAX_FRI_DEPTH {
+      let mapping = &self.rs_codeword_mapping[i];
+      let codeword_len = self.rs_codeword[i].len();
+      if !mapping.is_empty()
+        && (mapping.len() != codeword_len || mapping.iter().any(|&j| j >= codeword_len))
+      {
+        return Err(ProofFormatError::Malformed(
+          "rs codeword mapping out of range",
+        ));
+      }
+      if self.merkle_size[i] > self.merkle[i].len() {
+        return Err(ProofFormatError::Malformed("merkle size exceeds the tree"));
+      }
+    }
+    Ok(())
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct FieldElement64([Vec<FieldElement>; SLICE_NUMBER]);
 
@@ -72,7 +96,7 @@ impl FRIContext {
   }
 }
 /// Given private input, calculate the first oracle commitment
-pub fn request_init_commit(
+pub fn request_init_commit<H: MerkleHasher>(
   FRIContext {
     log_current_witness_size_per_slice,
     witness_bit_length_per_slice,
@@ -189,14 +213,14 @@ pub fn request_init_commit(
       data[0] = HashDigest::memcpy_from_field_elements([x, y]);
 
       data[1] = tmp_hash;
-      tmp_hash = my_hash(data);
+      tmp_hash = H::hash(data);
 
       j += 2;
     }
 
     leaf_hash[oracle_indicator][i] = tmp_hash;
   }
-  merkle_tree::create_tree(
+  merkle_tree::create_tree::<H>(
     &mut witness_merkle[oracle_indicator],
     &leaf_hash[oracle_indicator],
     true,
@@ -211,7 +235,7 @@ pub fn request_init_commit(
 }
 
 //Refactored
-pub fn request_init_value_with_merkle(
+pub fn request_init_value_with_merkle<H: MerkleHasher>(
   pow_0: usize,
   pow_1: usize,
   oracle_indicator: usize,
@@ -267,7 +291,7 @@ pub fn request_init_value_with_merkle(
   com_hhash[depth] = fri_ctx.witness_merkle[oracle_indicator][pos];
   let mut data = [HashDigest::default(); 2];
 
-  for i in 0..depth {
+  for hhash in com_hhash.iter_mut().take(depth) {
     let pow1 = pos ^ 1;
 
     if !fri_ctx.visited_init[oracle_indicator][pow1] {
@@ -285,9 +309,9 @@ pub fn request_init_value_with_merkle(
 
     data[0] = data0;
     data[1] = data1;
-    test_hash = my_hash(data);
+    test_hash = H::hash(data);
 
-    com_hhash[i] = fri_ctx.witness_merkle[oracle_indicator][pow1];
+    *hhash = fri_ctx.witness_merkle[oracle_indicator][pow1];
     pos /= 2;
     assert_eq!(test_hash, fri_ctx.witness_merkle[oracle_indicator][pos]);
   }
@@ -1,13 +1,13 @@
 use crate::PolyCommitContext;
-use infrastructure::my_hash::HashDigest;
+use infrastructure::my_hash::{HashDigest, MerkleHasher};
 
 use crate::vpd::fri::{request_init_commit, FRIContext};
 
 /// This will returns the merkle root
-pub fn vpd_prover_init(
+pub fn vpd_prover_init<H: MerkleHasher>(
   fri_ctx: &mut FRIContext,
   poly_ctx: &PolyCommitContext,
   log_input_length: usize,
 ) -> HashDigest {
-  request_init_commit(fri_ctx, poly_ctx, log_input_length, 0)
+  request_init_commit::<H>(fri_ctx, poly_ctx, log_input_length, 0)
 }
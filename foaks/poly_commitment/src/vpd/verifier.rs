@@ -4,14 +4,14 @@ use std::time::Instant;
 use global::constants::{FE_ZERO, LOG_SLICE_NUMBER, RS_CODE_RATE, SLICE_NUMBER};
 
 use infrastructure::merkle_tree::create_tree;
-use infrastructure::my_hash::my_hash;
-use infrastructure::my_hash::{self, HashDigest};
+use infrastructure::my_hash::{HashDigest, MerkleHasher};
+use infrastructure::transcript::Transcript;
 use prime_field::FieldElement;
 
 use crate::vpd::fri::FRIContext;
 use crate::LdtCommitment;
 
-pub fn verify_merkle(
+pub fn verify_merkle<H: MerkleHasher>(
   hash_digest: HashDigest,
   merkle_path: &Vec<HashDigest>,
   len: usize,
@@ -36,7 +36,7 @@ pub fn verify_merkle(
     }
     pow /= 2;
 
-    current_hash = my_hash(data);
+    current_hash = H::hash(data);
   }
 
   data = unsafe { mem::zeroed() };
@@ -46,7 +46,7 @@ pub fn verify_merkle(
   for value in values {
     data[0] = HashDigest::memcpy_from_field_elements([value.0, value.1]);
     data[1] = value_hash;
-    value_hash = my_hash::my_hash(data);
+    value_hash = H::hash(data);
   }
 
   hash_digest == current_hash && Some(&value_hash) == merkle_path.last()
@@ -54,7 +54,11 @@ pub fn verify_merkle(
 
 impl FRIContext {
   /// Given fold parameter r, return the root of the merkle tree of next level.
-  pub fn commit_phase_step(&mut self, r: FieldElement, slice_count: usize) -> HashDigest {
+  pub fn commit_phase_step<H: MerkleHasher>(
+    &mut self,
+    r: FieldElement,
+    slice_count: usize,
+  ) -> HashDigest {
     let nxt_witness_size = (1 << self.log_current_witness_size_per_slice) / 2;
     if self.cpd.rs_codeword[self.current_step_no].is_empty() {
       self.cpd.rs_codeword[self.current_step_no] = vec![FE_ZERO; nxt_witness_size * slice_count];
@@ -96,8 +100,7 @@ impl FRIContext {
       self.l_group[i] = self.l_group[i * 2];
     }
 
-    let mut tmp: Vec<FieldElement> =
-      vec![FieldElement::new_random(); nxt_witness_size * slice_count];
+    let mut tmp: Vec<FieldElement> = vec![FE_ZERO; nxt_witness_size * slice_count];
 
     self.cpd.rs_codeword_mapping[self.current_step_no] = vec![0; nxt_witness_size * slice_count];
 
@@ -143,13 +146,13 @@ impl FRIContext {
 
         data[0] = HashDigest::memcpy_from_field_elements(data_ele);
         data[1] = htmp;
-        htmp = my_hash(data);
+        htmp = H::hash(data);
       }
       *hash_val_item = htmp;
     }
 
     let current_step_no = self.cpd.merkle[self.current_step_no].clone();
-    create_tree(
+    create_tree::<H>(
       self.cpd.merkle[self.current_step_no].as_mut(),
       &hash_val,
       current_step_no.is_empty(),
@@ -167,7 +170,14 @@ impl FRIContext {
     self.cpd.rs_codeword[self.current_step_no - 1].clone()
   }
 
-  pub fn commit_phase(&mut self, log_length: usize, slice_count: usize) -> LdtCommitment {
+  /// Folding randomness of each step is squeezed from `transcript` after the
+  /// previous layer root has been absorbed.
+  pub fn commit_phase<H: MerkleHasher>(
+    &mut self,
+    log_length: usize,
+    slice_count: usize,
+    transcript: &mut Transcript<H>,
+  ) -> LdtCommitment {
     let t0 = Instant::now();
 
     let log_current_witness_size_per_slice_cp = self.log_current_witness_size_per_slice;
@@ -182,9 +192,10 @@ impl FRIContext {
     while codeword_size > 1 << RS_CODE_RATE {
       assert!(ptr < log_length + RS_CODE_RATE - LOG_SLICE_NUMBER);
 
-      randomness.push(FieldElement::new_random());
+      randomness.push(transcript.squeeze_field_element());
 
-      ret.push(self.commit_phase_step(randomness[ptr], slice_count));
+      ret.push(self.commit_phase_step::<H>(randomness[ptr], slice_count));
+      transcript.absorb_digest(ret[ptr]);
       codeword_size /= 2;
       ptr += 1;
     }
@@ -197,6 +208,7 @@ impl FRIContext {
       randomness,
       mx_depth: ptr,
     };
+    transcript.absorb_field_elements(&com.final_rs_code);
 
     println!("FRI commit time {}", t0.elapsed().as_secs_f64());
 
@@ -0,0 +1,54 @@
+//! The field of zkFOAKS as arkworks fields, so that constraint systems can be
+//! synthesized over it: [`Fp61`] is the base field of modulus `2^61 - 1` and
+//! [`Fp61x2`] its quadratic extension by `i^2 = -1`, which is the field of
+//! [`FieldElement`].
+
+use ark_ff::{Fp2, Fp2Config, Fp64, MontBackend, MontConfig, MontFp, PrimeField};
+
+use crate::{constants::MOD, error::NotBaseFieldError, FieldElement};
+
+#[derive(MontConfig)]
+#[modulus = "2305843009213693951"]
+#[generator = "37"]
+pub struct Fp61Config;
+
+pub type Fp61 = Fp64<MontBackend<Fp61Config, 1>>;
+
+/// `-1` is not a square as the modulus is `3 mod 4`
+pub struct Fp61x2Config;
+
+impl Fp2Config for Fp61x2Config {
+  type Fp = Fp61;
+
+  const NONRESIDUE: Fp61 = MontFp!("-1");
+
+  const FROBENIUS_COEFF_FP2_C1: &'static [Fp61] = &[MontFp!("1"), MontFp!("-1")];
+}
+
+pub type Fp61x2 = Fp2<Fp61x2Config>;
+
+fn to_u64(x: Fp61) -> u64 { x.into_bigint().0[0] }
+
+impl From<Fp61> for FieldElement {
+  fn from(x: Fp61) -> Self { Self::new(to_u64(x), 0) }
+}
+
+/// Parts of a [`FieldElement`] need not be reduced
+impl TryFrom<FieldElement> for Fp61 {
+  type Error = NotBaseFieldError;
+
+  fn try_from(x: FieldElement) -> Result<Self, Self::Error> {
+    if x.img % MOD != 0 {
+      return Err(NotBaseFieldError(x));
+    }
+    Ok(Self::from(x.real))
+  }
+}
+
+impl From<Fp61x2> for FieldElement {
+  fn from(x: Fp61x2) -> Self { Self::new(to_u64(x.c0), to_u64(x.c1)) }
+}
+
+impl From<FieldElement> for Fp61x2 {
+  fn from(x: FieldElement) -> Self { Self::new(Fp61::from(x.real), Fp61::from(x.img)) }
+}
@@ -0,0 +1,95 @@
+//! The fields of [`crate::ark`] for ark-ff 0.3, the version of `cs_build`
+//! and of the `R1CS` gadgets: [`Fp61`] is the base field of modulus
+//! `2^61 - 1` and [`Fp61x2`] its quadratic extension by `i^2 = -1`.
+
+use ark_ff_03::{
+  biginteger::BigInteger64 as BigInteger, field_new, FftParameters, Fp2, Fp2Parameters, Fp64,
+  Fp64Parameters, FpParameters, PrimeField,
+};
+
+use crate::{constants::MOD, error::NotBaseFieldError, FieldElement};
+
+pub struct Fp61Parameters;
+
+pub type Fp61 = Fp64<Fp61Parameters>;
+
+impl Fp64Parameters for Fp61Parameters {}
+
+impl FftParameters for Fp61Parameters {
+  type BigInt = BigInteger;
+
+  /// `MODULUS - 1 = 2 (2^60 - 1)`
+  const TWO_ADICITY: u32 = 1;
+
+  /// `-1`, in Montgomery form
+  const TWO_ADIC_ROOT_OF_UNITY: BigInteger = BigInteger([MOD - 8]);
+}
+
+/// Montgomery form is `x R` with `R = 2^64 = 8 mod 2^61 - 1`
+impl FpParameters for Fp61Parameters {
+  const MODULUS: BigInteger = BigInteger([MOD]);
+
+  const MODULUS_BITS: u32 = 61;
+
+  const CAPACITY: u32 = Self::MODULUS_BITS - 1;
+
+  const REPR_SHAVE_BITS: u32 = 3;
+
+  const R: BigInteger = BigInteger([8]);
+
+  const R2: BigInteger = BigInteger([64]);
+
+  /// `-MODULUS^-1 mod 2^64`
+  const INV: u64 = 0x2000000000000001;
+
+  /// `37`, in Montgomery form
+  const GENERATOR: BigInteger = BigInteger([37 * 8]);
+
+  const MODULUS_MINUS_ONE_DIV_TWO: BigInteger = BigInteger([MOD >> 1]);
+
+  const T: BigInteger = BigInteger([MOD >> 1]);
+
+  const T_MINUS_ONE_DIV_TWO: BigInteger = BigInteger([MOD >> 2]);
+}
+
+/// `-1` is not a square as the modulus is `3 mod 4`
+pub struct Fp61x2Parameters;
+
+impl Fp2Parameters for Fp61x2Parameters {
+  type Fp = Fp61;
+
+  const NONRESIDUE: Fp61 = field_new!(Fp61, "-1");
+
+  /// `1 + 4i`, of norm `17` which is not a square
+  const QUADRATIC_NONRESIDUE: (Fp61, Fp61) = (field_new!(Fp61, "1"), field_new!(Fp61, "4"));
+
+  const FROBENIUS_COEFF_FP2_C1: &'static [Fp61] = &[field_new!(Fp61, "1"), field_new!(Fp61, "-1")];
+}
+
+pub type Fp61x2 = Fp2<Fp61x2Parameters>;
+
+fn to_u64(x: Fp61) -> u64 { x.into_repr().0[0] }
+
+impl From<Fp61> for FieldElement {
+  fn from(x: Fp61) -> Self { Self::new(to_u64(x), 0) }
+}
+
+/// Parts of a [`FieldElement`] need not be reduced
+impl TryFrom<FieldElement> for Fp61 {
+  type Error = NotBaseFieldError;
+
+  fn try_from(x: FieldElement) -> Result<Self, Self::Error> {
+    if x.img % MOD != 0 {
+      return Err(NotBaseFieldError(x));
+    }
+    Ok(Self::from(x.real))
+  }
+}
+
+impl From<Fp61x2> for FieldElement {
+  fn from(x: Fp61x2) -> Self { Self::new(to_u64(x.c0), to_u64(x.c1)) }
+}
+
+impl From<FieldElement> for Fp61x2 {
+  fn from(x: FieldElement) -> Self { Self::new(Fp61::from(x.real), Fp61::from(x.img)) }
+}
@@ -1,3 +1,7 @@
+use std::fmt;
+
+use crate::FieldElement;
+
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum PrimeFieldError {
@@ -13,3 +17,39 @@ impl From<bincode::Error> for PrimeFieldError {
 pub enum RootOfUnityError {
   LogOrderTooHigh,
 }
+
+/// A serialized field element with a part not reduced modulo the prime
+#[derive(Debug)]
+pub struct NonCanonicalError {
+  pub real: u64,
+  pub img: u64,
+}
+
+impl fmt::Display for NonCanonicalError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "non canonical field element ({}, {})",
+      self.real, self.img
+    )
+  }
+}
+
+impl std::error::Error for NonCanonicalError {}
+
+/// An element with a non zero imaginary part where a base field element is
+/// expected
+#[derive(Debug)]
+pub struct NotBaseFieldError(pub FieldElement);
+
+impl fmt::Display for NotBaseFieldError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "not a base field element ({}, {})",
+      self.0.real, self.0.img
+    )
+  }
+}
+
+impl std::error::Error for NotBaseFieldError {}
@@ -1,17 +1,21 @@
 #![feature(bigint_helper_methods)]
+#[cfg(feature = "ark")]
+pub mod ark;
+#[cfg(feature = "ark03")]
+pub mod ark03;
 pub mod constants;
 pub mod error;
 pub mod ops;
 use constants::{MAX_ORDER, MOD};
 use ethnum::{i256, AsI256};
 use rand::Rng;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{
   arch::x86_64::{__m256i, _mm256_set_epi64x},
   mem::size_of_val,
 };
 
-use self::error::{PrimeFieldError, RootOfUnityError};
+use self::error::{NonCanonicalError, PrimeFieldError, RootOfUnityError};
 use rayon::prelude::*;
 
 pub struct FieldElementContext {
@@ -86,12 +90,46 @@ impl VecFieldElement {
   }
 }
 
-#[derive(Serialize, Default, Debug, PartialEq, Eq, Copy, Clone)]
+/// Serialized as `real` then `img`, both reduced below [`MOD`]. Deserializing
+/// an unreduced part fails.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Eq, Copy, Clone)]
+#[serde(into = "CanonicalFieldElement", try_from = "CanonicalFieldElement")]
 pub struct FieldElement {
   pub real: u64,
   pub img: u64,
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "FieldElement")]
+struct CanonicalFieldElement {
+  real: u64,
+  img: u64,
+}
+
+impl From<FieldElement> for CanonicalFieldElement {
+  fn from(x: FieldElement) -> Self {
+    Self {
+      real: x.real % MOD,
+      img: x.img % MOD,
+    }
+  }
+}
+
+impl TryFrom<CanonicalFieldElement> for FieldElement {
+  type Error = NonCanonicalError;
+
+  fn try_from(x: CanonicalFieldElement) -> Result<Self, Self::Error> {
+    if x.real < MOD && x.img < MOD {
+      Ok(Self::new(x.real, x.img))
+    } else {
+      Err(NonCanonicalError {
+        real: x.real,
+        img: x.img,
+      })
+    }
+  }
+}
+
 impl FieldElement {
   fn to_owned_bytes(self) -> Result<Vec<u8>, PrimeFieldError> { Ok(bincode::serialize(&self)?) }
 
@@ -115,6 +153,14 @@ impl FieldElement {
     Self::new(real, img)
   }
 
+  /// Uniform over the whole field, reproducible for a seeded `rng`
+  pub fn new_random_from_rng<R: Rng + ?Sized>(rng: &mut R) -> Self {
+    let real = rng.gen_range(0..MOD);
+    let img = rng.gen_range(0..MOD);
+
+    Self::new(real, img)
+  }
+
   pub fn new_random_real_only() -> Self {
     let real = rand::thread_rng().gen_range(0..(1 << 31) - 1) % MOD;
 
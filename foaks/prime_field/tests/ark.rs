@@ -0,0 +1,49 @@
+use ark_ff::{Field, PrimeField};
+use prime_field::{
+  ark::{Fp61, Fp61x2},
+  constants::MOD,
+  FieldElement,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn base_field_matches_the_modulus() {
+  assert_eq!(Fp61::MODULUS.0, [MOD]);
+  assert_eq!(Fp61::from(MOD), Fp61::from(0u64));
+  assert_eq!(-Fp61::from(1u64), Fp61::from(MOD - 1));
+  assert_eq!(
+    Fp61x2::new(Fp61::from(0u64), Fp61::from(1u64)).square(),
+    -Fp61x2::from(1u64)
+  );
+}
+
+#[test]
+fn conversions_round_trip() {
+  let mut rng = StdRng::seed_from_u64(7);
+  for _ in 0..100 {
+    let x = FieldElement::new_random_from_rng(&mut rng);
+    assert_eq!(FieldElement::from(Fp61x2::from(x)), x);
+
+    let real = FieldElement::new(x.real, 0);
+    assert_eq!(FieldElement::from(Fp61::try_from(real).unwrap()), real);
+    assert!(Fp61::try_from(x).is_err() || x.img == 0);
+  }
+  assert_eq!(
+    Fp61::try_from(FieldElement::new(MOD + 2, MOD)).unwrap(),
+    Fp61::from(2u64)
+  );
+}
+
+#[test]
+fn conversions_preserve_arithmetic() {
+  let mut rng = StdRng::seed_from_u64(11);
+  for _ in 0..100 {
+    let x = FieldElement::new_random_from_rng(&mut rng);
+    let y = FieldElement::new_random_from_rng(&mut rng);
+    let (ax, ay) = (Fp61x2::from(x), Fp61x2::from(y));
+    assert_eq!(Fp61x2::from(x + y), ax + ay);
+    assert_eq!(Fp61x2::from(x - y), ax - ay);
+    assert_eq!(Fp61x2::from(x * y), ax * ay);
+    assert_eq!(Fp61x2::from(x.inverse()), ax.inverse().unwrap());
+  }
+}
@@ -0,0 +1,63 @@
+use ark_ff_03::{FftField, Field, Fp2Parameters, FpParameters, One, PrimeField, SquareRootField};
+use prime_field::{
+  ark03::{Fp61, Fp61x2, Fp61x2Parameters},
+  constants::MOD,
+  FieldElement,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn base_field_matches_the_modulus() {
+  assert_eq!(<Fp61 as PrimeField>::Params::MODULUS.0, [MOD]);
+  assert_eq!(Fp61::from(MOD), Fp61::from(0u64));
+  assert_eq!(-Fp61::from(1u64), Fp61::from(MOD - 1));
+  assert_eq!(
+    Fp61x2::new(Fp61::from(0u64), Fp61::from(1u64)).square(),
+    -Fp61x2::from(1u64)
+  );
+}
+
+#[test]
+fn conversions_round_trip() {
+  let mut rng = StdRng::seed_from_u64(7);
+  for _ in 0..100 {
+    let x = FieldElement::new_random_from_rng(&mut rng);
+    assert_eq!(FieldElement::from(Fp61x2::from(x)), x);
+
+    let real = FieldElement::new(x.real, 0);
+    assert_eq!(FieldElement::from(Fp61::try_from(real).unwrap()), real);
+    assert!(Fp61::try_from(x).is_err() || x.img == 0);
+  }
+  assert_eq!(
+    Fp61::try_from(FieldElement::new(MOD + 2, MOD)).unwrap(),
+    Fp61::from(2u64)
+  );
+}
+
+#[test]
+fn conversions_preserve_arithmetic() {
+  let mut rng = StdRng::seed_from_u64(11);
+  for _ in 0..100 {
+    let x = FieldElement::new_random_from_rng(&mut rng);
+    let y = FieldElement::new_random_from_rng(&mut rng);
+    let (ax, ay) = (Fp61x2::from(x), Fp61x2::from(y));
+    assert_eq!(Fp61x2::from(x + y), ax + ay);
+    assert_eq!(Fp61x2::from(x - y), ax - ay);
+    assert_eq!(Fp61x2::from(x * y), ax * ay);
+    assert_eq!(Fp61x2::from(x.inverse()), ax.inverse().unwrap());
+  }
+}
+
+#[test]
+fn generator_and_non_residues() {
+  let p = MOD - 1;
+  for factor in [2, 3, 5, 7, 11, 13, 31, 41, 61, 151, 331, 1321] {
+    assert_ne!(
+      Fp61::multiplicative_generator().pow([p / factor]),
+      Fp61::one()
+    );
+  }
+  let (c0, c1) = <Fp61x2Parameters as Fp2Parameters>::QUADRATIC_NONRESIDUE;
+  assert!(Fp61x2::new(c0, c1).legendre().is_qnr());
+  assert_eq!(Fp61::two_adic_root_of_unity(), -Fp61::one());
+}
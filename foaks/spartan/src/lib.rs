@@ -0,0 +1,293 @@
+//! Spartan-style proofs of satisfiability of rank-one constraint systems over
+//! the field of zkFOAKS.
+//!
+//! The prover commits to the witness with `LinearPC`, then a first sumcheck
+//! reduces `Σ_x eq(τ, x) (Az(x) Bz(x) - Cz(x)) = 0` to claims on `Az`, `Bz`
+//! and `Cz` at a random row `r_x`. A second sumcheck reduces a random
+//! combination of those claims to a claim on `z` at a random column `r_y`,
+//! which the verifier settles with the public instance and an opening of the
+//! witness.
+//!
+//! `z` is padded to the witness followed by the instance, both padded to the
+//! same power of two `m`. The verifier evaluates the multilinear extensions of
+//! the public matrices itself, in time linear in their number of non-zero
+//! entries. Proofs are not zero knowledge.
+
+use global::constants::{FE_REAL_ONE, FE_ZERO};
+use infrastructure::{
+  my_hash::{HashDigest, MerkleHasher},
+  proof_format::{ProofFormat, ProofFormatError, ProofKind},
+  transcript::Transcript,
+};
+use linear_code::parameter::{CodeParams, DEFAULT_EXPANDER_SEED};
+use linear_gkr::sumcheck::{self, SumOfProducts, SumcheckProof};
+use linear_pc::{prover::LinearPcProver, verifier::LinearPcVerifier, OpeningProof};
+use prime_field::FieldElement;
+use serde::{Deserialize, Serialize};
+
+pub mod r1cs;
+pub mod r1cs_file;
+
+use r1cs::{mul_vector, Matrix, R1cs, R1csError};
+
+/// Smallest witness commitment, the opening needs rows of at least `2^7`
+/// elements with the default column size
+pub const MIN_COMMIT_SIZE: usize = 1 << 14;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpartanProof {
+  pub witness_root: HashDigest,
+  /// Sumcheck over the constraints
+  pub outer: SumcheckProof,
+  /// `Az`, `Bz` and `Cz` at the row the outer sumcheck ends on
+  pub abc_evals: [FieldElement; 3],
+  /// Sumcheck over the columns of `z`
+  pub inner: SumcheckProof,
+  /// The witness at the column the inner sumcheck ends on
+  pub witness_eval: FieldElement,
+  pub witness_opening: OpeningProof,
+}
+
+impl ProofFormat for SpartanProof {
+  const KIND: ProofKind = ProofKind::Spartan;
+
+  fn validate(&self) -> Result<(), ProofFormatError> {
+    self.outer.validate()?;
+    self.inner.validate()?;
+    self.witness_opening.validate()
+  }
+}
+
+/// Sizes of the padded constraint system
+struct Shape {
+  log_rows: usize,
+  /// `z` has `2m` entries
+  log_m: usize,
+  commit_size: usize,
+}
+
+impl Shape {
+  fn new(r1cs: &R1cs) -> Self {
+    let rows = r1cs.num_constraints.next_power_of_two();
+    let m = r1cs
+      .num_instance_variables
+      .max(r1cs.num_witness_variables)
+      .next_power_of_two();
+    Self {
+      log_rows: rows.trailing_zeros() as usize,
+      log_m: m.trailing_zeros() as usize,
+      commit_size: m.max(MIN_COMMIT_SIZE),
+    }
+  }
+
+  fn m(&self) -> usize { 1 << self.log_m }
+
+  /// Column of `z` in the padded layout
+  fn column(&self, r1cs: &R1cs, j: usize) -> usize {
+    if j < r1cs.num_instance_variables {
+      self.m() + j
+    } else {
+      j - r1cs.num_instance_variables
+    }
+  }
+
+  /// Point of the witness commitment for `point` in the sumcheck order, the
+  /// commitment is opened from the most significant bit
+  fn commit_point(&self, point: &[FieldElement]) -> Vec<FieldElement> {
+    let extra = (self.commit_size / self.m()).trailing_zeros() as usize;
+    let mut commit_point = vec![FE_ZERO; extra];
+    commit_point.extend(point.iter().rev());
+    commit_point
+  }
+}
+
+/// `eq(point, x)` for every `x`, bit `k` of `x` matching `point[k]`
+fn eq_table(point: &[FieldElement]) -> Vec<FieldElement> {
+  let mut table = vec![FE_REAL_ONE];
+  for &r in point {
+    let high: Vec<_> = table.iter().map(|&t| t * r).collect();
+    for t in table.iter_mut() {
+      *t = *t * (FE_REAL_ONE - r);
+    }
+    table.extend(high);
+  }
+  table
+}
+
+fn eq_eval(a: &[FieldElement], b: &[FieldElement]) -> FieldElement {
+  a.iter().zip(b).fold(FE_REAL_ONE, |acc, (&x, &y)| {
+    acc * (x * y + (FE_REAL_ONE - x) * (FE_REAL_ONE - y))
+  })
+}
+
+fn dot(a: &[FieldElement], b: &[FieldElement]) -> FieldElement {
+  a.iter().zip(b).fold(FE_ZERO, |acc, (&x, &y)| acc + x * y)
+}
+
+/// `Σ_i weights[i] M(i, y)` for every column `y` of the padded `z`
+fn bind_rows(
+  r1cs: &R1cs,
+  shape: &Shape,
+  matrices: [(&Matrix, FieldElement); 3],
+  row_weights: &[FieldElement],
+) -> Vec<FieldElement> {
+  let mut columns = vec![FE_ZERO; 2 * shape.m()];
+  for (matrix, scale) in matrices {
+    for (row, weight) in matrix.iter().zip(row_weights) {
+      for &(coefficient, j) in row {
+        let y = shape.column(r1cs, j);
+        columns[y] = columns[y] + scale * *weight * coefficient;
+      }
+    }
+  }
+  columns
+}
+
+fn absorb_shape<H: MerkleHasher>(transcript: &mut Transcript<H>, r1cs: &R1cs) {
+  transcript.absorb_field_elements(&[
+    FieldElement::from_real(r1cs.num_constraints as u64),
+    FieldElement::from_real(r1cs.num_instance_variables as u64),
+    FieldElement::from_real(r1cs.num_witness_variables as u64),
+  ]);
+}
+
+/// Proves that `witness` satisfies `r1cs` for the public `instance`, which
+/// starts with the constant one. The witness is committed with `params` and
+/// the default expander seed.
+pub fn prove<H: MerkleHasher>(
+  r1cs: &R1cs,
+  instance: &[FieldElement],
+  witness: &[FieldElement],
+  params: CodeParams,
+) -> Result<SpartanProof, R1csError> {
+  r1cs.validate()?;
+  r1cs.check_assignment(instance, witness)?;
+  let shape = Shape::new(r1cs);
+
+  let mut committed = witness.to_vec();
+  committed.resize(shape.commit_size, FE_ZERO);
+  let mut pc_prover = LinearPcProver::<H>::init(shape.commit_size, params);
+  let witness_root = pc_prover.commit(&committed);
+
+  let mut transcript = Transcript::<H>::new();
+  absorb_shape(&mut transcript, r1cs);
+  transcript.absorb_field_elements(instance);
+  transcript.absorb_digest(witness_root);
+
+  // outer sumcheck over the constraints
+  let tau = transcript.squeeze_field_elements(shape.log_rows);
+  let z = [instance, witness].concat();
+  let mut outer = SumOfProducts::new(shape.log_rows);
+  let eq = outer.add_mle(eq_table(&tau));
+  let [az, bz, cz] = [&r1cs.a, &r1cs.b, &r1cs.c].map(|m| {
+    let mut values = mul_vector(m, &z);
+    values.resize(1 << shape.log_rows, FE_ZERO);
+    outer.add_mle(values)
+  });
+  outer.add_product(FE_REAL_ONE, &[eq, az, bz]);
+  outer.add_product(-FE_REAL_ONE, &[eq, cz]);
+  let (outer, r_x, outer_evals) = sumcheck::prove(outer, &mut transcript);
+  let abc_evals = [outer_evals[az], outer_evals[bz], outer_evals[cz]];
+  transcript.absorb_field_elements(&abc_evals);
+
+  // inner sumcheck over the columns of z
+  let [r_a, r_b, r_c] = [0; 3].map(|_| transcript.squeeze_field_element());
+  let mut padded_z = committed[..shape.m()].to_vec();
+  padded_z.extend(instance);
+  padded_z.resize(2 * shape.m(), FE_ZERO);
+  let columns = bind_rows(
+    r1cs,
+    &shape,
+    [(&r1cs.a, r_a), (&r1cs.b, r_b), (&r1cs.c, r_c)],
+    &eq_table(&r_x),
+  );
+  let mut inner = SumOfProducts::new(shape.log_m + 1);
+  let m = inner.add_mle(columns);
+  let z = inner.add_mle(padded_z);
+  inner.add_product(FE_REAL_ONE, &[m, z]);
+  let (inner, r_y, _) = sumcheck::prove(inner, &mut transcript);
+
+  let witness_eval = dot(&eq_table(&r_y[..shape.log_m]), &committed[..shape.m()]);
+  transcript.absorb_field_element(witness_eval);
+  let (value, witness_opening) =
+    pc_prover.open_multi(&shape.commit_point(&r_y[..shape.log_m]), shape.commit_size);
+  debug_assert_eq!(value, witness_eval);
+
+  Ok(SpartanProof {
+    witness_root,
+    outer,
+    abc_evals,
+    inner,
+    witness_eval,
+    witness_opening,
+  })
+}
+
+/// Checks `proof` that `r1cs` is satisfiable for `instance`, the commitment
+/// must use the same `params` as the prover
+pub fn verify<H: MerkleHasher>(
+  r1cs: &R1cs,
+  instance: &[FieldElement],
+  proof: &SpartanProof,
+  params: CodeParams,
+) -> bool {
+  if r1cs.validate().is_err() || instance.len() != r1cs.num_instance_variables {
+    eprintln!("Verification fail, malformed instance");
+    return false;
+  }
+  if instance[0] != FE_REAL_ONE {
+    eprintln!("Verification fail, instance constant");
+    return false;
+  }
+  let shape = Shape::new(r1cs);
+
+  let mut transcript = Transcript::<H>::new();
+  absorb_shape(&mut transcript, r1cs);
+  transcript.absorb_field_elements(instance);
+  transcript.absorb_digest(proof.witness_root);
+
+  let tau = transcript.squeeze_field_elements(shape.log_rows);
+  let outer = match sumcheck::verify(shape.log_rows, 3, FE_ZERO, &proof.outer, &mut transcript) {
+    Some(claim) => claim,
+    None => return false,
+  };
+  let [a, b, c] = proof.abc_evals;
+  if outer.value != eq_eval(&tau, &outer.point) * (a * b - c) {
+    eprintln!("Verification fail, outer sumcheck");
+    return false;
+  }
+  transcript.absorb_field_elements(&proof.abc_evals);
+
+  let [r_a, r_b, r_c] = [0; 3].map(|_| transcript.squeeze_field_element());
+  let inner_sum = r_a * a + r_b * b + r_c * c;
+  let inner = match sumcheck::verify(shape.log_m + 1, 2, inner_sum, &proof.inner, &mut transcript) {
+    Some(claim) => claim,
+    None => return false,
+  };
+  let r_y = &inner.point;
+  let columns = bind_rows(
+    r1cs,
+    &shape,
+    [(&r1cs.a, r_a), (&r1cs.b, r_b), (&r1cs.c, r_c)],
+    &eq_table(&outer.point),
+  );
+  let matrix_eval = dot(&columns, &eq_table(r_y));
+  let top = r_y[shape.log_m];
+  let instance_eval = dot(&eq_table(&r_y[..shape.log_m]), instance);
+  let z_eval = (FE_REAL_ONE - top) * proof.witness_eval + top * instance_eval;
+  if inner.value != matrix_eval * z_eval {
+    eprintln!("Verification fail, inner sumcheck");
+    return false;
+  }
+  transcript.absorb_field_element(proof.witness_eval);
+
+  let mut pc_verifier =
+    LinearPcVerifier::<H>::from_seed(shape.commit_size, params, &DEFAULT_EXPANDER_SEED);
+  pc_verifier.verify_multi(
+    proof.witness_root,
+    &shape.commit_point(&r_y[..shape.log_m]),
+    shape.commit_size,
+    proof.witness_eval,
+    &proof.witness_opening,
+  )
+}
@@ -0,0 +1,109 @@
+use global::constants::{FE_REAL_ONE, FE_ZERO};
+use prime_field::FieldElement;
+
+/// Sparse matrix as in arkworks' `ConstraintMatrices`, a row of
+/// `(coefficient, column)` per constraint
+pub type Matrix = Vec<Vec<(FieldElement, usize)>>;
+
+/// Rank-one constraint system `Az ∘ Bz = Cz` laid out as arkworks does: `z`
+/// is the instance, starting with the constant one, followed by the witness
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct R1cs {
+  pub num_instance_variables: usize,
+  pub num_witness_variables: usize,
+  pub num_constraints: usize,
+  pub a: Matrix,
+  pub b: Matrix,
+  pub c: Matrix,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum R1csError {
+  /// A matrix without one row per constraint
+  RowCount {
+    matrix: char,
+  },
+  ColumnOutOfRange {
+    matrix: char,
+    row: usize,
+    column: usize,
+  },
+  /// No instance variable for the constant one
+  MissingConstant,
+  InstanceLength,
+  WitnessLength,
+  /// The instance does not start with the constant one
+  InstanceConstant,
+}
+
+impl R1cs {
+  pub fn num_variables(&self) -> usize { self.num_instance_variables + self.num_witness_variables }
+
+  pub fn matrices(&self) -> [(char, &Matrix); 3] {
+    [('A', &self.a), ('B', &self.b), ('C', &self.c)]
+  }
+
+  pub fn validate(&self) -> Result<(), R1csError> {
+    if self.num_instance_variables == 0 {
+      return Err(R1csError::MissingConstant);
+    }
+    for (matrix, rows) in self.matrices() {
+      if rows.len() != self.num_constraints {
+        return Err(R1csError::RowCount { matrix });
+      }
+      for (row, terms) in rows.iter().enumerate() {
+        if let Some(&(_, column)) = terms.iter().find(|(_, j)| *j >= self.num_variables()) {
+          return Err(R1csError::ColumnOutOfRange {
+            matrix,
+            row,
+            column,
+          });
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Checks the lengths of `instance` and `witness` and that the instance
+  /// starts with the constant one
+  pub fn check_assignment(
+    &self,
+    instance: &[FieldElement],
+    witness: &[FieldElement],
+  ) -> Result<(), R1csError> {
+    if instance.len() != self.num_instance_variables {
+      return Err(R1csError::InstanceLength);
+    }
+    if witness.len() != self.num_witness_variables {
+      return Err(R1csError::WitnessLength);
+    }
+    if instance[0] != FE_REAL_ONE {
+      return Err(R1csError::InstanceConstant);
+    }
+    Ok(())
+  }
+
+  /// First constraint the assignment violates, the assignment must pass
+  /// [`R1cs::check_assignment`]
+  pub fn unsatisfied_constraint(
+    &self,
+    instance: &[FieldElement],
+    witness: &[FieldElement],
+  ) -> Option<usize> {
+    let z = [instance, witness].concat();
+    let [az, bz, cz] = [&self.a, &self.b, &self.c].map(|m| mul_vector(m, &z));
+    (0..self.num_constraints).find(|&i| az[i] * bz[i] != cz[i])
+  }
+}
+
+/// `m z`, one value per row
+pub(crate) fn mul_vector(m: &Matrix, z: &[FieldElement]) -> Vec<FieldElement> {
+  m.iter()
+    .map(|row| {
+      row
+        .iter()
+        .fold(FE_ZERO, |acc, &(coefficient, j)| acc + coefficient * z[j])
+    })
+    .collect()
+}
@@ -0,0 +1,185 @@
+//! Text format of rank-one constraint systems and their assignments, written
+//! by `cs_build` for the constraint systems it synthesizes.
+//!
+//! A constraint system file is whitespace separated integers: the number of
+//! constraints, of instance variables (counting the constant one) and of
+//! witness variables, then the rows of `A`, of `B` and of `C`. A row is its
+//! number of terms `k` followed by `k` pairs `column coefficient`, with the
+//! columns of [`R1cs`].
+//!
+//! An assignment file is the number of instance variables followed by their
+//! values, then the number of witness variables followed by theirs.
+//!
+//! Coefficients and values are elements of the base field, written as signed
+//! decimal integers of absolute value below its modulus `2^61 - 1`.
+
+use std::{fmt::Write, fs, path::Path};
+
+use prime_field::{constants::MOD, FieldElement};
+
+use crate::r1cs::{Matrix, R1cs, R1csError};
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum R1csFileError {
+  Io(std::io::Error),
+  UnexpectedEnd,
+  InvalidNumber(String),
+  TrailingData,
+  Invalid(R1csError),
+  /// A coefficient or value outside of the base field, which the format has
+  /// no encoding for
+  NotBaseField(FieldElement),
+}
+
+impl From<std::io::Error> for R1csFileError {
+  fn from(err: std::io::Error) -> Self { Self::Io(err) }
+}
+
+impl From<R1csError> for R1csFileError {
+  fn from(err: R1csError) -> Self { Self::Invalid(err) }
+}
+
+struct Tokens<'a>(std::str::SplitAsciiWhitespace<'a>);
+
+impl<'a> Tokens<'a> {
+  fn new(text: &'a str) -> Self { Self(text.split_ascii_whitespace()) }
+
+  fn next_token(&mut self) -> Result<&'a str, R1csFileError> {
+    self.0.next().ok_or(R1csFileError::UnexpectedEnd)
+  }
+
+  fn next_usize(&mut self) -> Result<usize, R1csFileError> {
+    let token = self.next_token()?;
+    token
+      .parse()
+      .map_err(|_| R1csFileError::InvalidNumber(token.to_string()))
+  }
+
+  fn next_element(&mut self) -> Result<FieldElement, R1csFileError> {
+    let token = self.next_token()?;
+    let invalid = || R1csFileError::InvalidNumber(token.to_string());
+    let (negative, digits) = match token.strip_prefix('-') {
+      Some(digits) => (true, digits),
+      None => (false, token),
+    };
+    let magnitude: u64 = digits.parse().map_err(|_| invalid())?;
+    if magnitude >= MOD {
+      return Err(invalid());
+    }
+    let x = FieldElement::from_real(magnitude);
+    Ok(if negative { -x } else { x })
+  }
+
+  fn next_elements(&mut self) -> Result<Vec<FieldElement>, R1csFileError> {
+    let n = self.next_usize()?;
+    (0..n).map(|_| self.next_element()).collect()
+  }
+
+  fn finish(mut self) -> Result<(), R1csFileError> {
+    match self.0.next() {
+      Some(_) => Err(R1csFileError::TrailingData),
+      None => Ok(()),
+    }
+  }
+}
+
+fn read_matrix(tokens: &mut Tokens, num_constraints: usize) -> Result<Matrix, R1csFileError> {
+  (0..num_constraints)
+    .map(|_| {
+      let k = tokens.next_usize()?;
+      (0..k)
+        .map(|_| {
+          let column = tokens.next_usize()?;
+          Ok((tokens.next_element()?, column))
+        })
+        .collect()
+    })
+    .collect()
+}
+
+pub fn read_r1cs(text: &str) -> Result<R1cs, R1csFileError> {
+  let mut tokens = Tokens::new(text);
+  let num_constraints = tokens.next_usize()?;
+  let num_instance_variables = tokens.next_usize()?;
+  let num_witness_variables = tokens.next_usize()?;
+  let a = read_matrix(&mut tokens, num_constraints)?;
+  let b = read_matrix(&mut tokens, num_constraints)?;
+  let c = read_matrix(&mut tokens, num_constraints)?;
+  tokens.finish()?;
+
+  let r1cs = R1cs {
+    num_instance_variables,
+    num_witness_variables,
+    num_constraints,
+    a,
+    b,
+    c,
+  };
+  r1cs.validate()?;
+  Ok(r1cs)
+}
+
+/// Reads the instance, starting with the constant one, and the witness
+pub fn read_assignment(
+  text: &str,
+) -> Result<(Vec<FieldElement>, Vec<FieldElement>), R1csFileError> {
+  let mut tokens = Tokens::new(text);
+  let instance = tokens.next_elements()?;
+  let witness = tokens.next_elements()?;
+  tokens.finish()?;
+  Ok((instance, witness))
+}
+
+pub fn load_r1cs(path: &Path) -> Result<R1cs, R1csFileError> {
+  read_r1cs(&fs::read_to_string(path)?)
+}
+
+pub fn load_assignment(
+  path: &Path,
+) -> Result<(Vec<FieldElement>, Vec<FieldElement>), R1csFileError> {
+  read_assignment(&fs::read_to_string(path)?)
+}
+
+/// Base field elements are written as their canonical representative
+fn write_element(out: &mut String, x: FieldElement) -> Result<(), R1csFileError> {
+  if x.img != 0 {
+    return Err(R1csFileError::NotBaseField(x));
+  }
+  write!(out, " {}", x.real).expect("writing to a string");
+  Ok(())
+}
+
+pub fn write_r1cs(r1cs: &R1cs) -> Result<String, R1csFileError> {
+  r1cs.validate()?;
+  let mut out = format!(
+    "{} {} {}\n",
+    r1cs.num_constraints, r1cs.num_instance_variables, r1cs.num_witness_variables
+  );
+  for (_, rows) in r1cs.matrices() {
+    for row in rows {
+      write!(out, "{}", row.len()).expect("writing to a string");
+      for &(coefficient, column) in row {
+        write!(out, " {}", column).expect("writing to a string");
+        write_element(&mut out, coefficient)?;
+      }
+      out.push('\n');
+    }
+  }
+  Ok(out)
+}
+
+pub fn write_assignment(
+  instance: &[FieldElement],
+  witness: &[FieldElement],
+) -> Result<String, R1csFileError> {
+  let mut out = String::new();
+  for values in [instance, witness] {
+    write!(out, "{}", values.len()).expect("writing to a string");
+    for &x in values {
+      write_element(&mut out, x)?;
+    }
+    out.push('\n');
+  }
+  Ok(out)
+}
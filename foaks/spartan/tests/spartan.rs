@@ -0,0 +1,102 @@
+use infrastructure::{my_hash::Sha256Hasher, proof_format::ProofFormat};
+use linear_code::parameter::CodeParams;
+use prime_field::FieldElement;
+use spartan::{
+  prove,
+  r1cs::{R1cs, R1csError},
+  r1cs_file::{read_assignment, read_r1cs, write_assignment, write_r1cs, R1csFileError},
+  verify, SpartanProof,
+};
+
+/// `x^3 + x + 5 = out` over `1, out, x, x^2, x^3, x^3 + x`
+const CUBIC: &str = include_str!("data/cubic_r1cs.txt");
+const CUBIC_ASSIGNMENT: &str = include_str!("data/cubic_assignment.txt");
+
+fn cubic() -> (R1cs, Vec<FieldElement>, Vec<FieldElement>) {
+  let (instance, witness) = read_assignment(CUBIC_ASSIGNMENT).unwrap();
+  (read_r1cs(CUBIC).unwrap(), instance, witness)
+}
+
+fn prove_cubic(witness: &[FieldElement]) -> SpartanProof {
+  let (r1cs, instance, _) = cubic();
+  prove::<Sha256Hasher>(&r1cs, &instance, witness, CodeParams::default()).unwrap()
+}
+
+fn verify_cubic(instance: &[FieldElement], proof: &SpartanProof) -> bool {
+  let (r1cs, ..) = cubic();
+  verify::<Sha256Hasher>(&r1cs, instance, proof, CodeParams::default())
+}
+
+#[test]
+fn satisfied_systems_verify() {
+  let (r1cs, instance, witness) = cubic();
+  assert_eq!(r1cs.unsatisfied_constraint(&instance, &witness), None);
+  let proof = prove_cubic(&witness);
+  assert!(verify_cubic(&instance, &proof));
+
+  let decoded = SpartanProof::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+  assert!(verify_cubic(&instance, &decoded));
+}
+
+#[test]
+fn unsatisfied_systems_are_rejected() {
+  let (r1cs, instance, mut witness) = cubic();
+  let proof = prove_cubic(&witness);
+  let mut wrong_instance = instance.clone();
+  wrong_instance[1] = FieldElement::from_real(36);
+  assert!(!verify_cubic(&wrong_instance, &proof));
+
+  witness[1] = FieldElement::from_real(10);
+  assert_eq!(r1cs.unsatisfied_constraint(&instance, &witness), Some(0));
+  assert!(!verify_cubic(&instance, &prove_cubic(&witness)));
+
+  let mut tampered = proof;
+  tampered.witness_eval = tampered.witness_eval + FieldElement::real_one();
+  assert!(!verify_cubic(&instance, &tampered));
+}
+
+#[test]
+fn assignments_are_checked() {
+  let (r1cs, mut instance, witness) = cubic();
+  let params = CodeParams::default();
+  assert!(matches!(
+    prove::<Sha256Hasher>(&r1cs, &instance, &witness[1..], params),
+    Err(R1csError::WitnessLength)
+  ));
+  instance[0] = FieldElement::from_real(2);
+  assert!(matches!(
+    prove::<Sha256Hasher>(&r1cs, &instance, &witness, params),
+    Err(R1csError::InstanceConstant)
+  ));
+}
+
+#[test]
+fn r1cs_files_round_trip() {
+  let (r1cs, instance, witness) = cubic();
+  assert_eq!(write_r1cs(&r1cs).unwrap(), CUBIC);
+  assert_eq!(
+    write_assignment(&instance, &witness).unwrap(),
+    CUBIC_ASSIGNMENT
+  );
+
+  let negated = read_r1cs("1 1 1\n1 1 -1\n1 0 1\n1 1 -1\n").unwrap();
+  assert_eq!(negated.a[0][0].0, -FieldElement::real_one());
+  assert_eq!(read_r1cs(&write_r1cs(&negated).unwrap()).unwrap(), negated);
+
+  assert!(matches!(
+    read_r1cs("1 1 1\n1 2 1\n1 0 1\n1 0 1\n"),
+    Err(R1csFileError::Invalid(R1csError::ColumnOutOfRange {
+      matrix: 'A',
+      row: 0,
+      column: 2
+    }))
+  ));
+  assert!(matches!(
+    read_r1cs("1 1 1\n0\n0\n0\n7"),
+    Err(R1csFileError::TrailingData)
+  ));
+  assert!(matches!(
+    read_assignment("1 2305843009213693951\n0"),
+    Err(R1csFileError::InvalidNumber(_))
+  ));
+}
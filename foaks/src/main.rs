@@ -1,5 +1,10 @@
+use infrastructure::{
+  my_hash::{Blake3Hasher, Keccak256Hasher, MerkleHasher, Sha256Hasher},
+  poseidon::PoseidonHasher,
+};
+use linear_code::parameter::{CodeParams, DEFAULT_EXPANDER_SEED};
 use linear_gkr::verifier::generate_randomness;
-use linear_pc::LinearPC;
+use linear_pc::{prover::LinearPcProver, verifier::LinearPcVerifier};
 use prime_field::FieldElement;
 use std::{env, time::Instant};
 
@@ -14,34 +19,55 @@ fn main() -> Result<(), MainError> {
     None => return Err(MainError::NoNumberProvided),
   };
 
+  let multi = args
+    .get(3)
+    .map(|arg| arg.contains("multi"))
+    .unwrap_or(false);
+
+  let params = match args.get(5) {
+    Some(name) => CodeParams::preset(name).ok_or(MainError::UnknownCodeParams)?,
+    None => CodeParams::default(),
+  };
+
+  let result = match args.get(4).map(String::as_str) {
+    None | Some("sha256") => run::<Sha256Hasher>(lg_n, multi, params),
+    Some("blake3") => run::<Blake3Hasher>(lg_n, multi, params),
+    Some("keccak256") => run::<Keccak256Hasher>(lg_n, multi, params),
+    Some("poseidon") => run::<PoseidonHasher>(lg_n, multi, params),
+    Some(_) => return Err(MainError::UnknownHasher),
+  };
+  println!("{}", if result { "succ" } else { "fail" });
+
+  Ok(())
+}
+
+fn run<H: MerkleHasher>(lg_n: usize, multi: bool, params: CodeParams) -> bool {
   let n = 1 << lg_n;
 
-  let mut linear_pc = LinearPC::init(n); // Declara como mutable
+  let mut prover = LinearPcProver::<H>::init(n, params);
+  let mut verifier = LinearPcVerifier::<H>::from_seed(n, params, &DEFAULT_EXPANDER_SEED);
 
   let coefs = generate_randomness(n);
 
   let commit_t0 = Instant::now();
-  let h = linear_pc.commit(&coefs);
+  let h = prover.commit(&coefs);
   let commit_time_diff = commit_t0.elapsed();
 
   let open_t0 = Instant::now();
-  let multi = args
-    .get(3)
-    .map(|arg| arg.contains("multi"))
-    .unwrap_or(false);
-
   let result = if multi {
     let r = generate_randomness(lg_n);
-    linear_pc.open_and_verify_multi(&r, n, h)
+    let (value, proof) = prover.open_multi(&r, n);
+    verifier.verify_multi(h, &r, n, value, &proof)
   } else {
-    linear_pc.open_and_verify(FieldElement::new_random(), n, h)
+    let x = FieldElement::new_random();
+    let (value, proof) = prover.open(x, n);
+    verifier.verify(h, x, n, value, &proof)
   };
 
   let open_time_diff = open_t0.elapsed();
 
   println!("Commit time: {}", commit_time_diff.as_secs_f64());
   println!("Open time: {}", open_time_diff.as_secs_f64());
-  println!("{}", if result.1 { "succ" } else { "fail" });
 
-  Ok(())
+  result
 }
@@ -5,6 +5,8 @@ pub enum MainError {
   BelowThreshold,
   ParseParamsError,
   NoNumberProvided,
+  UnknownHasher,
+  UnknownCodeParams,
 }
 
 pub fn parse_number(input: &str) -> Result<usize, MainError> {